@@ -1,26 +1,35 @@
-use once_cell::sync::Lazy;
+use crate::{
+    ClockDomain, ClockDomains, CompiledProgram, EventDrivenState, ExternalPorts, Faults, LaneValue,
+    ScopeId, Scopes, SimulationBackend, ToggleCounter, WireSetter, VALUE_X, VALUE_Z,
+};
 use std::any::Any;
+use std::cell::{Cell, Ref, RefCell, UnsafeCell};
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::ops::Range;
+use std::rc::Rc;
 
 pub type WireValue = u8;
 pub type LatencyValue = u16;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq)]
 pub struct Wire(pub usize);
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq)]
 pub struct Reg(pub usize);
 
-enum ExecuteSegment {
+pub(crate) enum ExecuteSegment {
     Gates(Range<usize>),
     Externals(Range<usize>),
 }
 
 impl Debug for Wire {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!("{}", self.get()))
+        match self.get() {
+            VALUE_X => f.write_str("X"),
+            VALUE_Z => f.write_str("Z"),
+            value => f.write_str(&format!("{value}")),
+        }
     }
 }
 impl Debug for Reg {
@@ -39,135 +48,592 @@ impl Debug for ExecuteSegment {
     }
 }
 
-static mut WIRES: Vec<WireValue> = Vec::new();
-static mut LATENCIES: Vec<LatencyValue> = Vec::new();
-static mut GATES_MAP: Lazy<HashMap<(usize, usize), Wire>> = Lazy::new(HashMap::new); // (a, b) -> out
-static mut GATES: Vec<Gate> = Vec::new();
-static mut EXTERNALS: Vec<Box<dyn External>> = Vec::new();
-static mut REGS: Vec<RegValue> = Vec::new();
-static mut EXECUTE_SEGMENTS: Vec<ExecuteSegment> = Vec::new();
+pub(crate) const WIRE_0: usize = 0;
+pub(crate) const WIRE_1: usize = 1;
+
+/// Owns a whole netlist: wires, gates, regs, externals and their execution order.
+///
+/// `Wire`/`Reg` handles and the free functions (`input()`, `nand()`, `simulate()`...) always
+/// work on the current circuit of this thread, which is a per-thread default circuit unless
+/// another one is made current with `Circuit::enter`.
+pub struct Circuit {
+    pub(crate) wires: Vec<WireValue>,
+    pub(crate) latencies: Vec<LatencyValue>,
+    pub(crate) gates_map: HashMap<(usize, usize), Wire>, // (a, b) -> out
+    pub(crate) gates: Vec<Gate>,
+    pub(crate) externals: Vec<Rc<RefCell<dyn External>>>, // shared with `ExternalHandle`s
+    pub(crate) regs: Vec<RegValue>,
+    pub(crate) execute_segments: Vec<ExecuteSegment>,
+    pub(crate) lanes: Vec<LaneValue>, // bit-parallel wire values, see `simulator::lanes`
+    pub(crate) lane_phases: Vec<u32>, // clock domain phases of the lanes
+    pub(crate) backend: SimulationBackend,
+    pub(crate) compiled: Option<CompiledProgram>,
+    pub(crate) event_driven: Option<EventDrivenState>,
+    pub(crate) set_by: Vec<WireSetter>, // who last drove each wire until frozen, see `validate()`
+    pub(crate) frozen: bool,
+    pub(crate) scopes: Scopes, // module hierarchy and wire names, see `scope()`
+    pub(crate) four_state: bool, // see `simulator::four_state`
+    pub(crate) reset: Option<Wire>, // synchronous reset of all regs, see `set_reset()`
+    pub(crate) clock_domains: ClockDomains, // see `add_clock_domain()`
+    pub(crate) faults: Faults, // see `inject_stuck_at()`
+    pub(crate) toggles: Option<ToggleCounter>, // see `set_toggle_counting()`
+}
 
-const WIRE_0: usize = 0;
-const WIRE_1: usize = 1;
+impl Default for Circuit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-pub fn clear_all() {
-    unsafe {
-        WIRES.clear();
-        LATENCIES.clear();
-        GATES_MAP.clear();
-        GATES.clear();
-        EXTERNALS.clear();
-        REGS.clear();
-        EXECUTE_SEGMENTS.clear();
+thread_local! {
+    static DEFAULT_CIRCUIT: UnsafeCell<Circuit> = UnsafeCell::new(Circuit::new());
+    static CURRENT_CIRCUIT: Cell<*mut Circuit> = Cell::new(std::ptr::null_mut());
+}
 
-        WIRES.push(0); // => WIRE_0
-        WIRES.push(1); // => WIRE_1
-        LATENCIES.push(0); // => WIRE_0
-        LATENCIES.push(0); // => WIRE_1
+/// The circuit the free functions work on. Only for the duration of one call: the reference must
+/// not be kept across anything that reaches `current()` again, or be handed out to users. That
+/// is why externals get an `ExternalContext` and `external()` returns an `ExternalHandle`.
+pub(crate) fn current() -> &'static mut Circuit {
+    let mut ptr = CURRENT_CIRCUIT.with(|c| c.get());
+    if ptr.is_null() {
+        ptr = DEFAULT_CIRCUIT.with(|c| c.get());
     }
+    // SAFETY: the circuit is either this thread's default or kept alive and borrowed by
+    // `Circuit::enter` while it is current
+    unsafe { &mut *ptr }
 }
 
-#[derive(Debug, Copy, Clone)]
-pub struct GateExport {
-    pub wire_a_index: usize,
-    pub wire_b_index: usize,
-    pub wire_out_index: usize,
+/// Makes a circuit current until dropped, then restores the previous one.
+pub(crate) struct CurrentCircuitGuard {
+    previous: *mut Circuit,
 }
-#[derive(Debug, Copy, Clone)]
-pub struct RegExport {
-    pub wire_in_index: usize,
-    pub wire_out_index: usize,
+impl CurrentCircuitGuard {
+    pub(crate) fn new(circuit: *mut Circuit) -> Self {
+        let previous = CURRENT_CIRCUIT.with(|c| c.replace(circuit));
+        Self { previous }
+    }
 }
-pub struct ExportGateReg {
-    pub wire_0_value: u8,
-    pub wire_1_value: u8,
-    pub wire_count: usize,
-    pub gates: Vec<GateExport>,
-    pub regs: Vec<RegExport>,
+impl Drop for CurrentCircuitGuard {
+    fn drop(&mut self) {
+        CURRENT_CIRCUIT.with(|c| c.set(self.previous));
+    }
 }
-pub fn export_gate_reg() -> ExportGateReg {
-    unsafe {
-        let wire_0_value = WIRES[WIRE_0];
-        let wire_1_value = WIRES[WIRE_1];
 
-        let gates = GATES
+impl Circuit {
+    pub fn new() -> Self {
+        Self {
+            wires: vec![0, 1],     // => WIRE_0, WIRE_1
+            latencies: vec![0, 0], // => WIRE_0, WIRE_1
+            gates_map: HashMap::new(),
+            gates: Vec::new(),
+            externals: Vec::new(),
+            regs: Vec::new(),
+            execute_segments: Vec::new(),
+            lanes: Vec::new(),
+            lane_phases: Vec::new(),
+            backend: SimulationBackend::Interpreted,
+            compiled: None,
+            event_driven: None,
+            set_by: Vec::new(),
+            frozen: false,
+            scopes: Scopes::new(),
+            four_state: false,
+            reset: None,
+            clock_domains: ClockDomains::new(),
+            faults: Faults::default(),
+            toggles: None,
+        }
+    }
+
+    /// Drop the whole netlist, the simulation backend is kept.
+    pub fn clear(&mut self) {
+        let backend = self.backend;
+        *self = Self::new();
+        self.backend = backend;
+    }
+
+    /// Run `f` with this circuit as the current one, so that free functions, `Wire`/`Reg`
+    /// methods and the operators in `component_lib` build into and read from it.
+    pub fn enter<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        let _guard = CurrentCircuitGuard::new(self);
+        f()
+    }
+
+    pub fn input(&mut self) -> Wire {
+        assert!(!self.frozen, "Circuit is frozen!");
+        let index = self.wires.len();
+        self.wires.push(if self.four_state { VALUE_X } else { 0 });
+        self.latencies.push(0);
+        self.scopes.wire_scopes.push(self.scopes.current);
+        Wire(index)
+    }
+
+    fn find_gate(&self, a: Wire, b: Wire) -> Option<Wire> {
+        let v1 = self.gates_map.get(&(a.0, b.0));
+        if v1.is_some() {
+            return v1.copied();
+        }
+        let v2 = self.gates_map.get(&(b.0, a.0));
+        if v2.is_some() {
+            return v2.copied();
+        }
+        None
+    }
+
+    pub fn nand(&mut self, a: Wire, b: Wire) -> Wire {
+        // deduplicate
+        let duplicated = self.find_gate(a, b);
+        if let Some(out) = duplicated {
+            return out;
+        }
+
+        self.before_new_gate();
+        let out = self.input();
+        self.gates_map.insert((a.0, b.0), out);
+        let latency = self.get_latency(a).max(self.get_latency(b)) + 1;
+        self.set_latency(out, latency);
+        self.gates.push(Gate {
+            wire_a: a,
+            wire_b: b,
+            wire_out: out,
+        });
+        out
+    }
+
+    pub fn reg(&mut self) -> Reg {
+        let wire_out = self.input();
+        let reg = RegValue {
+            wire_in: None,
+            wire_out,
+            temp_value: self.wires[wire_out.0],
+            init: None,
+            enable: None,
+            domain: self.clock_domains.current,
+        };
+        let index = self.regs.len();
+        self.regs.push(reg);
+        Reg(index)
+    }
+
+    /// A reg that powers up as `value` and goes back to it on reset.
+    pub fn reg_with_init(&mut self, value: WireValue) -> Reg {
+        let reg = self.reg();
+        let r = &mut self.regs[reg.0];
+        r.init = Some(value);
+        r.temp_value = value;
+        self.wires[r.wire_out.0] = value;
+        reg
+    }
+
+    pub fn set_reg_in(&mut self, reg: Reg, wire: Wire) {
+        assert!(!self.frozen, "Circuit is frozen!");
+        let reg = &mut self.regs[reg.0];
+        assert!(reg.wire_in.is_none());
+        reg.wire_in = Some(wire);
+        self.netlist_changed();
+    }
+
+    /// The reg keeps its value on clock ticks where `wire` is 0.
+    pub fn set_reg_enable(&mut self, reg: Reg, wire: Wire) {
+        assert!(!self.frozen, "Circuit is frozen!");
+        let reg = &mut self.regs[reg.0];
+        assert!(reg.enable.is_none());
+        reg.enable = Some(wire);
+        self.netlist_changed();
+    }
+
+    /// Regs load their init value (0 without one) on clock ticks where `wire` is 1.
+    pub fn set_reset(&mut self, wire: Wire) {
+        assert!(!self.frozen, "Circuit is frozen!");
+        self.reset = Some(wire);
+        self.netlist_changed();
+    }
+    /// The reset wire, a new input is created and used as reset on first use.
+    pub fn reset_signal(&mut self) -> Wire {
+        match self.reset {
+            Some(wire) => wire,
+            None => {
+                let wire = self.input();
+                self.set_reset(wire);
+                wire
+            }
+        }
+    }
+
+    /// Changes that keep the wire count, so the backends cannot notice them on their own.
+    pub(crate) fn netlist_changed(&mut self) {
+        self.compiled = None;
+        self.event_driven = None;
+    }
+
+    pub fn reg_out(&self, reg: Reg) -> Wire {
+        self.regs[reg.0].wire_out
+    }
+
+    pub fn external<E: External>(&mut self, e: E) -> ExternalHandle<E> {
+        assert!(!self.frozen, "Circuit is frozen!");
+        self.before_new_external();
+        let e = Rc::new(RefCell::new(e));
+        self.externals.push(e.clone());
+        self.scopes.external_scopes.push(self.scopes.current);
+        ExternalHandle(e)
+    }
+
+    pub fn get(&self, wire: Wire) -> WireValue {
+        self.wires[wire.0]
+    }
+    pub fn set(&mut self, wire: Wire, value: WireValue) {
+        self.wires[wire.0] = value;
+        // a frozen netlist is already validated
+        if self.frozen {
+            return;
+        }
+        if self.set_by.len() <= wire.0 {
+            self.set_by.resize(self.wires.len(), WireSetter::Nobody);
+        }
+        // an external driving the wire is what matters for validation
+        if self.set_by[wire.0] == WireSetter::Nobody {
+            self.set_by[wire.0] = WireSetter::Testbench;
+        }
+    }
+    pub fn get_latency(&self, wire: Wire) -> LatencyValue {
+        self.latencies[wire.0]
+    }
+    pub fn set_latency(&mut self, wire: Wire, value: LatencyValue) {
+        self.latencies[wire.0] = value;
+    }
+
+    pub fn simulate(&mut self) {
+        self.execute_gates();
+        self.clock_tick();
+    }
+
+    pub fn execute_gates(&mut self) {
+        match self.backend {
+            _ if self.four_state => self.execute_gates_four_state(),
+            SimulationBackend::Interpreted => self.execute_gates_interpreted(),
+            SimulationBackend::Compiled => self.execute_gates_compiled(),
+            SimulationBackend::EventDriven => self.execute_gates_event_driven(),
+        }
+        self.force_stuck_wires();
+        self.count_toggles(false);
+    }
+
+    fn execute_gates_interpreted(&mut self) {
+        let mut ctx = ExternalContext::new(
+            &mut self.wires,
+            &mut self.latencies,
+            &mut self.set_by,
+            self.frozen,
+            &self.faults.stuck,
+        );
+        // println!("execute segments {:?}", self.execute_segments);
+        for segment in &self.execute_segments {
+            segment.execute(&self.gates, &self.externals, &mut ctx);
+        }
+    }
+
+    /// Tick the default domain, and every other domain that is due by its ratio.
+    pub fn clock_tick(&mut self) {
+        let due = self.clock_domains.advance();
+        self.tick_domains(due);
+    }
+
+    /// Regs outside the `due` mask of domains keep their value.
+    pub(crate) fn tick_domains(&mut self, due: u64) {
+        if self.backend == SimulationBackend::Compiled && !self.four_state {
+            self.clock_tick_compiled(due);
+        } else {
+            self.clock_tick_interpreted(due);
+        }
+        self.force_stuck_wires();
+        self.count_toggles(true);
+    }
+
+    fn clock_tick_interpreted(&mut self, due: u64) {
+        let missing = if self.four_state { VALUE_X } else { 0 };
+        let wires = &mut self.wires;
+        let reset = self.reset.map_or(0, |w| wires[w.0]);
+        self.regs.iter_mut().for_each(|reg| {
+            let keep = wires[reg.wire_out.0];
+            if (due >> reg.domain.0) & 1 == 0 {
+                reg.temp_value = keep;
+                return;
+            }
+            let value = reg.wire_in.map(|w| wires[w.0]).unwrap_or_else(|| {
+                // println!("reg without in");
+                missing
+            });
+            let enable = reg.enable.map_or(1, |w| wires[w.0]);
+            let init = reg.init.unwrap_or(0);
+            reg.temp_value = next_reg_value(reset, init, enable, value, keep);
+        });
+        self.regs
+            .iter()
+            .for_each(|reg| wires[reg.wire_out.0] = reg.temp_value);
+    }
+
+    pub fn get_statistics(&self) -> ExecutionResult {
+        let max_latency_wire = (0..self.latencies.len())
+            .max_by_key(|i| (self.latencies[*i], std::cmp::Reverse(*i)))
+            .unwrap_or(WIRE_0);
+        ExecutionResult {
+            wire_count: self.wires.len(),
+            gate_count: self.gates.len(),
+            max_latency: *self.latencies.iter().max().unwrap_or(&0),
+            max_latency_wire: self.wire_name(Wire(max_latency_wire)),
+        }
+    }
+
+    pub fn export_gate_reg(&self) -> ExportGateReg {
+        let wire_0_value = self.wires[WIRE_0];
+        let wire_1_value = self.wires[WIRE_1];
+
+        let gates = self
+            .gates
             .iter()
             .map(|gate| GateExport {
                 wire_a_index: gate.wire_a.0,
                 wire_b_index: gate.wire_b.0,
                 wire_out_index: gate.wire_out.0,
+                scope: self.wire_scope(gate.wire_out),
             })
             .collect::<Vec<_>>();
 
-        let regs = REGS
+        let regs = self
+            .regs
             .iter()
             .map(|reg| RegExport {
                 wire_in_index: reg.wire_in.unwrap().0,
                 wire_out_index: reg.wire_out.0,
+                init: reg.init.unwrap_or(0),
+                enable_index: reg.enable.map(|wire| wire.0),
+                domain: reg.domain.0,
             })
             .collect::<Vec<_>>();
 
-        assert!(
-            EXTERNALS.is_empty(),
-            "Export wire/reg only! Externals are not supported!"
-        );
+        // observers are simulation only, everything else needs a black box
+        let externals = self
+            .externals
+            .iter()
+            .enumerate()
+            .map(|(index, external)| (index, external.borrow()))
+            .filter(|(_, external)| !external.is_observer())
+            .map(|(index, external)| ExternalExport {
+                ports: external.export_ports().unwrap_or_else(|| {
+                    panic!("External {index} cannot be exported, see External::export_ports()!")
+                }),
+                scope: self.external_scope(index),
+            })
+            .collect::<Vec<_>>();
 
         ExportGateReg {
             wire_0_value,
             wire_1_value,
-            wire_count: WIRES.len(),
+            wire_count: self.wires.len(),
             gates,
             regs,
+            externals,
+            reset_index: self.reset.map(|wire| wire.0),
+            clock_domains: self.clock_domains.names(),
+            wire_names: (0..self.wires.len())
+                .filter_map(|i| Some((i, self.given_wire_name(Wire(i))?.to_string())))
+                .collect(),
+            scopes: (0..self.scope_count())
+                .map(|i| self.scope_path(i))
+                .collect(),
         }
     }
 }
 
+pub fn clear_all() {
+    current().clear();
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct GateExport {
+    pub wire_a_index: usize,
+    pub wire_b_index: usize,
+    pub wire_out_index: usize,
+    pub scope: ScopeId,
+}
+#[derive(Debug, Copy, Clone)]
+pub struct RegExport {
+    pub wire_in_index: usize,
+    pub wire_out_index: usize,
+    pub init: WireValue,
+    pub enable_index: Option<usize>,
+    pub domain: usize, // index in `ExportGateReg::clock_domains`
+}
+pub struct ExternalExport {
+    pub ports: ExternalPorts,
+    pub scope: ScopeId,
+}
+pub struct ExportGateReg {
+    pub wire_0_value: u8,
+    pub wire_1_value: u8,
+    pub wire_count: usize,
+    pub gates: Vec<GateExport>,
+    pub regs: Vec<RegExport>,
+    pub externals: Vec<ExternalExport>, // observers left out
+    pub reset_index: Option<usize>,
+    pub clock_domains: Vec<String>, // names, the default domain first
+    pub wire_names: HashMap<usize, String>, // hierarchical names given with `named()`
+    pub scopes: Vec<String>,        // path of each scope, indexed by `ScopeId`
+}
+pub fn export_gate_reg() -> ExportGateReg {
+    current().export_gate_reg()
+}
+
+/// Wire values and latencies of the circuit, for an external while it executes.
+///
+/// Externals read and write wires only through it: `Wire::get()`, `Wires::set_u8()`... go
+/// through the current circuit, which is busy executing them.
+pub struct ExternalContext<'a> {
+    pub(crate) wires: &'a mut [WireValue],
+    latencies: &'a mut [LatencyValue],
+    set_by: Option<&'a mut Vec<WireSetter>>, // none once the netlist is frozen
+    stuck: &'a [(Wire, WireValue)],          // see `Faults`
+    external: usize,                         // index of the external executing
+}
+
+impl<'a> ExternalContext<'a> {
+    pub(crate) fn new(
+        wires: &'a mut [WireValue],
+        latencies: &'a mut [LatencyValue],
+        set_by: &'a mut Vec<WireSetter>,
+        frozen: bool,
+        stuck: &'a [(Wire, WireValue)],
+    ) -> Self {
+        Self {
+            wires,
+            latencies,
+            set_by: (!frozen).then_some(set_by),
+            stuck,
+            external: 0,
+        }
+    }
+
+    pub fn get(&self, wire: Wire) -> WireValue {
+        self.wires[wire.0]
+    }
+    pub fn set(&mut self, wire: Wire, value: WireValue) {
+        self.wires[wire.0] = value;
+        if let Some(set_by) = &mut self.set_by {
+            if set_by.len() <= wire.0 {
+                set_by.resize(self.wires.len(), WireSetter::Nobody);
+            }
+            set_by[wire.0] = WireSetter::External(self.external);
+        }
+    }
+    pub fn is_one(&self, wire: Wire) -> bool {
+        self.get(wire) == 1
+    }
+    pub fn get_latency(&self, wire: Wire) -> LatencyValue {
+        self.latencies[wire.0]
+    }
+    pub fn set_latency(&mut self, wire: Wire, value: LatencyValue) {
+        self.latencies[wire.0] = value;
+    }
+
+    /// `None` if any bit is X or Z, lsb first like `Wires`.
+    pub fn try_get_u64(&self, wires: &[Wire]) -> Option<u64> {
+        assert!(wires.len() <= 64, "More than 64 bits!");
+        let bits = wires.iter().enumerate();
+        bits.fold(Some(0), |value, (i, wire)| match self.get(*wire) {
+            bit @ (0 | 1) => Some(value? | ((bit as u64) << i)),
+            _ => None,
+        })
+    }
+    pub fn get_u64(&self, wires: &[Wire]) -> u64 {
+        self.try_get_u64(wires)
+            .expect("Unknown (X/Z) bit, use try_get_u64()!")
+    }
+    pub fn set_u64(&mut self, wires: &[Wire], value: u64) {
+        assert!(wires.len() <= 64, "More than 64 bits!");
+        for (i, wire) in wires.iter().enumerate() {
+            self.set(*wire, ((value >> i) & 1) as WireValue);
+        }
+    }
+    pub fn try_get_u8(&self, wires: &[Wire]) -> Option<u8> {
+        assert!(wires.len() <= 8, "More than 8 bits!");
+        self.try_get_u64(wires).map(|value| value as u8)
+    }
+    pub fn get_u8(&self, wires: &[Wire]) -> u8 {
+        self.try_get_u8(wires)
+            .expect("Unknown (X/Z) bit, use try_get_u8()!")
+    }
+    pub fn set_u8(&mut self, wires: &[Wire], value: u8) {
+        assert!(wires.len() <= 8, "More than 8 bits!");
+        self.set_u64(wires, value as u64);
+    }
+}
+
 pub trait External: Any {
-    fn execute(&mut self);
-    fn as_any(&self) -> &dyn Any;
+    fn execute(&mut self, ctx: &mut ExternalContext);
+    /// Opt-in for `snapshot()`, externals without state of their own keep the default.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+    fn restore_state(&mut self, _state: &[u8]) {}
+    /// Black box for `export_gate_reg()`, designs with externals that have none can't be exported.
+    fn export_ports(&self) -> Option<ExternalPorts> {
+        None
+    }
+    /// Observers only read wires (loggers, recorders...), exports leave them out.
+    fn is_observer(&self) -> bool {
+        false
+    }
 }
 
-pub fn external<E: External>(e: E) -> &'static E {
-    before_new_external();
-    unsafe {
-        EXTERNALS.push(Box::new(e));
-        let r = EXTERNALS.last().unwrap().as_ref();
-        r.as_any().downcast_ref::<E>().unwrap()
+/// An external added to a circuit, to read back what it has collected.
+///
+/// It stays usable after the circuit is cleared or dropped. Borrowing it while the circuit
+/// executes it, for example across a `simulate()`, panics.
+pub struct ExternalHandle<E>(Rc<RefCell<E>>);
+
+impl<E> ExternalHandle<E> {
+    pub fn borrow(&self) -> Ref<'_, E> {
+        self.0.borrow()
     }
 }
 
-pub fn reg() -> Reg {
-    let reg = RegValue {
-        wire_in: None,
-        wire_out: input(),
-        temp_value: 0,
-    };
-    unsafe {
-        let index = REGS.len();
-        REGS.push(reg);
-        Reg(index)
+impl<E> Clone for ExternalHandle<E> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
     }
 }
+
+pub fn external<E: External>(e: E) -> ExternalHandle<E> {
+    current().external(e)
+}
+
+pub fn reg() -> Reg {
+    current().reg()
+}
+pub fn reg_with_init(value: WireValue) -> Reg {
+    current().reg_with_init(value)
+}
+pub fn set_reset(wire: Wire) {
+    current().set_reset(wire);
+}
+pub fn reset_signal() -> Wire {
+    current().reset_signal()
+}
 impl Reg {
     pub fn set_in(self, wire: Wire) {
-        unsafe {
-            let reg = &mut REGS[self.0];
-            assert!(reg.wire_in.is_none());
-            reg.wire_in = Some(wire);
-        }
+        current().set_reg_in(self, wire);
+    }
+    pub fn set_enable(self, wire: Wire) {
+        current().set_reg_enable(self, wire);
     }
     pub fn out(self) -> Wire {
-        unsafe { REGS[self.0].wire_out }
+        current().reg_out(self)
     }
 }
 
 pub fn input() -> Wire {
-    unsafe {
-        let index = WIRES.len();
-        WIRES.push(0);
-        LATENCIES.push(0);
-        Wire(index)
-    }
+    current().input()
 }
 
 pub fn input_const(value: WireValue) -> Wire {
@@ -181,67 +647,57 @@ pub fn input_const(value: WireValue) -> Wire {
     Wire(index)
 }
 
-fn find_gate(a: Wire, b: Wire) -> Option<Wire> {
-    unsafe {
-        let v1 = GATES_MAP.get(&(a.0, b.0));
-        if v1.is_some() {
-            return v1.copied();
-        }
-        let v2 = GATES_MAP.get(&(b.0, a.0));
-        if v2.is_some() {
-            return v2.copied();
-        }
-    }
-    None
-}
-
 pub fn nand(a: Wire, b: Wire) -> Wire {
-    // deduplicate
-    let duplicated = find_gate(a, b);
-    if let Some(out) = duplicated {
-        return out;
-    }
-
-    before_new_gate();
-    unsafe {
-        let out = input();
-        GATES_MAP.insert((a.0, b.0), out);
-        out.set_latency(a.get_latency().max(b.get_latency()) + 1);
-        GATES.push(Gate {
-            wire_a: a,
-            wire_b: b,
-            wire_out: out,
-        });
-        out
-    }
+    current().nand(a, b)
 }
 
 impl Wire {
     pub fn is_one(self) -> bool {
-        unsafe { WIRES[self.0] > 0 }
+        current().get(self) == 1
     }
     pub fn get(self) -> WireValue {
-        unsafe { WIRES[self.0] }
+        current().get(self)
     }
     pub fn set(self, value: WireValue) {
-        unsafe {
-            WIRES[self.0] = value;
-        }
+        current().set(self, value);
     }
     pub fn get_latency(self) -> LatencyValue {
-        unsafe { LATENCIES[self.0] }
+        current().get_latency(self)
     }
     pub fn set_latency(self, value: LatencyValue) {
-        unsafe {
-            LATENCIES[self.0] = value;
-        }
+        current().set_latency(self, value);
     }
 }
 
 pub struct RegValue {
-    wire_in: Option<Wire>,
+    pub(crate) wire_in: Option<Wire>,
     pub wire_out: Wire,
-    temp_value: WireValue,
+    pub(crate) temp_value: WireValue,
+    pub(crate) init: Option<WireValue>, // power-up and reset value, see `reg_with_init()`
+    pub(crate) enable: Option<Wire>,
+    pub(crate) domain: ClockDomain,
+}
+
+/// Value a reg loads on a clock tick, X where an unknown reset or enable could make a difference.
+pub(crate) fn next_reg_value(
+    reset: WireValue,
+    init: WireValue,
+    enable: WireValue,
+    value: WireValue,
+    keep: WireValue,
+) -> WireValue {
+    let loaded = match enable {
+        1 => value,
+        0 => keep,
+        _ if value == keep => value,
+        _ => VALUE_X,
+    };
+    match reset {
+        0 => loaded,
+        1 => init,
+        _ if loaded == init => init,
+        _ => VALUE_X,
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -252,48 +708,65 @@ pub struct Gate {
 }
 
 impl Gate {
-    fn execute(&self) {
-        let a = self.wire_a.get();
-        let b = self.wire_b.get();
-        self.wire_out.set(!(a & b) & 1);
+    fn execute(&self, wires: &mut [WireValue]) {
+        let a = wires[self.wire_a.0];
+        let b = wires[self.wire_b.0];
+        wires[self.wire_out.0] = !(a & b) & 1;
     }
 }
 
 //region Execute Segments
 
-fn before_new_gate() {
-    unsafe {
-        if let Some(ExecuteSegment::Gates(range)) = EXECUTE_SEGMENTS.last_mut() {
+impl Circuit {
+    fn before_new_gate(&mut self) {
+        if let Some(ExecuteSegment::Gates(range)) = self.execute_segments.last_mut() {
             range.end += 1;
         } else {
-            let next = GATES.len();
-            EXECUTE_SEGMENTS.push(ExecuteSegment::Gates(next..(next + 1)));
+            let next = self.gates.len();
+            self.execute_segments
+                .push(ExecuteSegment::Gates(next..(next + 1)));
         }
     }
-}
-fn before_new_external() {
-    unsafe {
-        if let Some(ExecuteSegment::Externals(range)) = EXECUTE_SEGMENTS.last_mut() {
+    fn before_new_external(&mut self) {
+        if let Some(ExecuteSegment::Externals(range)) = self.execute_segments.last_mut() {
             range.end += 1;
         } else {
-            let next = EXTERNALS.len();
-            EXECUTE_SEGMENTS.push(ExecuteSegment::Externals(next..(next + 1)));
+            let next = self.externals.len();
+            self.execute_segments
+                .push(ExecuteSegment::Externals(next..(next + 1)));
         }
     }
 }
 
 impl ExecuteSegment {
-    fn execute(&self) {
+    fn execute(
+        &self,
+        gates: &[Gate],
+        externals: &[Rc<RefCell<dyn External>>],
+        ctx: &mut ExternalContext,
+    ) {
         match self {
             ExecuteSegment::Gates(range) => {
-                let gates = unsafe { &GATES[range.start..range.end] };
-                gates.iter().for_each(|gate| gate.execute());
-            }
-            ExecuteSegment::Externals(range) => {
-                let externals = unsafe { &mut EXTERNALS[range.start..range.end] };
-                externals.iter_mut().for_each(|external| external.execute());
+                let gates = &gates[range.start..range.end];
+                gates.iter().for_each(|gate| gate.execute(ctx.wires));
             }
+            ExecuteSegment::Externals(range) => execute_externals(externals, range, ctx),
+        }
+    }
+}
+
+pub(crate) fn execute_externals(
+    externals: &[Rc<RefCell<dyn External>>],
+    range: &Range<usize>,
+    ctx: &mut ExternalContext,
+) {
+    for index in range.clone() {
+        // whatever drove them since, including the previous external
+        for (wire, value) in ctx.stuck {
+            ctx.wires[wire.0] = *value;
         }
+        ctx.external = index;
+        externals[index].borrow_mut().execute(ctx);
     }
 }
 
@@ -304,41 +777,57 @@ pub struct ExecutionResult {
     pub wire_count: usize,
     pub gate_count: usize,
     pub max_latency: LatencyValue,
+    pub max_latency_wire: String, // hierarchical name of the first wire with max_latency
 }
 
 pub fn simulate() {
-    execute_gates();
-    clock_tick();
+    current().simulate();
 }
 
 pub fn get_statistics() -> ExecutionResult {
-    unsafe {
-        ExecutionResult {
-            wire_count: WIRES.len(),
-            gate_count: GATES.len(),
-            max_latency: *LATENCIES.iter().max().unwrap_or(&0),
-        }
-    }
+    current().get_statistics()
 }
 
 pub fn execute_gates() {
-    unsafe {
-        // println!("execute segments {:?}", EXECUTE_SEGMENTS);
-        for segment in &EXECUTE_SEGMENTS {
-            segment.execute()
-        }
-    }
+    current().execute_gates();
 }
 
 pub fn clock_tick() {
-    unsafe {
-        REGS.iter_mut().for_each(|reg| {
-            reg.temp_value = reg.wire_in.map(|w| w.get()).unwrap_or_else(|| {
-                // println!("reg without in");
-                0
-            })
+    current().clock_tick();
+}
+
+#[test]
+fn test_circuit() {
+    use crate::{add_naive, input_w, Wires};
+
+    // a counter and a reference adder, built side by side in two circuits
+    let mut counter = Circuit::new();
+    let out = counter.enter(|| {
+        let curr = crate::reg_w::<4>();
+        curr.set_in(add_naive(curr.out, Wires::parse_u8(1)).sum);
+        curr.out
+    });
+
+    let mut adder = Circuit::new();
+    let (a, b, sum) = adder.enter(|| {
+        let a = input_w::<4>();
+        let b = input_w::<4>();
+        (a, b, add_naive(a, b).sum)
+    });
+
+    for i in 1..20u8 {
+        counter.simulate();
+        adder.enter(|| {
+            a.set_u8(i % 16);
+            b.set_u8(3);
         });
-        REGS.iter_mut()
-            .for_each(|reg| reg.wire_out.set(reg.temp_value));
+        adder.simulate();
+        let counter_value = counter.enter(|| out.get_u8());
+        let adder_value = adder.enter(|| sum.get_u8());
+        assert_eq!(i % 16, counter_value);
+        assert_eq!((i + 3) % 16, adder_value);
     }
+
+    // the thread default circuit is untouched
+    assert_eq!(0, get_statistics().gate_count);
 }
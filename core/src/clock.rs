@@ -0,0 +1,259 @@
+use crate::{current, reg, Circuit, Reg, Regs, Wire};
+
+/// Handle of a clock domain, regs tick only with the clock of their domain.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ClockDomain(pub usize);
+pub const DEFAULT_CLOCK_DOMAIN: ClockDomain = ClockDomain(0);
+/// Domains are tracked in a `u64` mask on every tick.
+pub const MAX_CLOCK_DOMAINS: usize = 64;
+
+#[derive(Clone)]
+struct ClockDomainValue {
+    name: String,
+    numerator: u32,
+    denominator: u32,
+    phase: u32, // accumulates `numerator` per `clock_tick()`, ticks on reaching `denominator`
+}
+
+/// Clock domains of a circuit, every reg remembers the domain that was open when it was created.
+pub(crate) struct ClockDomains {
+    domains: Vec<ClockDomainValue>,
+    pub(crate) current: ClockDomain,
+}
+
+impl ClockDomains {
+    pub(crate) fn new() -> Self {
+        Self {
+            domains: vec![ClockDomainValue {
+                name: "clk".to_string(),
+                numerator: 1,
+                denominator: 1,
+                phase: 0,
+            }],
+            current: DEFAULT_CLOCK_DOMAIN,
+        }
+    }
+
+    /// Advance every domain by one `clock_tick()`, returns the mask of domains that tick.
+    pub(crate) fn advance(&mut self) -> u64 {
+        let mut due = 0;
+        for (index, domain) in self.domains.iter_mut().enumerate() {
+            domain.phase += domain.numerator;
+            if domain.phase >= domain.denominator {
+                domain.phase -= domain.denominator;
+                due |= 1 << index;
+            }
+        }
+        due
+    }
+
+    /// Masks of the next `count` `advance()`s, without advancing.
+    pub(crate) fn upcoming(&self, count: usize) -> Vec<u64> {
+        let mut domains = Self {
+            domains: self.domains.clone(),
+            current: self.current,
+        };
+        (0..count).map(|_| domains.advance()).collect()
+    }
+
+    pub(crate) fn phases(&self) -> Vec<u32> {
+        self.domains.iter().map(|domain| domain.phase).collect()
+    }
+    pub(crate) fn set_phases(&mut self, phases: &[u32]) {
+        for (domain, phase) in self.domains.iter_mut().zip(phases) {
+            domain.phase = *phase;
+        }
+    }
+
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.domains
+            .iter()
+            .map(|domain| domain.name.clone())
+            .collect()
+    }
+}
+
+impl Circuit {
+    /// A domain that ticks `numerator` times every `denominator` `clock_tick()`s, so at most as
+    /// fast as the default domain. A numerator of 0 only ticks with `clock_tick_domain()`.
+    pub fn add_clock_domain(
+        &mut self,
+        name: &str,
+        numerator: u32,
+        denominator: u32,
+    ) -> ClockDomain {
+        assert!(!self.frozen, "Circuit is frozen!");
+        assert!(
+            denominator > 0 && numerator <= denominator,
+            "Clock ratio {numerator}/{denominator} must be between 0 and 1!"
+        );
+        let domains = &mut self.clock_domains.domains;
+        assert!(
+            domains.len() < MAX_CLOCK_DOMAINS,
+            "At most {MAX_CLOCK_DOMAINS} clock domains!"
+        );
+        assert!(
+            domains.iter().all(|domain| domain.name != name),
+            "Clock domain {name} already exists!"
+        );
+        domains.push(ClockDomainValue {
+            name: name.to_string(),
+            numerator,
+            denominator,
+            phase: 0,
+        });
+        ClockDomain(domains.len() - 1)
+    }
+
+    pub fn clock_domain_count(&self) -> usize {
+        self.clock_domains.domains.len()
+    }
+    pub fn clock_domain_name(&self, domain: ClockDomain) -> &str {
+        &self.clock_domains.domains[domain.0].name
+    }
+
+    pub fn set_reg_domain(&mut self, reg: Reg, domain: ClockDomain) {
+        assert!(!self.frozen, "Circuit is frozen!");
+        assert!(
+            domain.0 < self.clock_domain_count(),
+            "Unknown clock domain!"
+        );
+        self.regs[reg.0].domain = domain;
+        self.netlist_changed();
+    }
+    pub fn reg_domain(&self, reg: Reg) -> ClockDomain {
+        self.regs[reg.0].domain
+    }
+
+    pub fn enter_clock_domain(&mut self, domain: ClockDomain) -> ClockDomain {
+        assert!(
+            domain.0 < self.clock_domain_count(),
+            "Unknown clock domain!"
+        );
+        std::mem::replace(&mut self.clock_domains.current, domain)
+    }
+    pub fn exit_clock_domain(&mut self, previous: ClockDomain) {
+        self.clock_domains.current = previous;
+    }
+
+    /// Tick the regs of one domain only, whatever its ratio.
+    pub fn clock_tick_domain(&mut self, domain: ClockDomain) {
+        self.tick_domains(1 << domain.0);
+    }
+}
+
+pub fn add_clock_domain(name: &str, numerator: u32, denominator: u32) -> ClockDomain {
+    current().add_clock_domain(name, numerator, denominator)
+}
+
+/// Leaves the domain `clock_domain()` entered, also when `f` panics.
+struct ClockDomainGuard {
+    previous: ClockDomain,
+}
+impl Drop for ClockDomainGuard {
+    fn drop(&mut self) {
+        current().exit_clock_domain(self.previous);
+    }
+}
+
+/// Build everything inside `f` with its regs in `domain`.
+pub fn clock_domain<R>(domain: ClockDomain, f: impl FnOnce() -> R) -> R {
+    let _guard = ClockDomainGuard {
+        previous: current().enter_clock_domain(domain),
+    };
+    f()
+}
+
+pub fn clock_tick_domain(domain: ClockDomain) {
+    current().clock_tick_domain(domain);
+}
+
+/// Two regs in `domain`, `wire` should come straight from a reg of its own domain.
+pub fn synchronizer(wire: Wire, domain: ClockDomain) -> Wire {
+    clock_domain(domain, || {
+        let first = reg();
+        let second = reg();
+        first.set_in(wire);
+        second.set_in(first.out());
+        second.out()
+    })
+}
+
+impl Reg {
+    pub fn set_domain(self, domain: ClockDomain) -> Reg {
+        current().set_reg_domain(self, domain);
+        self
+    }
+    pub fn domain(self) -> ClockDomain {
+        current().reg_domain(self)
+    }
+}
+
+impl<const W: usize> Regs<W> {
+    pub fn set_domain(self, domain: ClockDomain) -> Regs<W> {
+        for reg in self.regs {
+            reg.set_domain(domain);
+        }
+        self
+    }
+}
+
+#[test]
+fn test_clock_domains() {
+    use crate::*;
+
+    // a counter in each domain, the slow one at 2/3 of the default clock
+    for backend in [
+        SimulationBackend::Interpreted,
+        SimulationBackend::Compiled,
+        SimulationBackend::EventDriven,
+    ] {
+        let mut circuit = Circuit::new();
+        circuit.set_backend(backend);
+        circuit.enter(|| {
+            let slow = add_clock_domain("slow", 2, 3);
+            let manual = add_clock_domain("manual", 0, 1);
+            let counter = || {
+                let count = reg_w::<4>();
+                count.set_in(add_naive(count.out, Wires::parse_u8(1)).sum);
+                count
+            };
+            let fast = counter();
+            let slow_count = clock_domain(slow, counter);
+            let manual_count = counter().set_domain(manual);
+            assert_eq!(slow, slow_count.regs[0].domain());
+            assert_eq!("manual", current().clock_domain_name(manual));
+
+            let mut slow_values = vec![];
+            for _ in 0..6 {
+                simulate();
+                slow_values.push(slow_count.out.get_u8());
+            }
+            assert_eq!(6, fast.out.get_u8(), "{backend:?}");
+            assert_eq!(vec![0, 1, 2, 2, 3, 4], slow_values, "{backend:?}");
+            assert_eq!(0, manual_count.out.get_u8());
+
+            execute_gates();
+            clock_tick_domain(manual);
+            assert_eq!(
+                (6, 4, 1),
+                (
+                    fast.out.get_u8(),
+                    slow_count.out.get_u8(),
+                    manual_count.out.get_u8()
+                )
+            );
+        });
+    }
+}
+
+#[test]
+fn test_clock_domain_panic() {
+    use crate::*;
+    clear_all();
+
+    // a panic inside leaves the domain too
+    let slow = add_clock_domain("slow", 1, 2);
+    assert!(std::panic::catch_unwind(|| clock_domain(slow, || panic!("build failed"))).is_err());
+    assert_eq!(DEFAULT_CLOCK_DOMAIN, reg().domain());
+}
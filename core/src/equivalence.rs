@@ -0,0 +1,261 @@
+use crate::sat::{Lit, Solver};
+use crate::{current, Circuit, Wire, WireValue, WIRE_0, WIRE_1};
+use std::fmt::{Display, Formatter};
+
+/// Inputs on which two cones differ, bits in the order of the input wires given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counterexample {
+    pub inputs: Vec<WireValue>,
+    pub outputs_a: Vec<WireValue>,
+    pub outputs_b: Vec<WireValue>,
+}
+
+impl Counterexample {
+    /// `width` input bits from `offset`, to replay with `set_u8()`.
+    pub fn get_u8(&self, offset: usize, width: usize) -> u8 {
+        assert!(width <= 8, "More than 8 bits!");
+        (0..width).fold(0, |value, i| value | (self.inputs[offset + i] << i))
+    }
+
+    /// Set `inputs` to the counterexample.
+    pub fn apply(&self, inputs: &[Wire]) {
+        assert_eq!(self.inputs.len(), inputs.len(), "Input count mismatch!");
+        for (wire, value) in inputs.iter().zip(&self.inputs) {
+            wire.set(*value);
+        }
+    }
+
+    /// Output bits that differ.
+    pub fn differing_outputs(&self) -> Vec<usize> {
+        let pairs = self.outputs_a.iter().zip(&self.outputs_b);
+        pairs
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl Display for Counterexample {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let bits = |values: &[WireValue]| values.iter().map(|v| v.to_string()).collect::<String>();
+        writeln!(f, "inputs {}", bits(&self.inputs))?;
+        writeln!(f, "  a {}", bits(&self.outputs_a))?;
+        writeln!(f, "  b {}", bits(&self.outputs_b))?;
+        let differing = self.differing_outputs();
+        let differing = differing.iter().map(|i| i.to_string());
+        writeln!(
+            f,
+            "  differing outputs {}",
+            differing.collect::<Vec<_>>().join(" ")
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Equivalence {
+    Equivalent,
+    Different(Counterexample),
+}
+
+impl Equivalence {
+    pub fn is_equivalent(&self) -> bool {
+        matches!(self, Equivalence::Equivalent)
+    }
+}
+
+impl Circuit {
+    /// Tseitin encoding of the gates `outputs` depend on. `literals` holds the literal of every
+    /// wire known before (inputs, reg outputs), constants are added on the way. A wire that is
+    /// read but neither known nor driven by a gate is returned as the error.
+    pub(crate) fn encode_cone(
+        &self,
+        solver: &mut Solver,
+        literals: &mut [Option<Lit>],
+        outputs: &[Wire],
+    ) -> Result<(), Wire> {
+        let mut driver = vec![None; self.wires.len()];
+        for (index, gate) in self.gates.iter().enumerate() {
+            driver[gate.wire_out.0] = Some(index);
+        }
+        let mut needed = vec![false; self.gates.len()];
+        let mut stack = outputs.to_vec();
+        while let Some(wire) = stack.pop() {
+            if literals[wire.0].is_some() {
+                continue;
+            }
+            match driver[wire.0] {
+                Some(index) if !needed[index] => {
+                    needed[index] = true;
+                    let gate = &self.gates[index];
+                    stack.extend([gate.wire_a, gate.wire_b]);
+                }
+                Some(_) => {}
+                None if wire.0 == WIRE_0 || wire.0 == WIRE_1 => {
+                    let one = solver.true_lit();
+                    literals[wire.0] = Some(match self.wires[wire.0] {
+                        1 => one,
+                        _ => !one,
+                    });
+                }
+                None => return Err(wire),
+            }
+        }
+        // gate inputs are always created before the gate
+        for (gate, _) in self.gates.iter().zip(needed).filter(|(_, needed)| *needed) {
+            let a = literals[gate.wire_a.0].unwrap();
+            let b = literals[gate.wire_b.0].unwrap();
+            literals[gate.wire_out.0] = Some(solver.nand(a, b));
+        }
+        Ok(())
+    }
+
+    /// Prove that `outputs` of this circuit and `other_outputs` of `other` are the same function
+    /// of their inputs, input `i` of one cone being input `i` of the other. Both cones have to
+    /// be combinational: anything they read that is not a gate must be one of their inputs.
+    ///
+    /// `other` may be this circuit, the cones can then share inputs and gates.
+    pub fn check_equivalence(
+        &self,
+        inputs: &[Wire],
+        outputs: &[Wire],
+        other: &Circuit,
+        other_inputs: &[Wire],
+        other_outputs: &[Wire],
+    ) -> Equivalence {
+        assert_eq!(inputs.len(), other_inputs.len(), "Input count mismatch!");
+        assert_eq!(outputs.len(), other_outputs.len(), "Output count mismatch!");
+        let mut solver = Solver::new();
+        let input_lits = inputs.iter().map(|_| solver.new_var()).collect::<Vec<_>>();
+
+        let mut encode = |circuit: &Circuit, inputs: &[Wire], outputs: &[Wire]| {
+            let mut literals: Vec<Option<Lit>> = vec![None; circuit.wires.len()];
+            for (wire, lit) in inputs.iter().zip(&input_lits) {
+                // the same wire twice makes both inputs equal
+                match literals[wire.0] {
+                    Some(first) => {
+                        solver.add_clause(&[!first, *lit]);
+                        solver.add_clause(&[first, !*lit]);
+                    }
+                    None => literals[wire.0] = Some(*lit),
+                }
+            }
+            if let Err(wire) = circuit.encode_cone(&mut solver, &mut literals, outputs) {
+                panic!(
+                    "Wire {} is read by the cone but is not one of its inputs!",
+                    circuit.wire_name(wire)
+                );
+            }
+            outputs
+                .iter()
+                .map(|wire| literals[wire.0].unwrap())
+                .collect::<Vec<_>>()
+        };
+        let outputs_a = encode(self, inputs, outputs);
+        let outputs_b = encode(other, other_inputs, other_outputs);
+
+        // miter: some output pair differs
+        let differences = outputs_a
+            .iter()
+            .zip(&outputs_b)
+            .filter(|(a, b)| a != b)
+            .map(|(a, b)| solver.xor(*a, *b))
+            .collect::<Vec<_>>();
+        let differ = solver.or(&differences);
+        if !solver.solve(&[differ]) {
+            return Equivalence::Equivalent;
+        }
+        let values = |lits: &[Lit]| {
+            let values = lits.iter().map(|lit| solver.model_value(*lit) as WireValue);
+            values.collect::<Vec<_>>()
+        };
+        Equivalence::Different(Counterexample {
+            inputs: values(&input_lits),
+            outputs_a: values(&outputs_a),
+            outputs_b: values(&outputs_b),
+        })
+    }
+}
+
+/// `check_equivalence()` of two cones in the current circuit.
+pub fn check_equivalence(
+    inputs_a: &[Wire],
+    outputs_a: &[Wire],
+    inputs_b: &[Wire],
+    outputs_b: &[Wire],
+) -> Equivalence {
+    let circuit: &Circuit = current();
+    circuit.check_equivalence(inputs_a, outputs_a, circuit, inputs_b, outputs_b)
+}
+
+#[test]
+fn test_equivalence() {
+    use crate::*;
+    clear_all();
+
+    // add_naive against a ripple carry adder written out with xor and mux
+    let a = input_w::<8>();
+    let b = input_w::<8>();
+    let naive = add_naive(a, b);
+    let mut carry = input_const(0);
+    let mut sum = vec![];
+    for i in 0..8 {
+        let propagate = a.wires[i] ^ b.wires[i];
+        sum.push(propagate ^ carry);
+        carry = mux2(a.wires[i], carry, propagate);
+    }
+    sum.push(carry);
+    let inputs = [a.wires, b.wires].concat();
+    let mut outputs = naive.sum.wires.to_vec();
+    outputs.push(naive.carry);
+    assert!(check_equivalence(&inputs, &outputs, &inputs, &sum).is_equivalent());
+
+    // a wrong carry into bit 5
+    let mut carry = input_const(0);
+    let mut wrong = vec![];
+    for i in 0..8 {
+        let propagate = a.wires[i] ^ b.wires[i];
+        wrong.push(propagate ^ carry);
+        carry = match i {
+            4 => a.wires[i] & b.wires[i],
+            _ => mux2(a.wires[i], carry, propagate),
+        };
+    }
+    wrong.push(carry);
+    let result = check_equivalence(&inputs, &outputs, &inputs, &wrong);
+    let Equivalence::Different(counterexample) = result else {
+        panic!("no counterexample");
+    };
+    a.set_u8(counterexample.get_u8(0, 8));
+    b.set_u8(counterexample.get_u8(8, 8));
+    execute_gates();
+    let wrong_sum = (0..8).fold(0u8, |v, i| v | (wrong[i].get() << i));
+    assert_ne!(naive.sum.get_u8(), wrong_sum);
+    assert_eq!(
+        naive.sum.get_u8(),
+        counterexample
+            .get_u8(0, 8)
+            .wrapping_add(counterexample.get_u8(8, 8))
+    );
+    assert!(counterexample.differing_outputs().contains(&5));
+    let text = counterexample.to_string();
+    let bits = counterexample.inputs.iter().map(|v| v.to_string());
+    assert!(text.starts_with(&format!("inputs {}\n  a ", bits.collect::<String>())));
+    let differing = text.lines().last().unwrap();
+    assert!(differing.starts_with("  differing outputs "));
+    assert!(differing.split(' ').any(|i| i == "5"));
+
+    // cones in two circuits, inputs in another order
+    let mut other = Circuit::new();
+    let (x, y, out) = other.enter(|| {
+        let x = input_w::<8>();
+        let y = input_w::<8>();
+        (x, y, add_naive(y, x))
+    });
+    let other_inputs = [x.wires, y.wires].concat();
+    let other_outputs = [&out.sum.wires[..], &[out.carry]].concat();
+    let circuit: &Circuit = current();
+    let result =
+        circuit.check_equivalence(&inputs, &outputs, &other, &other_inputs, &other_outputs);
+    assert_eq!(Equivalence::Equivalent, result);
+}
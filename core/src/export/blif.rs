@@ -0,0 +1,248 @@
+use crate::export::{sanitize, wire_ident, ExportModuleInterface, ExportPort, Exporter};
+use crate::{ExportGateReg, RegExport};
+
+/// Berkeley Logic Interchange Format, for ABC and the tools that read it.
+///
+/// Every NAND becomes a `.names` cover and every reg a rising edge `.latch`, reset and enable
+/// are folded into one more cover in front of it. Externals become `.subckt` of `.blackbox`
+/// models.
+#[derive(Default)]
+pub struct BlifExporter {}
+
+/// `.names` with its on-set cubes, a constant 1 has the single cube `""`.
+fn names(inputs: &[&str], output: &str, cubes: &[&str]) -> String {
+    let signals = inputs.iter().chain([&output]).copied();
+    let mut lines = vec![format!(".names {}", signals.collect::<Vec<_>>().join(" "))];
+    lines.extend(
+        cubes
+            .iter()
+            .map(|cube| format!("{cube} 1").trim_start().to_string()),
+    );
+    lines.join("\n")
+}
+
+/// Next value of a reg with reset and enable, like `regs_write()` of the Verilog exporter.
+fn reg_next(
+    index: usize,
+    reg: &RegExport,
+    reset: Option<&str>,
+    w: &impl Fn(usize) -> String,
+) -> String {
+    let (wire_in, wire_out) = (w(reg.wire_in_index), w(reg.wire_out_index));
+    let enable = reg.enable_index.map(w);
+    let mut inputs = reset.into_iter().collect::<Vec<_>>();
+    inputs.extend(enable.as_deref());
+    inputs.push(&wire_in);
+    // cubes without the reset column: load when enabled, hold otherwise
+    let load = match enable {
+        None => vec!["1"],
+        Some(_) => {
+            inputs.push(&wire_out);
+            vec!["11-", "0-1"]
+        }
+    };
+    let cubes = match reset {
+        None => load.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+        Some(_) => {
+            let mut cubes = load.iter().map(|c| format!("0{c}")).collect::<Vec<_>>();
+            if reg.init == 1 {
+                cubes.push(format!("1{}", "-".repeat(load[0].len())));
+            }
+            cubes
+        }
+    };
+    let cubes = cubes.iter().map(String::as_str).collect::<Vec<_>>();
+    names(&inputs, &format!("r{index}_next"), &cubes)
+}
+
+fn blackbox(
+    module_name: &str,
+    inputs: &[ExportPort],
+    outputs: &[ExportPort],
+    clocked: bool,
+) -> String {
+    let bits = |ports: &[ExportPort]| {
+        let bits = ports.iter().flat_map(ExportPort::bits).map(|(bit, _)| bit);
+        bits.collect::<Vec<_>>().join(" ")
+    };
+    let clk = if clocked { "clk " } else { "" };
+    format!(
+        ".model {module_name}\n.inputs {clk}{}\n.outputs {}\n.blackbox\n.end\n",
+        bits(inputs),
+        bits(outputs)
+    )
+}
+
+impl Exporter for BlifExporter {
+    fn exporter_name() -> &'static str {
+        "Blif"
+    }
+
+    fn export(&self, interface: &ExportModuleInterface, content: &ExportGateReg) -> String {
+        let exporter_name = Self::exporter_name();
+        let module_name = interface.module_name;
+        let w = |index: usize| wire_ident(content, index);
+
+        // io, the clocks are inputs too unless a port already drives them
+
+        let mut inputs = interface
+            .inputs
+            .iter()
+            .flat_map(ExportPort::bits)
+            .map(|(bit, _)| bit)
+            .collect::<Vec<_>>();
+        let domain_clk = |domain: usize| match domain {
+            0 => interface.clk.to_string(),
+            _ => sanitize(&content.clock_domains[domain]),
+        };
+        for domain in 0..content.clock_domains.len() {
+            let clk = domain_clk(domain);
+            if !inputs.contains(&clk) {
+                inputs.push(clk);
+            }
+        }
+        let outputs = interface
+            .outputs
+            .iter()
+            .flat_map(ExportPort::bits)
+            .map(|(bit, _)| bit)
+            .collect::<Vec<_>>();
+
+        let mut lines = vec![
+            format!("# exported from {exporter_name}"),
+            format!(".model {module_name}"),
+            format!(".inputs {}", inputs.join(" ")),
+            format!(".outputs {}", outputs.join(" ")),
+            "# wire01".to_string(),
+        ];
+        for (wire, value) in [(0, content.wire_0_value), (1, content.wire_1_value)] {
+            let cubes: &[&str] = if value == 1 { &[""] } else { &[] };
+            lines.push(names(&[], &w(wire), cubes));
+        }
+        lines.push("# inputs".to_string());
+        for (bit, wire) in interface.inputs.iter().flat_map(ExportPort::bits) {
+            lines.push(names(&[&bit], &w(wire), &["1"]));
+        }
+
+        // logic
+
+        lines.push("# gates".to_string());
+        let mut scope = 0;
+        for gate in &content.gates {
+            if gate.scope != scope {
+                scope = gate.scope;
+                lines.push(format!("# scope {}", content.scopes[scope]));
+            }
+            let (a, b, out) = (
+                w(gate.wire_a_index),
+                w(gate.wire_b_index),
+                w(gate.wire_out_index),
+            );
+            lines.push(match a == b {
+                true => names(&[&a], &out, &["0"]),
+                false => names(&[&a, &b], &out, &["0-", "-0"]),
+            });
+        }
+
+        lines.push("# regs".to_string());
+        let reset = content.reset_index.map(w);
+        for (index, reg) in content.regs.iter().enumerate() {
+            let next = match (reset.is_some(), reg.enable_index.is_some()) {
+                (false, false) => w(reg.wire_in_index),
+                _ => {
+                    lines.push(reg_next(index, reg, reset.as_deref(), &w));
+                    format!("r{index}_next")
+                }
+            };
+            lines.push(format!(
+                ".latch {next} {} re {} {}",
+                w(reg.wire_out_index),
+                domain_clk(reg.domain),
+                reg.init
+            ));
+        }
+
+        let mut blackboxes = vec![];
+        for (index, external) in content.externals.iter().enumerate() {
+            let ports = &external.ports;
+            let module_name = sanitize(&ports.module_name);
+            lines.push(format!("# external {index}"));
+            let clk = ports.clocked.then(|| format!("clk={}", domain_clk(0)));
+            let connections = (ports.inputs.iter().chain(&ports.outputs))
+                .flat_map(ExportPort::bits)
+                .map(|(bit, wire)| format!("{bit}={}", w(wire)));
+            let connections = clk.into_iter().chain(connections).collect::<Vec<_>>();
+            lines.push(format!(".subckt {module_name} {}", connections.join(" ")));
+            let model = blackbox(&module_name, &ports.inputs, &ports.outputs, ports.clocked);
+            if !blackboxes.contains(&model) {
+                blackboxes.push(model);
+            }
+        }
+
+        lines.push("# outputs".to_string());
+        for (bit, wire) in interface.outputs.iter().flat_map(ExportPort::bits) {
+            lines.push(names(&[&w(wire)], &bit, &["1"]));
+        }
+        lines.push(".end\n".to_string());
+
+        let mut output = lines.join("\n");
+        for model in blackboxes {
+            output.push('\n');
+            output.push_str(&model);
+        }
+        output
+    }
+}
+
+#[test]
+fn test_blif() {
+    use crate::*;
+    clear_all();
+    let reset = reset_signal().named("reset");
+    let enable = input();
+    let a = input_w::<2>();
+    let count = reg_w::<2>();
+    count.set_in(add_naive(count.out, Wires::parse_u8(1)).sum);
+    count.set_enable(enable);
+    let flag = reg_with_init(1);
+    flag.set_in(!a.wires[0]);
+    let out = a.wires[0] & a.wires[1];
+
+    let content = export_gate_reg();
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("blif_test")
+        .clk("clk")
+        .input_wire("Reset", reset)
+        .input_wire("Enable", enable)
+        .input_wires("A", a)
+        .output_wires("Count", count.out)
+        .output_wire("Out", out);
+
+    let blif = BlifExporter::default().export(&interface, &content);
+    let w = |wire: Wire| wire_ident(&content, wire.0);
+    assert!(blif.contains(".model blif_test\n.inputs Reset Enable A[0] A[1] clk\n"));
+    assert!(blif.contains(".outputs Count[0] Count[1] Out\n"));
+    assert!(blif.contains(".names w0\n.names w1\n1\n"));
+    assert!(blif.contains(&format!(".names A[1] {}\n1 1\n", w(a.wires[1]))));
+    // the and is a nand and a not
+    let (a0, a1, nand) = (w(a.wires[0]), w(a.wires[1]), out.0 - 1);
+    assert!(blif.contains(&format!(".names {a0} {a1} w{nand}\n0- 1\n-0 1\n")));
+    assert!(blif.contains(&format!(".names w{nand} w{}\n0 1\n", out.0)));
+    // reset over enable, hold when disabled
+    let (reset, enable) = (w(reset), w(enable));
+    let count_out = w(count.out.wires[0]);
+    assert!(blif.contains(&format!(
+        ".names {reset} {enable} w{} {count_out} r0_next\n011- 1\n00-1 1\n\
+         .latch r0_next {count_out} re clk 0\n",
+        content.regs[0].wire_in_index
+    )));
+    // init 1 comes back on reset
+    assert!(blif.contains(&format!(
+        ".names {reset} w{} r2_next\n01 1\n1- 1\n.latch r2_next {} re clk 1\n",
+        content.regs[2].wire_in_index,
+        w(flag.out())
+    )));
+    assert!(blif.contains(&format!(".names {} Out\n1 1\n", w(out))));
+    assert!(blif.ends_with(".end\n"));
+}
@@ -0,0 +1,243 @@
+use crate::{ExportGateReg, GateExport};
+
+/// A wire, possibly inverted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Literal {
+    pub wire: usize,
+    pub inverted: bool,
+}
+
+/// What a gate output computes, with the NAND gates it absorbed already left out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Expression {
+    Nand(usize, usize),
+    Not(usize),
+    And(usize, usize),
+    Or(Literal, Literal),
+    Xor(usize, usize),
+    Mux { select: usize, a: usize, b: usize }, // select ? b : a, like `mux2()`
+}
+
+struct Matcher<'a> {
+    gates: &'a [GateExport],
+    driver: Vec<Option<usize>>, // gate of each wire
+    fanout: Vec<usize>,         // readers, wires that have to stay visible count one more
+    visible: Vec<bool>,         // gates not absorbed into an expression
+}
+
+impl<'a> Matcher<'a> {
+    /// Driving gate of a wire that only `readers` gates of the current pattern read.
+    fn inner(&self, wire: usize, readers: usize) -> Option<(usize, &'a GateExport)> {
+        let gate = self.driver[wire]?;
+        (self.fanout[wire] == readers && self.visible[gate]).then_some((gate, &self.gates[gate]))
+    }
+
+    /// Input of a NOT gate driving `wire`.
+    fn not_input(&self, wire: usize) -> Option<usize> {
+        let gate = &self.gates[self.driver[wire]?];
+        (gate.wire_a_index == gate.wire_b_index).then_some(gate.wire_a_index)
+    }
+
+    /// Skip pairs of inner NOT gates, `!!x` is `x`.
+    fn strip_double_not(&self, mut wire: usize, absorbed: &mut Vec<usize>) -> usize {
+        while let Some((outer, gate)) = self.inner(wire, 1) {
+            let Some((inner, inner_gate)) = self.inner(gate.wire_a_index, 1) else { break };
+            if gate.wire_a_index != gate.wire_b_index
+                || inner_gate.wire_a_index != inner_gate.wire_b_index
+            {
+                break;
+            }
+            absorbed.extend([outer, inner]);
+            wire = inner_gate.wire_a_index;
+        }
+        wire
+    }
+
+    fn literal(&self, wire: usize, absorbed: &mut Vec<usize>) -> Literal {
+        match self.inner(wire, 1) {
+            Some((gate, g)) if g.wire_a_index == g.wire_b_index => {
+                absorbed.push(gate);
+                Literal {
+                    wire: g.wire_a_index,
+                    inverted: false,
+                }
+            }
+            _ => Literal {
+                wire,
+                inverted: true,
+            },
+        }
+    }
+
+    fn xor(&self, a: usize, b: usize, absorbed: &mut Vec<usize>) -> Option<Expression> {
+        let (gate2, n2) = self.inner(a, 1)?;
+        let (gate3, n3) = self.inner(b, 1)?;
+        let [p, n1] = [n2.wire_a_index, n2.wire_b_index];
+        for (p, n1) in [(p, n1), (n1, p)] {
+            let Some((gate1, g1)) = self.inner(n1, 2) else { continue };
+            let q = if n3.wire_a_index == n1 {
+                n3.wire_b_index
+            } else if n3.wire_b_index == n1 {
+                n3.wire_a_index
+            } else {
+                continue;
+            };
+            let pair = [g1.wire_a_index, g1.wire_b_index];
+            if p != q && (pair == [p, q] || pair == [q, p]) {
+                absorbed.extend([gate1, gate2, gate3]);
+                return Some(Expression::Xor(p, q));
+            }
+        }
+        None
+    }
+
+    fn mux(&self, a: usize, b: usize, absorbed: &mut Vec<usize>) -> Option<Expression> {
+        let mut chain = vec![];
+        let x = self.strip_double_not(a, &mut chain);
+        let y = self.strip_double_not(b, &mut chain);
+        let (gate_x, gx) = self.inner(x, 1)?;
+        let (gate_y, gy) = self.inner(y, 1)?;
+        for (gx, gy) in [(gx, gy), (gy, gx)] {
+            // gx = nand(data0, !select), gy = nand(data1, select)
+            for (data0, not_select) in [
+                (gx.wire_a_index, gx.wire_b_index),
+                (gx.wire_b_index, gx.wire_a_index),
+            ] {
+                let Some(select) = self.not_input(not_select) else { continue };
+                let data1 = if gy.wire_a_index == select {
+                    gy.wire_b_index
+                } else if gy.wire_b_index == select {
+                    gy.wire_a_index
+                } else {
+                    continue;
+                };
+                absorbed.extend(chain);
+                absorbed.extend([gate_x, gate_y]);
+                if let Some((gate, _)) = self.inner(not_select, 1) {
+                    absorbed.push(gate);
+                }
+                return Some(Expression::Mux {
+                    select,
+                    a: data0,
+                    b: data1,
+                });
+            }
+        }
+        None
+    }
+
+    fn expression(&self, gate: &GateExport, absorbed: &mut Vec<usize>) -> Expression {
+        let (a, b) = (gate.wire_a_index, gate.wire_b_index);
+        if a == b {
+            return match self.inner(a, 1) {
+                Some((inner, g)) if g.wire_a_index != g.wire_b_index => {
+                    absorbed.push(inner);
+                    Expression::And(g.wire_a_index, g.wire_b_index)
+                }
+                _ => Expression::Not(a),
+            };
+        }
+        if let Some(xor) = self.xor(a, b, absorbed) {
+            return xor;
+        }
+        if let Some(mux) = self.mux(a, b, absorbed) {
+            return mux;
+        }
+        let mut literals = vec![];
+        let (x, y) = (
+            self.literal(a, &mut literals),
+            self.literal(b, &mut literals),
+        );
+        if literals.is_empty() {
+            Expression::Nand(a, b)
+        } else {
+            absorbed.extend(literals);
+            Expression::Or(x, y)
+        }
+    }
+}
+
+/// Expression of every gate that stays visible, `None` for gates absorbed into another one.
+/// Wires in `keep` (ports, reg inputs, named wires...) are never absorbed.
+pub(crate) fn gate_expressions(content: &ExportGateReg, keep: &[usize]) -> Vec<Option<Expression>> {
+    let mut driver = vec![None; content.wire_count];
+    let mut fanout = vec![0; content.wire_count];
+    for (index, gate) in content.gates.iter().enumerate() {
+        driver[gate.wire_out_index] = Some(index);
+        fanout[gate.wire_a_index] += 1;
+        if gate.wire_b_index != gate.wire_a_index {
+            fanout[gate.wire_b_index] += 1;
+        }
+    }
+    for wire in keep {
+        fanout[*wire] += 1;
+    }
+    let mut matcher = Matcher {
+        gates: &content.gates,
+        driver,
+        fanout,
+        visible: vec![true; content.gates.len()],
+    };
+
+    // readers first, so each pattern absorbs the gates in front of it
+    let mut expressions = vec![None; content.gates.len()];
+    for (index, gate) in content.gates.iter().enumerate().rev() {
+        if !matcher.visible[index] {
+            continue;
+        }
+        let mut absorbed = vec![];
+        expressions[index] = Some(matcher.expression(gate, &mut absorbed));
+        for gate in absorbed {
+            matcher.visible[gate] = false;
+        }
+    }
+    expressions
+}
+
+#[test]
+fn test_gate_expressions() {
+    use crate::*;
+    clear_all();
+
+    // fresh inputs for each pattern, deduplicated NANDs would be shared otherwise
+    let [a0, b0, a1, a2, b2, a3, b3, a4, b4, a5, b5, s5] = [(); 12].map(|_| input());
+    let not_a1 = !a1;
+    let outputs = [
+        nand(a0, b0),
+        not_a1,
+        a2 & b2,
+        a3 | b3,
+        a4 ^ b4,
+        mux2(a5, b5, s5),
+        nand(not_a1, b0),
+    ];
+    let content = export_gate_reg();
+    let expressions = gate_expressions(&content, &outputs.map(|w| w.0));
+    let of = |wire: Wire| {
+        let gate = content
+            .gates
+            .iter()
+            .position(|g| g.wire_out_index == wire.0)
+            .unwrap();
+        expressions[gate].unwrap()
+    };
+    let lit = |wire: Wire| Literal {
+        wire: wire.0,
+        inverted: false,
+    };
+    assert_eq!(Expression::Nand(a0.0, b0.0), of(outputs[0]));
+    assert_eq!(Expression::Not(a1.0), of(outputs[1]));
+    assert_eq!(Expression::And(a2.0, b2.0), of(outputs[2]));
+    assert_eq!(Expression::Or(lit(a3), lit(b3)), of(outputs[3]));
+    assert_eq!(Expression::Xor(a4.0, b4.0), of(outputs[4]));
+    let mux = Expression::Mux {
+        select: s5.0,
+        a: a5.0,
+        b: b5.0,
+    };
+    assert_eq!(mux, of(outputs[5]));
+    // !a1 is an output too, so it is not absorbed
+    assert_eq!(Expression::Nand(not_a1.0, b0.0), of(outputs[6]));
+    let visible = expressions.iter().filter(|e| e.is_some()).count();
+    assert_eq!(outputs.len(), visible);
+}
@@ -1,16 +1,67 @@
+mod blif;
+mod expressions;
+mod testbench;
 mod verilog_module;
+mod yosys_json;
+
+pub use blif::BlifExporter;
+pub use testbench::VerilogTestbench;
+pub use verilog_module::VerilogModuleExporter;
+pub use yosys_json::YosysJsonExporter;
 
 use crate::{ExportGateReg, Wire, Wires};
 
+/// A scalar port, or a vector port with bit `i` in `wires[i]`.
+pub struct ExportPort {
+    name: String,
+    wires: Vec<Wire>,
+    is_bus: bool,
+}
+
+impl ExportPort {
+    fn scalar(name: &str, wire: Wire) -> Self {
+        Self {
+            name: name.to_string(),
+            wires: vec![wire],
+            is_bus: false,
+        }
+    }
+    fn bus(name: &str, wires: &[Wire]) -> Self {
+        Self {
+            name: name.to_string(),
+            wires: wires.to_vec(),
+            is_bus: true,
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn wires(&self) -> &[Wire] {
+        &self.wires
+    }
+    pub fn is_bus(&self) -> bool {
+        self.is_bus
+    }
+    /// Each bit with its wire index, named `name[i]` in a vector port.
+    pub fn bits(&self) -> impl Iterator<Item = (String, usize)> + '_ {
+        self.wires
+            .iter()
+            .enumerate()
+            .map(|(i, wire)| match self.is_bus {
+                false => (self.name.clone(), wire.0),
+                true => (format!("{}[{i}]", self.name), wire.0),
+            })
+    }
+}
+
 #[derive(Default)]
 pub struct ExportModuleInterface {
     module_name: &'static str,
     clk: &'static str,
-    input_wires: Vec<(String, Wire)>,
-    output_wires: Vec<(String, Wire)>,
+    inputs: Vec<ExportPort>,
+    outputs: Vec<ExportPort>,
 }
 
-#[allow(unused)]
 impl ExportModuleInterface {
     pub fn module_name(&mut self, module_name: &'static str) -> &mut Self {
         self.module_name = module_name;
@@ -21,7 +72,7 @@ impl ExportModuleInterface {
         self
     }
     pub fn input_wire(&mut self, name: &'static str, wire: Wire) -> &mut Self {
-        self.input_wires.push((name.to_string(), wire));
+        self.inputs.push(ExportPort::scalar(name, wire));
         self
     }
     pub fn input_wires<const T: usize>(
@@ -29,14 +80,11 @@ impl ExportModuleInterface {
         name: &'static str,
         wires: Wires<T>,
     ) -> &mut Self {
-        for i in 0..T {
-            self.input_wires
-                .push((format!("{name}_{i}"), wires.wires[i]));
-        }
+        self.inputs.push(ExportPort::bus(name, &wires.wires));
         self
     }
     pub fn output_wire(&mut self, name: &'static str, wire: Wire) -> &mut Self {
-        self.output_wires.push((name.to_string(), wire));
+        self.outputs.push(ExportPort::scalar(name, wire));
         self
     }
     pub fn output_wires<const T: usize>(
@@ -44,15 +92,98 @@ impl ExportModuleInterface {
         name: &'static str,
         wires: Wires<T>,
     ) -> &mut Self {
-        for i in 0..T {
-            self.output_wires
-                .push((format!("{name}_{i}"), wires.wires[i]));
+        self.outputs.push(ExportPort::bus(name, &wires.wires));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        self.module_name
+    }
+    pub fn clk_name(&self) -> &str {
+        self.clk
+    }
+    pub fn inputs(&self) -> &[ExportPort] {
+        &self.inputs
+    }
+    pub fn outputs(&self) -> &[ExportPort] {
+        &self.outputs
+    }
+}
+
+/// Black-box module standing for an external in exports, see `External::export_ports()`.
+///
+/// Inputs are the wires the external reads, outputs the wires it sets. The module itself is
+/// not exported, it has to come with the synthesis sources.
+pub struct ExternalPorts {
+    module_name: String,
+    inputs: Vec<ExportPort>,
+    outputs: Vec<ExportPort>,
+    clocked: bool,
+}
+
+impl ExternalPorts {
+    pub fn new(module_name: &str) -> Self {
+        Self {
+            module_name: module_name.to_string(),
+            inputs: vec![],
+            outputs: vec![],
+            clocked: false,
         }
+    }
+    pub fn module_name(&self) -> &str {
+        &self.module_name
+    }
+    pub fn inputs(&self) -> &[ExportPort] {
+        &self.inputs
+    }
+    pub fn outputs(&self) -> &[ExportPort] {
+        &self.outputs
+    }
+    pub fn is_clocked(&self) -> bool {
+        self.clocked
+    }
+    /// The module keeps state and gets the default clock as `clk`.
+    pub fn clocked(&mut self) -> &mut Self {
+        self.clocked = true;
+        self
+    }
+    pub fn input_wire(&mut self, name: &str, wire: Wire) -> &mut Self {
+        self.inputs.push(ExportPort::scalar(name, wire));
+        self
+    }
+    pub fn input_wires(&mut self, name: &str, wires: &[Wire]) -> &mut Self {
+        self.inputs.push(ExportPort::bus(name, wires));
+        self
+    }
+    pub fn output_wire(&mut self, name: &str, wire: Wire) -> &mut Self {
+        self.outputs.push(ExportPort::scalar(name, wire));
+        self
+    }
+    pub fn output_wires(&mut self, name: &str, wires: &[Wire]) -> &mut Self {
+        self.outputs.push(ExportPort::bus(name, wires));
         self
     }
 }
 
-trait Exporter {
+/// Writes the netlist of `export_gate_reg()` as one module with the ports of `interface`.
+pub trait Exporter {
     fn exporter_name() -> &'static str;
     fn export(&self, interface: &ExportModuleInterface, content: &ExportGateReg) -> String;
 }
+
+/// Identifier characters only, for names that come from `named()` and clock domains.
+pub(crate) fn sanitize(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    name.trim_end_matches('_').to_string()
+}
+
+/// `w{index}`, with the sanitized hierarchical name appended for named wires.
+pub(crate) fn wire_ident(content: &ExportGateReg, index: usize) -> String {
+    match content.wire_names.get(&index) {
+        None => format!("w{index}"),
+        Some(name) => format!("w{index}_{}", sanitize(name)),
+    }
+}
@@ -0,0 +1,242 @@
+use crate::export::{sanitize, ExportModuleInterface, ExportPort, Exporter};
+use crate::{clock_tick, current, execute_gates, ExportGateReg, WireValue, VALUE_X, VALUE_Z};
+
+/// Port values of one cycle, in interface order.
+struct TestbenchCycle {
+    inputs: Vec<Vec<WireValue>>,
+    outputs: Vec<Vec<WireValue>>,
+    ticks: u64, // mask of the clock domains that ticked
+}
+
+/// Records a simulation run and exports it as a self-checking testbench for the module that
+/// `VerilogModuleExporter` exports with the same interface.
+///
+/// Every cycle drives the recorded inputs, checks the outputs before the rising clock edge, and
+/// `$display`s each mismatch. The default domain runs on the interface `clk`, every other domain
+/// on its own clock that only rises in the cycles the domain ticked in. Record from the state the
+/// module starts in (reg init values), externals have to come with their black-box modules.
+#[derive(Default)]
+pub struct VerilogTestbench {
+    cycles: Vec<TestbenchCycle>,
+}
+
+fn sample(ports: &[ExportPort]) -> Vec<Vec<WireValue>> {
+    let port_values = |port: &ExportPort| port.wires.iter().map(|w| w.get()).collect();
+    ports.iter().map(port_values).collect()
+}
+
+/// `4'b0101`, highest bit first like the port declaration.
+fn literal(values: &[WireValue]) -> String {
+    let bits = values
+        .iter()
+        .rev()
+        .map(|value| match *value {
+            0 => '0',
+            1 => '1',
+            VALUE_X => 'x',
+            VALUE_Z => 'z',
+            _ => unreachable!(),
+        })
+        .collect::<String>();
+    format!("{}'b{bits}", values.len())
+}
+
+impl VerilogTestbench {
+    /// `simulate()` that records the interface ports: the inputs as set, the outputs after
+    /// `execute_gates()`, that is before the clock edge.
+    pub fn record(&mut self, interface: &ExportModuleInterface) {
+        execute_gates();
+        self.cycles.push(TestbenchCycle {
+            inputs: sample(&interface.inputs),
+            outputs: sample(&interface.outputs),
+            ticks: current().clock_domains.upcoming(1)[0],
+        });
+        clock_tick();
+    }
+
+    pub fn cycle_count(&self) -> usize {
+        self.cycles.len()
+    }
+}
+
+impl Exporter for VerilogTestbench {
+    fn exporter_name() -> &'static str {
+        "VerilogTestbench"
+    }
+
+    fn export(&self, interface: &ExportModuleInterface, content: &ExportGateReg) -> String {
+        let exporter_name = Self::exporter_name();
+        let module_name = interface.module_name;
+        let cycle_count = self.cycles.len();
+
+        let declare = |kind: &str, port: &ExportPort| match port.is_bus {
+            false => format!("{kind} {};", port.name),
+            true => format!("{kind} [{}:0] {};", port.wires.len() - 1, port.name),
+        };
+        // named like `VerilogModuleExporter` does, the clock may also be an input port
+        let clk = interface.clk;
+        let domain_clk = |domain: usize| match domain {
+            0 => clk.to_string(),
+            _ => sanitize(&content.clock_domains[domain]),
+        };
+        let domains = 0..content.clock_domains.len();
+        let inputs = interface.inputs.iter().filter(|port| port.name != clk);
+        let clocks_declare = domains
+            .clone()
+            .map(|domain| format!("reg {} = 0;\n", domain_clk(domain)))
+            .collect::<String>();
+        let ports_declare = (inputs.clone().map(|port| declare("reg", port)))
+            .chain(interface.outputs.iter().map(|port| declare("wire", port)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let ports_connect = (interface.inputs.iter().chain(&interface.outputs))
+            .map(|port| format!("    .{0}({0}),\n", port.name))
+            .chain(domains.clone().skip(1).map(|domain| {
+                let domain_clk = domain_clk(domain);
+                format!("    .{domain_clk}({domain_clk}),\n")
+            }))
+            .collect::<String>();
+        let clocks = |ticks: u64, value: u8| {
+            let ticked = domains.clone().filter(|domain| (ticks >> domain) & 1 == 1);
+            let clocks = ticked.map(|domain| format!("    {} = {value};", domain_clk(domain)));
+            clocks.collect::<Vec<_>>().join("\n")
+        };
+
+        let mut stimulus = vec![];
+        for (index, cycle) in self.cycles.iter().enumerate() {
+            stimulus.push(format!("    // cycle {index}"));
+            for (port, values) in interface.inputs.iter().zip(&cycle.inputs) {
+                if port.name == clk {
+                    continue;
+                }
+                stimulus.push(format!("    {} = {};", port.name, literal(values)));
+            }
+            stimulus.push("    #1;".to_string());
+            for (port, values) in interface.outputs.iter().zip(&cycle.outputs) {
+                let (name, expected) = (&port.name, literal(values));
+                stimulus.push(format!(
+                    "    if ({name} !== {expected}) begin\n        \
+                     $display(\"cycle {index}: {name} = %b, expected {expected}\", {name});\n        \
+                     errors = errors + 1;\n    end"
+                ));
+            }
+            stimulus.push(clocks(cycle.ticks, 1));
+            stimulus.push("    #1;".to_string());
+            stimulus.push(clocks(cycle.ticks, 0));
+        }
+        let stimulus = stimulus.join("\n");
+
+        format!(
+            "// exported from {exporter_name}, {cycle_count} cycles
+`timescale 1ns / 1ps
+module {module_name}_tb;
+
+{clocks_declare}{ports_declare}
+integer errors = 0;
+
+{module_name} dut(
+{ports_connect}    .clk({clk}));
+
+initial begin
+{stimulus}
+
+    if (errors == 0)
+        $display(\"PASS {module_name}: {cycle_count} cycles\");
+    else
+        $display(\"FAIL {module_name}: %0d mismatches\", errors);
+    $finish;
+end
+
+endmodule
+"
+        )
+    }
+}
+
+#[test]
+fn test_testbench() {
+    use crate::*;
+    clear_all();
+    let step = input_w::<2>();
+    let count = reg_w::<3>().named("count");
+    let [step0, step1] = step.wires;
+    let sum = add_naive(
+        count.out,
+        Wires {
+            wires: [step0, step1, Wire(WIRE_0)],
+        },
+    )
+    .sum;
+    count.set_in(sum);
+    let odd = count.out.wires[0];
+
+    let content = export_gate_reg();
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("counter")
+        .clk("clk")
+        .input_wires("Step", step)
+        .output_wires("Next", sum)
+        .output_wire("Odd", odd);
+
+    let mut testbench = VerilogTestbench::default();
+    for i in 0..4 {
+        step.set_u8(i % 3 + 1);
+        testbench.record(&interface);
+    }
+    assert_eq!(4, testbench.cycle_count());
+    assert_eq!(1 + 2 + 3 + 1, count.out.get_u8());
+
+    let verilog_output = testbench.export(&interface, &content);
+    println!("{verilog_output}");
+    assert!(verilog_output.contains("module counter_tb;\n"));
+    assert!(verilog_output.contains("reg [1:0] Step;\nwire [2:0] Next;\nwire Odd;\n"));
+    assert!(verilog_output.contains("counter dut(\n    .Step(Step),\n    .Next(Next),\n"));
+    // count is 3 when cycle 2 adds 3
+    assert!(verilog_output.contains(
+        "    // cycle 2\n    Step = 2'b11;\n    #1;\n    if (Next !== 3'b110) begin\n        \
+         $display(\"cycle 2: Next = %b, expected 3'b110\", Next);\n"
+    ));
+    assert!(verilog_output.contains("    if (Odd !== 1'b1) begin"));
+    assert!(verilog_output.contains("    clk = 1;\n    #1;\n    clk = 0;\n"));
+    assert_eq!(4, verilog_output.matches("clk = 1;").count());
+}
+
+#[test]
+fn test_testbench_clock_domains() {
+    use crate::*;
+    clear_all();
+    let slow = add_clock_domain("slow.clk", 1, 2);
+    let clock = input();
+    let fast = reg_w::<2>();
+    fast.set_in(add_naive(fast.out, Wires::parse_u8(1)).sum);
+    let slow_reg = clock_domain(slow, reg);
+    slow_reg.set_in(!slow_reg.out());
+
+    let content = export_gate_reg();
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("domains")
+        .clk("Clock")
+        .input_wire("Clock", clock)
+        .output_wires("Fast", fast.out)
+        .output_wire("Slow", slow_reg.out());
+
+    let mut testbench = VerilogTestbench::default();
+    for _ in 0..4 {
+        testbench.record(&interface);
+    }
+    assert_eq!((0, 0), (fast.out.get_u8(), slow_reg.out().get()));
+
+    let verilog_output = testbench.export(&interface, &content);
+    assert!(verilog_output.contains("reg Clock = 0;\nreg slow_clk = 0;\nwire [1:0] Fast;\n"));
+    assert!(verilog_output.contains(
+        "domains dut(\n    .Clock(Clock),\n    .Fast(Fast),\n    .Slow(Slow),\n    \
+         .slow_clk(slow_clk),\n    .clk(Clock));\n"
+    ));
+    assert!(!verilog_output.contains("Clock = 1'b"));
+    // the slow domain ticks every other cycle, starting with the second
+    assert!(verilog_output.contains("    Clock = 1;\n    slow_clk = 1;\n    #1;\n"));
+    assert_eq!(4, verilog_output.matches("Clock = 1;").count());
+    assert_eq!(2, verilog_output.matches("slow_clk = 1;").count());
+}
@@ -1,7 +1,119 @@
-use crate::export::{ExportModuleInterface, Exporter};
-use crate::ExportGateReg;
+use crate::export::expressions::{gate_expressions, Expression, Literal};
+use crate::export::{sanitize, wire_ident, ExportModuleInterface, ExportPort, Exporter};
+use crate::{ExportGateReg, ExternalExport, RegExport};
 
-pub struct VerilogModuleExporter {}
+#[derive(Default)]
+pub struct VerilogModuleExporter {
+    readable_expressions: bool,
+}
+
+impl VerilogModuleExporter {
+    /// Emit AND/OR/XOR/NOT/MUX expressions recognized in the NAND structure instead of one
+    /// `!(a & b)` per gate. Ports, reg inputs and named wires stay visible either way.
+    pub fn readable_expressions(&mut self, readable_expressions: bool) -> &mut Self {
+        self.readable_expressions = readable_expressions;
+        self
+    }
+}
+
+/// `input name` or `input [T-1:0] name`.
+fn port_declare(direction: &str, port: &ExportPort) -> String {
+    match port.is_bus {
+        false => format!("    {direction} {},", port.name),
+        true => format!(
+            "    {direction} [{}:0] {},",
+            port.wires.len() - 1,
+            port.name
+        ),
+    }
+}
+
+/// `w3` for a scalar port, `{w5, w4}` with the highest bit first for a vector port.
+fn port_connect(port: &ExportPort, w: &impl Fn(usize) -> String) -> String {
+    match port.is_bus {
+        false => w(port.wires[0].0),
+        true => {
+            let bits = port.wires.iter().rev().map(|wire| w(wire.0));
+            format!("{{{}}}", bits.collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+/// Instance of the black box of an external, named `ext{index}`.
+fn external_instance(
+    index: usize,
+    external: &ExternalExport,
+    clk: &str,
+    scopes: &[String],
+    w: &impl Fn(usize) -> String,
+) -> String {
+    let ports = &external.ports;
+    let clk = ports.clocked.then(|| format!("    .clk({clk})"));
+    let connections = (ports.inputs.iter().chain(&ports.outputs))
+        .map(|port| format!("    .{}({})", port.name, port_connect(port, w)));
+    let connections = clk.into_iter().chain(connections).collect::<Vec<_>>();
+    let scope = match scopes[external.scope].as_str() {
+        "" => String::new(),
+        scope => format!(", scope {scope}"),
+    };
+    format!(
+        "// external {index}{scope}\n{} ext{index} (\n{}\n);",
+        sanitize(&ports.module_name),
+        connections.join(",\n")
+    )
+}
+
+fn expression(expression: Expression, w: &impl Fn(usize) -> String) -> String {
+    let literal = |literal: Literal| match literal.inverted {
+        false => w(literal.wire),
+        true => format!("~{}", w(literal.wire)),
+    };
+    match expression {
+        Expression::Nand(a, b) => format!("~({} & {})", w(a), w(b)),
+        Expression::Not(a) => format!("~{}", w(a)),
+        Expression::And(a, b) => format!("{} & {}", w(a), w(b)),
+        Expression::Or(a, b) => format!("{} | {}", literal(a), literal(b)),
+        Expression::Xor(a, b) => format!("{} ^ {}", w(a), w(b)),
+        Expression::Mux { select, a, b } => format!("{} ? {} : {}", w(select), w(b), w(a)),
+    }
+}
+
+/// Body of one `always` block, a synchronous reset loads the init values.
+fn regs_write(
+    regs: &[(usize, &RegExport)],
+    reset: Option<String>,
+    w: &impl Fn(usize) -> String,
+) -> String {
+    let regs_load = regs
+        .iter()
+        .map(|(index, reg)| match reg.enable_index {
+            None => format!("    r{index} <= {};", w(reg.wire_in_index)),
+            Some(enable) => format!(
+                "    if ({}) r{index} <= {};",
+                w(enable),
+                w(reg.wire_in_index)
+            ),
+        })
+        .collect::<Vec<_>>();
+    match reset {
+        None => regs_load.join("\n"),
+        Some(reset) => {
+            let regs_reset = regs
+                .iter()
+                .map(|(index, reg)| format!("        r{index} <= 1'b{};", reg.init))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let regs_load = regs_load
+                .iter()
+                .map(|line| format!("    {line}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                "    if ({reset}) begin\n{regs_reset}\n    end else begin\n{regs_load}\n    end"
+            )
+        }
+    }
+}
 
 impl Exporter for VerilogModuleExporter {
     fn exporter_name() -> &'static str {
@@ -15,20 +127,21 @@ impl Exporter for VerilogModuleExporter {
         // io
 
         let inputs = interface
-            .input_wires
+            .inputs
             .iter()
-            .map(|(name, _)| format!("    input {name},"))
+            .map(|port| port_declare("input", port))
             .collect::<Vec<_>>()
             .join("\n");
         let outputs = interface
-            .output_wires
+            .outputs
             .iter()
-            .map(|(name, _)| format!("    output {name},"))
+            .map(|port| port_declare("output", port))
             .collect::<Vec<_>>()
             .join("\n");
 
         // wire/reg declaration
 
+        let w = |index: usize| wire_ident(content, index);
         let wires01 = format!(
             "wire w0 = 1'b{};\nwire w1 = 1'b{};",
             content.wire_0_value, content.wire_1_value
@@ -44,16 +157,17 @@ impl Exporter for VerilogModuleExporter {
             .regs
             .iter()
             .enumerate()
-            .map(|(index, _)| format!("reg r{index} = 1'b0;"))
+            .map(|(index, reg)| format!("reg r{index} = 1'b{};", reg.init))
             .collect::<Vec<_>>()
             .join("\n");
 
         // input
 
         let input_assign = interface
-            .input_wires
+            .inputs
             .iter()
-            .map(|(name, wire)| format!("wire w{} = {name};", wire.0))
+            .flat_map(ExportPort::bits)
+            .map(|(bit, wire)| format!("wire {} = {bit};", w(wire)))
             .collect::<Vec<_>>()
             .join("\n");
 
@@ -61,49 +175,113 @@ impl Exporter for VerilogModuleExporter {
             .regs
             .iter()
             .enumerate()
-            .map(|(index, reg)| format!("wire w{} = r{index};", reg.wire_out_index))
+            .map(|(index, reg)| format!("wire {} = r{index};", w(reg.wire_out_index)))
             .collect::<Vec<_>>()
             .join("\n");
 
+        // externals set their outputs, as black boxes the wires need a declaration
+        let externals_read = content
+            .externals
+            .iter()
+            .flat_map(|external| &external.ports.outputs)
+            .flat_map(|port| &port.wires)
+            .map(|wire| format!("wire {};\n", w(wire.0)))
+            .collect::<String>();
+        let externals_read = match externals_read.is_empty() {
+            true => externals_read,
+            false => format!("// externals read\n{externals_read}"),
+        };
+
         // logic
 
-        let gates = content
-            .gates
+        let expressions = match self.readable_expressions {
+            false => vec![],
+            true => {
+                let ports = interface.inputs.iter().chain(&interface.outputs);
+                let keep = ports
+                    .flat_map(|port| port.wires.iter().map(|wire| wire.0))
+                    .chain(content.regs.iter().map(|reg| reg.wire_in_index))
+                    .chain(content.regs.iter().filter_map(|reg| reg.enable_index))
+                    .chain(content.reset_index)
+                    .chain(content.externals.iter().flat_map(|external| {
+                        let inputs = external.ports.inputs.iter();
+                        inputs.flat_map(|port| port.wires.iter().map(|wire| wire.0))
+                    }))
+                    .chain(content.wire_names.keys().copied())
+                    .collect::<Vec<_>>();
+                gate_expressions(content, &keep)
+            }
+        };
+        let mut gates = vec![];
+        let mut scope = 0;
+        for (index, gate) in content.gates.iter().enumerate() {
+            let value = match expressions.get(index) {
+                None => format!("!({} & {})", w(gate.wire_a_index), w(gate.wire_b_index)),
+                Some(None) => continue, // absorbed into a reader
+                Some(Some(e)) => expression(*e, &w),
+            };
+            if gate.scope != scope {
+                scope = gate.scope;
+                gates.push(format!("// scope {}", content.scopes[scope]));
+            }
+            gates.push(format!("wire {} = {value};", w(gate.wire_out_index)));
+        }
+        let gates = gates.join("\n");
+        let externals = content
+            .externals
             .iter()
-            .map(|gate| {
-                format!(
-                    "wire w{} = !(w{} & w{});",
-                    gate.wire_out_index, gate.wire_a_index, gate.wire_b_index
-                )
+            .enumerate()
+            .map(|(index, external)| {
+                let instance =
+                    external_instance(index, external, interface.clk, &content.scopes, &w);
+                format!("\n{instance}\n")
             })
-            .collect::<Vec<_>>()
-            .join("\n");
+            .collect::<String>();
 
         // output
 
-        let regs_write = content
-            .regs
-            .iter()
-            .enumerate()
-            .map(|(index, reg)| format!("    r{index} <= w{};", reg.wire_in_index))
-            .collect::<Vec<_>>()
-            .join("\n");
+        let clk = &interface.clk;
+        // the default domain runs on `clk`, every other domain on an input named after it
+        let domain_clk = |domain: usize| match domain {
+            0 => clk.to_string(),
+            _ => sanitize(&content.clock_domains[domain]),
+        };
+        let mut always = vec![];
+        for domain in 0..content.clock_domains.len() {
+            let regs = content
+                .regs
+                .iter()
+                .enumerate()
+                .filter(|(_, reg)| reg.domain == domain)
+                .collect::<Vec<_>>();
+            if domain != 0 && regs.is_empty() {
+                continue;
+            }
+            let regs_write = regs_write(&regs, content.reset_index.map(w), &w);
+            always.push(format!(
+                "always @(posedge {}) begin\n    // regs write\n{regs_write}\nend",
+                domain_clk(domain)
+            ));
+        }
+        let always = always.join("\n\n");
+        let domain_inputs = (1..content.clock_domains.len())
+            .map(|domain| format!("    input {},\n", domain_clk(domain)))
+            .collect::<String>();
 
         let output_assign = interface
-            .output_wires
+            .outputs
             .iter()
-            .map(|(name, wire)| format!("assign {name} = w{};", wire.0))
+            .flat_map(ExportPort::bits)
+            .map(|(bit, wire)| format!("assign {bit} = {};", w(wire)))
             .collect::<Vec<_>>()
             .join("\n");
 
-        let clk = &interface.clk;
-
         format!(
             "// exported from {exporter_name}
 module {module_name}(
 {inputs}
 {outputs}
-    input clk);
+{domain_inputs}    input clk);
 
 // wire01
 {wires01}
@@ -113,14 +291,11 @@ module {module_name}(
 {input_assign}
 // regs read
 {regs_read}
-
+{externals_read}
 // gates
 {gates}
-
-always @(posedge {clk}) begin
-    // regs write
-{regs_write}
-end
+{externals}
+{always}
 
 // outputs
 {output_assign}
@@ -154,7 +329,7 @@ fn test_basic_nand() {
         .output_wire("Led2", out2)
         .output_wire("Led3", out3);
 
-    let verilog_output = VerilogModuleExporter {}.export(&interface, &content);
+    let verilog_output = VerilogModuleExporter::default().export(&interface, &content);
     println!("{verilog_output}");
 }
 
@@ -185,7 +360,7 @@ fn test_basic_reg() {
         .output_wire("Led4", Wire(1))
         .output_wire("Led5", Wire(1));
 
-    let verilog_output = VerilogModuleExporter {}.export(&interface, &content);
+    let verilog_output = VerilogModuleExporter::default().export(&interface, &content);
     println!("{verilog_output}");
 }
 
@@ -214,6 +389,229 @@ fn test_basic_adder() {
         .output_wire("Led4", led.wires[4])
         .output_wire("Led5", led.wires[5]);
 
-    let verilog_output = VerilogModuleExporter {}.export(&interface, &content);
+    let verilog_output = VerilogModuleExporter::default().export(&interface, &content);
+    println!("{verilog_output}");
+}
+
+#[test]
+fn test_named_scopes() {
+    use crate::*;
+    clear_all();
+    let button = input().named("button");
+    let counter = scope("counter", || {
+        let r = reg_w::<2>().named("value");
+        let next = scope("inc", || add_naive(r.out, Wires::parse_u8(1)).sum);
+        r.set_in(next & !button.expand());
+        r
+    });
+
+    let content = export_gate_reg();
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("counter")
+        .clk("clk")
+        .input_wire("Button", button)
+        .output_wires("Led", counter.out);
+
+    let verilog_output = VerilogModuleExporter::default().export(&interface, &content);
+    println!("{verilog_output}");
+    let button = button.0;
+    let value1 = counter.out.wires[1].0;
+    assert!(verilog_output.contains(&format!("wire w{button}_button = Button;")));
+    assert!(verilog_output.contains(&format!("wire w{value1}_counter_value_1 = r1;")));
+    assert!(verilog_output.contains("// scope counter.inc\n"));
+    assert!(verilog_output.contains("// scope counter\n"));
+}
+
+#[test]
+fn test_reset_enable() {
+    use crate::*;
+    clear_all();
+    let reset = reset_signal().named("reset");
+    let enable = input().named("enable");
+    let a = reg_with_init(1);
+    let b = reg();
+    a.set_in(!a.out());
+    b.set_in(a.out());
+    b.set_enable(enable);
+
+    let content = export_gate_reg();
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("reset_enable")
+        .clk("clk")
+        .input_wire("Reset", reset)
+        .input_wire("Enable", enable)
+        .output_wire("Led", b.out());
+
+    let verilog_output = VerilogModuleExporter::default().export(&interface, &content);
+    println!("{verilog_output}");
+    let (reset, enable, a_out) = (reset.0, enable.0, a.out().0);
+    assert!(verilog_output.contains("reg r0 = 1'b1;\nreg r1 = 1'b0;"));
+    assert!(verilog_output.contains(&format!(
+        "    if (w{reset}_reset) begin\n        r0 <= 1'b1;\n        r1 <= 1'b0;\n    end else begin\n"
+    )));
+    assert!(verilog_output.contains(&format!(
+        "        if (w{enable}_enable) r1 <= w{a_out};\n    end\n"
+    )));
+}
+
+#[test]
+fn test_clock_domains() {
+    use crate::*;
+    clear_all();
+    let slow = add_clock_domain("slow.clk", 1, 4);
+    let fast = reg();
+    fast.set_in(!fast.out());
+    let slow_reg = clock_domain(slow, || {
+        let r = reg();
+        r.set_in(fast.out());
+        r
+    });
+
+    let content = export_gate_reg();
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("domains")
+        .clk("clk")
+        .output_wire("Led", slow_reg.out());
+
+    let verilog_output = VerilogModuleExporter::default().export(&interface, &content);
+    println!("{verilog_output}");
+    let (fast_in, fast_out) = (fast.out().0 + 1, fast.out().0);
+    assert!(verilog_output.contains("    input slow_clk,\n    input clk);"));
+    assert!(verilog_output.contains(&format!(
+        "always @(posedge clk) begin\n    // regs write\n    r0 <= w{fast_in};\nend\n\n"
+    )));
+    assert!(verilog_output.contains(&format!(
+        "always @(posedge slow_clk) begin\n    // regs write\n    r1 <= w{fast_out};\nend\n"
+    )));
+}
+
+#[test]
+fn test_vector_ports() {
+    use crate::*;
+    clear_all();
+    let a = input_w::<4>();
+    let b = input_w::<4>();
+    let sum = add_naive(a, b).sum;
+
+    let content = export_gate_reg();
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("adder")
+        .clk("clk")
+        .input_wires("A", a)
+        .input_wires("B", b)
+        .output_wires("Sum", sum);
+
+    let verilog_output = VerilogModuleExporter::default().export(&interface, &content);
+    println!("{verilog_output}");
+    assert!(verilog_output.contains("    input [3:0] A,\n    input [3:0] B,\n"));
+    assert!(verilog_output.contains("    output [3:0] Sum,\n"));
+    for i in 0..4 {
+        let (a, sum) = (a.wires[i].0, sum.wires[i].0);
+        assert!(verilog_output.contains(&format!("wire w{a} = A[{i}];")));
+        assert!(verilog_output.contains(&format!("assign Sum[{i}] = w{sum};")));
+    }
+}
+
+#[test]
+fn test_readable_expressions() {
+    use crate::*;
+    clear_all();
+    let a = input_w::<2>();
+    let b = input_w::<2>();
+    let select = input();
+    let [p, q, r] = [(); 3].map(|_| input());
+    let xor = a ^ b;
+    let mux = mux2_w(a, b, select);
+    let and = p & q;
+    let or = and | r;
+
+    let content = export_gate_reg();
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("readable")
+        .clk("clk")
+        .input_wires("A", a)
+        .input_wires("B", b)
+        .input_wire("Select", select)
+        .input_wire("P", p)
+        .input_wire("Q", q)
+        .input_wire("R", r)
+        .output_wires("Xor", xor)
+        .output_wires("Mux", mux)
+        .output_wire("Or", or);
+
+    let verilog_output = VerilogModuleExporter::default()
+        .readable_expressions(true)
+        .export(&interface, &content);
+    println!("{verilog_output}");
+    let w = |wire: Wire| format!("w{}", wire.0);
+    for i in 0..2 {
+        let (a, b) = (w(a.wires[i]), w(b.wires[i]));
+        let (xor, mux) = (w(xor.wires[i]), w(mux.wires[i]));
+        assert!(verilog_output.contains(&format!("wire {xor} = {a} ^ {b};")));
+        let select = w(select);
+        assert!(verilog_output.contains(&format!("wire {mux} = {select} ? {b} : {a};")));
+    }
+    let (p, q, r) = (w(p), w(q), w(r));
+    assert!(verilog_output.contains(&format!("wire {} = {p} & {q};", w(and))));
+    assert!(verilog_output.contains(&format!("wire {} = {} | {r};", w(or), w(and))));
+    // the inverted select is shared by both bits
+    assert!(verilog_output.contains(&format!("= ~{};", w(select))));
+    assert!(!verilog_output.contains('!'));
+}
+
+#[test]
+fn test_externals() {
+    use crate::*;
+
+    // a ROM emulated in Rust, exported as a black box
+    struct Rom {
+        addr: Wires<2>,
+        data: Wires<4>,
+    }
+    impl External for Rom {
+        fn execute(&mut self, ctx: &mut ExternalContext) {
+            ctx.set_u8(&self.data.wires, ctx.get_u8(&self.addr.wires) * 3);
+        }
+        fn export_ports(&self) -> Option<ExternalPorts> {
+            let mut ports = ExternalPorts::new("rom.4x4");
+            ports
+                .clocked()
+                .input_wires("addr", &self.addr.wires)
+                .output_wires("data", &self.data.wires);
+            Some(ports)
+        }
+    }
+
+    clear_all();
+    let addr = input_w::<2>().named("addr");
+    let data = input_w::<4>();
+    scope("rom", || external(Rom { addr, data }));
+    let led = !data;
+    external(Logger::from_wire(led.wires[0]));
+
+    let content = export_gate_reg();
+    assert_eq!(1, content.externals.len());
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("rom_leds")
+        .clk("clk")
+        .input_wires("Addr", addr)
+        .output_wires("Led", led);
+
+    let verilog_output = VerilogModuleExporter::default().export(&interface, &content);
     println!("{verilog_output}");
+    let w = |wire: Wire| wire_ident(&content, wire.0);
+    let [d0, d1, d2, d3] = data.wires.map(w);
+    let [a0, a1] = addr.wires.map(w);
+    assert!(verilog_output.contains(&format!("// externals read\nwire {d0};\nwire {d1};\n")));
+    assert!(verilog_output.contains(&format!(
+        "// external 0, scope rom\nrom_4x4 ext0 (\n    .clk(clk),\n    .addr({{{a1}, {a0}}}),\n    \
+         .data({{{d3}, {d2}, {d1}, {d0}}})\n);\n"
+    )));
+    assert!(verilog_output.contains(&format!("wire {} = !({d0} & {d0});", w(led.wires[0]))));
 }
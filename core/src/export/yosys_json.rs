@@ -0,0 +1,257 @@
+use crate::export::{sanitize, wire_ident, ExportModuleInterface, ExportPort, Exporter};
+use crate::{json_string, ExportGateReg, WIRE_0, WIRE_1};
+
+/// Netlist in the JSON format of Yosys `write_json`, read back with `read_json`.
+///
+/// Wire `i` is bit `i`, the constant wires are the constant bits `"0"`/`"1"`. NANDs are
+/// `$_NAND_` cells and regs the flip-flop cell with their reset and enable, the sync reset taking
+/// priority like in the Verilog export. Externals are cells of their black-box module.
+#[derive(Default)]
+pub struct YosysJsonExporter {}
+
+/// One cell, `connections` is (port, direction, bits).
+fn cell(
+    name: &str,
+    cell_type: &str,
+    hide_name: bool,
+    connections: &[(&str, &str, String)],
+) -> String {
+    let directions = connections
+        .iter()
+        .map(|(port, direction, _)| format!("{}: \"{direction}\"", json_string(port)))
+        .collect::<Vec<_>>();
+    let connections = connections
+        .iter()
+        .map(|(port, _, bits)| format!("{}: {bits}", json_string(port)))
+        .collect::<Vec<_>>();
+    format!(
+        "        {}: {{\n          \"hide_name\": {},\n          \"type\": {},\n          \
+         \"parameters\": {{}},\n          \"attributes\": {{}},\n          \
+         \"port_directions\": {{ {} }},\n          \"connections\": {{ {} }}\n        }}",
+        json_string(name),
+        hide_name as u8,
+        json_string(cell_type),
+        directions.join(", "),
+        connections.join(", ")
+    )
+}
+
+impl Exporter for YosysJsonExporter {
+    fn exporter_name() -> &'static str {
+        "YosysJson"
+    }
+
+    fn export(&self, interface: &ExportModuleInterface, content: &ExportGateReg) -> String {
+        let exporter_name = Self::exporter_name();
+        let module_name = interface.module_name;
+        let bit = |index: usize| match index {
+            WIRE_0 => format!("\"{}\"", content.wire_0_value),
+            WIRE_1 => format!("\"{}\"", content.wire_1_value),
+            _ => index.to_string(),
+        };
+        let bits = |wires: &mut dyn Iterator<Item = usize>| {
+            format!("[{}]", wires.map(bit).collect::<Vec<_>>().join(", "))
+        };
+        let port_bits = |port: &ExportPort| bits(&mut port.wires.iter().map(|wire| wire.0));
+
+        // clocks get bits after the wires, unless an input port drives them
+
+        let domain_clk = |domain: usize| match domain {
+            0 => interface.clk.to_string(),
+            _ => sanitize(&content.clock_domains[domain]),
+        };
+        let mut clock_ports = vec![];
+        let clock_bits = (0..content.clock_domains.len())
+            .map(|domain| {
+                let clk = domain_clk(domain);
+                let input = interface
+                    .inputs
+                    .iter()
+                    .find(|port| port.name == clk && !port.is_bus);
+                match input {
+                    Some(port) => bit(port.wires[0].0),
+                    None => {
+                        let index = content.wire_count + domain;
+                        clock_ports.push((clk, index));
+                        index.to_string()
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut ports = vec![];
+        let mut netnames = vec![];
+        let mut port_entry = |name: &str, direction: &str, bits: String| {
+            let name = json_string(name);
+            ports.push(format!(
+                "        {name}: {{ \"direction\": \"{direction}\", \"bits\": {bits} }}"
+            ));
+            netnames.push(format!(
+                "        {name}: {{ \"hide_name\": 0, \"bits\": {bits}, \"attributes\": {{}} }}"
+            ));
+        };
+        for port in &interface.inputs {
+            port_entry(&port.name, "input", port_bits(port));
+        }
+        for (clk, index) in &clock_ports {
+            port_entry(clk, "input", format!("[{index}]"));
+        }
+        for port in &interface.outputs {
+            port_entry(&port.name, "output", port_bits(port));
+        }
+
+        // named wires, and reg outputs with their init value
+
+        let port_names = (interface.inputs.iter().chain(&interface.outputs))
+            .map(|port| port.name.as_str())
+            .collect::<Vec<_>>();
+        let mut wire_names = content.wire_names.iter().collect::<Vec<_>>();
+        wire_names.retain(|(_, name)| !port_names.contains(&name.as_str()));
+        wire_names.sort();
+        for (index, name) in wire_names {
+            netnames.push(format!(
+                "        {}: {{ \"hide_name\": 0, \"bits\": [{index}], \"attributes\": {{}} }}",
+                json_string(name)
+            ));
+        }
+        for reg in &content.regs {
+            netnames.push(format!(
+                "        {}: {{ \"hide_name\": 1, \"bits\": [{}], \"attributes\": {{ \"init\": \"{}\" }} }}",
+                json_string(&format!("${}", wire_ident(content, reg.wire_out_index))),
+                reg.wire_out_index,
+                reg.init
+            ));
+        }
+
+        // cells
+
+        let mut cells = vec![];
+        for (index, gate) in content.gates.iter().enumerate() {
+            cells.push(cell(
+                &format!("$g{index}"),
+                "$_NAND_",
+                true,
+                &[
+                    ("A", "input", format!("[{}]", bit(gate.wire_a_index))),
+                    ("B", "input", format!("[{}]", bit(gate.wire_b_index))),
+                    ("Y", "output", format!("[{}]", bit(gate.wire_out_index))),
+                ],
+            ));
+        }
+        for (index, reg) in content.regs.iter().enumerate() {
+            let mut connections = vec![
+                ("C", "input", format!("[{}]", clock_bits[reg.domain])),
+                ("D", "input", format!("[{}]", bit(reg.wire_in_index))),
+            ];
+            if let Some(enable) = reg.enable_index {
+                connections.push(("E", "input", format!("[{}]", bit(enable))));
+            }
+            if let Some(reset) = content.reset_index {
+                connections.push(("R", "input", format!("[{}]", bit(reset))));
+            }
+            connections.push(("Q", "output", format!("[{}]", reg.wire_out_index)));
+            let init = reg.init;
+            let cell_type = match (content.reset_index, reg.enable_index) {
+                (None, None) => "$_DFF_P_".to_string(),
+                (None, Some(_)) => "$_DFFE_PP_".to_string(),
+                (Some(_), None) => format!("$_SDFF_PP{init}_"),
+                (Some(_), Some(_)) => format!("$_SDFFE_PP{init}P_"),
+            };
+            cells.push(cell(&format!("$r{index}"), &cell_type, true, &connections));
+        }
+        for (index, external) in content.externals.iter().enumerate() {
+            let ports = &external.ports;
+            let mut connections = vec![];
+            if ports.clocked {
+                connections.push(("clk", "input", format!("[{}]", clock_bits[0])));
+            }
+            for (port_list, direction) in [(&ports.inputs, "input"), (&ports.outputs, "output")] {
+                for port in port_list {
+                    connections.push((port.name.as_str(), direction, port_bits(port)));
+                }
+            }
+            let cell_type = sanitize(&ports.module_name);
+            cells.push(cell(
+                &format!("ext{index}"),
+                &cell_type,
+                false,
+                &connections,
+            ));
+        }
+
+        format!(
+            "{{
+  \"creator\": \"exported from {exporter_name}\",
+  \"modules\": {{
+    {}: {{
+      \"attributes\": {{ \"top\": \"1\" }},
+      \"ports\": {{
+{}
+      }},
+      \"cells\": {{
+{}
+      }},
+      \"netnames\": {{
+{}
+      }}
+    }}
+  }}
+}}
+",
+            json_string(module_name),
+            ports.join(",\n"),
+            cells.join(",\n"),
+            netnames.join(",\n")
+        )
+    }
+}
+
+#[test]
+fn test_yosys_json() {
+    use crate::*;
+    clear_all();
+    let enable = input().named("enable");
+    let a = input_w::<2>();
+    let r = reg_with_init(1);
+    r.set_in(a.wires[0] & a.wires[1]);
+    r.set_enable(enable);
+    let out = !r.out();
+
+    let content = export_gate_reg();
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("json_test")
+        .clk("clk")
+        .input_wire("Enable", enable)
+        .input_wires("A", a)
+        .output_wire("Out", out);
+
+    let json = YosysJsonExporter::default().export(&interface, &content);
+    let (a0, a1, r_out) = (a.wires[0].0, a.wires[1].0, r.out().0);
+    let [nand, and] = [0, 1].map(|gate| content.gates[gate].wire_out_index);
+    let clk = content.wire_count;
+    assert!(json.contains("    \"json_test\": {\n"));
+    assert!(json.contains(&format!(
+        "        \"A\": {{ \"direction\": \"input\", \"bits\": [{a0}, {a1}] }},\n        \
+         \"clk\": {{ \"direction\": \"input\", \"bits\": [{clk}] }},\n        \
+         \"Out\": {{ \"direction\": \"output\", \"bits\": [{}] }}\n",
+        out.0
+    )));
+    assert!(json.contains(&format!(
+        "\"connections\": {{ \"A\": [{a0}], \"B\": [{a1}], \"Y\": [{nand}] }}"
+    )));
+    assert!(json.contains(
+        "        \"$r0\": {\n          \"hide_name\": 1,\n          \"type\": \"$_DFFE_PP_\",\n"
+    ));
+    assert!(json.contains(&format!(
+        "\"connections\": {{ \"C\": [{clk}], \"D\": [{and}], \"E\": [{}], \"Q\": [{r_out}] }}",
+        enable.0
+    )));
+    assert!(json.contains(&format!(
+        "\"bits\": [{r_out}], \"attributes\": {{ \"init\": \"1\" }} }}"
+    )));
+    assert!(json.contains(&format!(
+        "        \"enable\": {{ \"hide_name\": 0, \"bits\": [{}], \"attributes\": {{}} }}",
+        enable.0
+    )));
+}
@@ -1,5 +1,4 @@
-use crate::{External, Wire, WireValue, Wires, WiresU8};
-use std::any::Any;
+use crate::{External, ExternalContext, Wire, WireValue, Wires, WiresU64, WiresU8};
 
 pub struct Logger {
     name: String,
@@ -7,11 +6,17 @@ pub struct Logger {
     values: Vec<WireValue>,
 }
 impl External for Logger {
-    fn execute(&mut self) {
-        self.values.push(self.wire.get());
+    fn execute(&mut self, ctx: &mut ExternalContext) {
+        self.values.push(ctx.get(self.wire));
     }
-    fn as_any(&self) -> &dyn Any {
-        self
+    fn save_state(&self) -> Option<Vec<u8>> {
+        Some(self.values.clone())
+    }
+    fn restore_state(&mut self, state: &[u8]) {
+        self.values = state.to_vec();
+    }
+    fn is_observer(&self) -> bool {
+        true
     }
 }
 impl Logger {
@@ -22,6 +27,13 @@ impl Logger {
             values: Vec::new(),
         }
     }
+    /// Logger named after the hierarchical name of the wire.
+    pub fn from_wire(wire: Wire) -> Logger {
+        Self::new(wire.name(), wire)
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
     pub fn print(&self) {
         print!("{}:", self.name);
         for v in &self.values {
@@ -40,17 +52,31 @@ where
 {
     name: String,
     wires: Wires<W>,
-    values: Vec<u8>,
+    values: Vec<Option<u8>>, // None for a value with X or Z bits
 }
 impl<const W: usize> External for LoggerU8<W>
 where
     Wires<W>: WiresU8,
 {
-    fn execute(&mut self) {
-        self.values.push(self.wires.get_u8());
+    fn execute(&mut self, ctx: &mut ExternalContext) {
+        self.values.push(ctx.try_get_u8(&self.wires.wires));
     }
-    fn as_any(&self) -> &dyn Any {
-        self
+    fn save_state(&self) -> Option<Vec<u8>> {
+        // a known flag before each value
+        let values = self
+            .values
+            .iter()
+            .flat_map(|v| [v.is_some() as u8, v.unwrap_or(0)]);
+        Some(values.collect())
+    }
+    fn restore_state(&mut self, state: &[u8]) {
+        let values = state
+            .chunks(2)
+            .map(|bytes| (bytes[0] == 1).then_some(bytes[1]));
+        self.values = values.collect();
+    }
+    fn is_observer(&self) -> bool {
+        true
     }
 }
 impl<const W: usize> LoggerU8<W>
@@ -64,14 +90,91 @@ where
             values: Vec::new(),
         }
     }
+    /// Logger named after the hierarchical name of the bus.
+    pub fn from_wires(wires: Wires<W>) -> LoggerU8<W> {
+        Self::new(wires.name(), wires)
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
     pub fn print(&self) {
         print!("{}:", self.name);
         for v in &self.values {
-            print!(" {}", *v);
+            match v {
+                Some(v) => print!(" {v}"),
+                None => print!(" x"),
+            }
         }
         println!();
     }
-    pub fn get_values(&self) -> &Vec<u8> {
+    pub fn get_values(&self) -> &Vec<Option<u8>> {
+        &self.values
+    }
+}
+
+/// `LoggerU8` for buses up to 64 bits.
+pub struct LoggerU64<const W: usize>
+where
+    Wires<W>: WiresU64,
+{
+    name: String,
+    wires: Wires<W>,
+    values: Vec<Option<u64>>, // None for a value with X or Z bits
+}
+impl<const W: usize> External for LoggerU64<W>
+where
+    Wires<W>: WiresU64,
+{
+    fn execute(&mut self, ctx: &mut ExternalContext) {
+        self.values.push(ctx.try_get_u64(&self.wires.wires));
+    }
+    fn save_state(&self) -> Option<Vec<u8>> {
+        // a known flag before each value
+        let values = (self.values.iter())
+            .flat_map(|v| [&[v.is_some() as u8][..], &v.unwrap_or(0).to_le_bytes()].concat());
+        Some(values.collect())
+    }
+    fn restore_state(&mut self, state: &[u8]) {
+        self.values = state
+            .chunks(9)
+            .map(|bytes| {
+                (bytes[0] == 1).then(|| u64::from_le_bytes(bytes[1..].try_into().unwrap()))
+            })
+            .collect();
+    }
+    fn is_observer(&self) -> bool {
+        true
+    }
+}
+impl<const W: usize> LoggerU64<W>
+where
+    Wires<W>: WiresU64,
+{
+    pub fn new(name: String, wires: Wires<W>) -> LoggerU64<W> {
+        Self {
+            name,
+            wires,
+            values: Vec::new(),
+        }
+    }
+    /// Logger named after the hierarchical name of the bus.
+    pub fn from_wires(wires: Wires<W>) -> LoggerU64<W> {
+        Self::new(wires.name(), wires)
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn print(&self) {
+        print!("{}:", self.name);
+        for v in &self.values {
+            match v {
+                Some(v) => print!(" {v}"),
+                None => print!(" x"),
+            }
+        }
+        println!();
+    }
+    pub fn get_values(&self) -> &Vec<Option<u64>> {
         &self.values
     }
 }
@@ -90,7 +193,17 @@ fn test_logger() {
         b.set(if i % 2 == 0 { 1 } else { 0 });
         simulate();
     }
-    assert_eq!(logger.get_values(), &vec![1, 0, 1, 1, 1, 0, 1, 0, 1, 1]);
+    assert_eq!(
+        logger.borrow().get_values(),
+        &vec![1, 0, 1, 1, 1, 0, 1, 0, 1, 1]
+    );
+
+    // the handle outlives the netlist, but not a borrow across the execution
+    let borrowed = logger.borrow();
+    assert!(std::panic::catch_unwind(simulate).is_err());
+    drop(borrowed);
+    clear_all();
+    assert_eq!(10, logger.borrow().get_values().len());
 }
 #[test]
 fn test_logger_u8() {
@@ -100,12 +213,81 @@ fn test_logger_u8() {
     let one = Wires::<4>::parse_u8(1);
     let curr = reg_w::<4>();
     curr.set_in(add_naive(curr.out, one).sum);
-    let logger = external(LoggerU8::new("inc".to_string(), curr.clone().out));
+    let curr = scope("counter", || curr.named("curr"));
+    let logger = external(LoggerU8::from_wires(curr.out));
+    assert_eq!("counter.curr", logger.borrow().name());
     for _ in 0..=16 {
         simulate();
     }
+    let expected = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0];
+    assert_eq!(logger.borrow().get_values(), &expected.map(Some).to_vec());
+
+    // an uninitialized reg is logged as unknown until the reset loads it
+    clear_all();
+    set_four_state(true);
+    let reset = reset_signal();
+    let curr = reg_w::<4>();
+    curr.set_in(add_naive(curr.out, one).sum);
+    let logger = external(LoggerU8::from_wires(curr.out));
+    let wide = external(LoggerU64::from_wires(curr.out));
+    simulate();
+    reset.set(1);
+    simulate();
+    reset.set(0);
+    simulate();
+    simulate();
     assert_eq!(
-        logger.get_values(),
-        &vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0]
+        &vec![None, None, Some(0), Some(1)],
+        logger.borrow().get_values()
     );
+    assert_eq!(
+        &vec![None, None, Some(0), Some(1)],
+        wide.borrow().get_values()
+    );
+    let snapshot = snapshot();
+    simulate();
+    restore(&snapshot);
+    assert_eq!(
+        &vec![None, None, Some(0), Some(1)],
+        logger.borrow().get_values()
+    );
+    assert_eq!(
+        &vec![None, None, Some(0), Some(1)],
+        wide.borrow().get_values()
+    );
+}
+#[test]
+fn test_logger_u64() {
+    use crate::*;
+    clear_all();
+
+    // 16 bit counter stepping by 0x1111 from 0xfffe
+    let step = Wires::<16>::parse_u64(0x1111);
+    let count = reg_w::<16>().named("count");
+    count.set_in(add_naive(count.out, step).sum);
+    count.out.set_u64(0xfffe);
+    let logger = external(LoggerU64::from_wires(count.out));
+    for _ in 0..3 {
+        simulate();
+    }
+    let expected = vec![Some(0xfffe), Some(0x110f), Some(0x2220)];
+    assert_eq!(&expected, logger.borrow().get_values());
+    assert_eq!("count", logger.borrow().name());
+    assert_eq!(0x3331, count.out.get_u64());
+    assert_eq!("13105(11001100110001)", format!("{:?}", count.out));
+
+    let snapshot = snapshot();
+    simulate();
+    restore(&snapshot);
+    assert_eq!(3, logger.borrow().get_values().len());
+
+    // signed and 64 bits wide
+    let a = input_w::<12>();
+    a.set_u64(-5i64 as u64);
+    assert_eq!((0xffb, -5), (a.get_u64(), a.get_i64()));
+    a.set_u64(0x7ff);
+    assert_eq!(0x7ff, a.get_i64());
+    let wide = input_w::<64>();
+    wide.set_u64(u64::MAX - 1);
+    assert_eq!((u64::MAX - 1, -2), (wide.get_u64(), wide.get_i64()));
 }
@@ -1,2 +1,4 @@
 mod logger;
+mod vcd;
 pub use logger::*;
+pub use vcd::*;
@@ -0,0 +1,284 @@
+use crate::{External, ExternalContext, Wire, WireValue, Wires, VALUE_X, VALUE_Z};
+use std::fmt::Write;
+use std::path::Path;
+
+struct VcdSignal {
+    path: Vec<String>, // scopes of the netlist hierarchy
+    name: String,
+    wires: Vec<Wire>,
+    is_bus: bool,
+}
+
+/// Samples the registered signals on every `execute()` and writes a Value Change Dump.
+///
+/// Like all externals it samples at its place in the execute order, so create it after the
+/// logic it records. Signal names and scopes come from `named()`/`scope()`.
+pub struct VcdRecorder {
+    module_name: String,
+    signals: Vec<VcdSignal>,
+    bit_count: usize,
+    values: Vec<WireValue>, // bit_count values per sample
+}
+
+impl External for VcdRecorder {
+    fn execute(&mut self, ctx: &mut ExternalContext) {
+        for signal in &self.signals {
+            self.values.extend(signal.wires.iter().map(|w| ctx.get(*w)));
+        }
+    }
+    fn save_state(&self) -> Option<Vec<u8>> {
+        Some(self.values.clone())
+    }
+    fn restore_state(&mut self, state: &[u8]) {
+        self.values = state.to_vec();
+    }
+    fn is_observer(&self) -> bool {
+        true
+    }
+}
+
+/// Scope tree of the signals, in registration order.
+#[derive(Default)]
+struct VcdScope<'a> {
+    name: &'a str,
+    signals: Vec<usize>,
+    children: Vec<VcdScope<'a>>,
+}
+
+impl<'a> VcdScope<'a> {
+    fn insert(&mut self, path: &'a [String], signal: usize) {
+        match path.split_first() {
+            None => self.signals.push(signal),
+            Some((first, rest)) => {
+                let index = match self.children.iter().position(|c| c.name == first) {
+                    Some(index) => index,
+                    None => {
+                        self.children.push(VcdScope {
+                            name: first,
+                            ..Default::default()
+                        });
+                        self.children.len() - 1
+                    }
+                };
+                self.children[index].insert(rest, signal);
+            }
+        }
+    }
+}
+
+/// Printable ASCII identifier codes: `!`, `"`, ... `~`, `!!`, ...
+fn vcd_id(mut index: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            return id;
+        }
+        index -= 1;
+    }
+}
+
+fn vcd_bit(value: WireValue) -> char {
+    match value {
+        0 => '0',
+        1 => '1',
+        VALUE_X => 'x',
+        VALUE_Z => 'z',
+        _ => unreachable!(),
+    }
+}
+
+impl VcdRecorder {
+    pub fn new(module_name: &str) -> VcdRecorder {
+        Self {
+            module_name: module_name.to_string(),
+            signals: Vec::new(),
+            bit_count: 0,
+            values: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, full_name: String, wires: Vec<Wire>, is_bus: bool) -> &mut Self {
+        let mut path = full_name.split('.').map(str::to_string).collect::<Vec<_>>();
+        let name = path.pop().unwrap();
+        self.bit_count += wires.len();
+        self.signals.push(VcdSignal {
+            path,
+            name,
+            wires,
+            is_bus,
+        });
+        self
+    }
+    pub fn add_wire(&mut self, wire: Wire) -> &mut Self {
+        self.add(wire.name(), vec![wire], false)
+    }
+    pub fn add_wires<const W: usize>(&mut self, wires: Wires<W>) -> &mut Self {
+        self.add(wires.name(), wires.wires.to_vec(), true)
+    }
+
+    pub fn sample_count(&self) -> usize {
+        match self.bit_count {
+            0 => 0,
+            n => self.values.len() / n,
+        }
+    }
+
+    fn write_scope(&self, out: &mut String, scope: &VcdScope) {
+        for index in &scope.signals {
+            let signal = &self.signals[*index];
+            let width = signal.wires.len();
+            let reference = match (signal.is_bus, signal.name.split_once('[')) {
+                (true, _) => format!("{} [{}:0]", signal.name, width - 1),
+                (false, Some((name, bit))) => format!("{name} [{bit}"),
+                (false, None) => signal.name.clone(),
+            };
+            let id = vcd_id(*index);
+            writeln!(out, "$var wire {width} {id} {reference} $end").unwrap();
+        }
+        for child in &scope.children {
+            writeln!(out, "$scope module {} $end", child.name).unwrap();
+            self.write_scope(out, child);
+            writeln!(out, "$upscope $end").unwrap();
+        }
+    }
+
+    fn value_change(&self, index: usize, bits: &[WireValue]) -> String {
+        if self.signals[index].is_bus {
+            let bits = bits.iter().rev().map(|b| vcd_bit(*b)).collect::<String>();
+            format!("b{bits} {}", vcd_id(index))
+        } else {
+            format!("{}{}", vcd_bit(bits[0]), vcd_id(index))
+        }
+    }
+
+    /// One time unit per sample, the dump starts with all values and then lists changes.
+    pub fn to_vcd(&self) -> String {
+        let mut offsets = vec![0];
+        for signal in &self.signals {
+            offsets.push(offsets.last().unwrap() + signal.wires.len());
+        }
+        let mut root = VcdScope::default();
+        for (index, signal) in self.signals.iter().enumerate() {
+            root.insert(&signal.path, index);
+        }
+
+        let mut out = String::new();
+        writeln!(out, "$version digital-design-code VcdRecorder $end").unwrap();
+        writeln!(out, "$timescale 1ns $end").unwrap();
+        writeln!(out, "$scope module {} $end", self.module_name).unwrap();
+        self.write_scope(&mut out, &root);
+        writeln!(out, "$upscope $end").unwrap();
+        writeln!(out, "$enddefinitions $end").unwrap();
+
+        let samples = self.values.chunks(self.bit_count.max(1));
+        let mut previous: Option<&[WireValue]> = None;
+        for (time, sample) in samples.enumerate() {
+            writeln!(out, "#{time}").unwrap();
+            if previous.is_none() {
+                writeln!(out, "$dumpvars").unwrap();
+            }
+            for index in 0..self.signals.len() {
+                let range = offsets[index]..offsets[index + 1];
+                if previous.map_or(true, |p| p[range.clone()] != sample[range.clone()]) {
+                    writeln!(out, "{}", self.value_change(index, &sample[range])).unwrap();
+                }
+            }
+            if previous.is_none() {
+                writeln!(out, "$end").unwrap();
+            }
+            previous = Some(sample);
+        }
+        writeln!(out, "#{}", self.sample_count()).unwrap();
+        out
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_vcd())
+    }
+}
+
+#[test]
+fn test_vcd_recorder() {
+    use crate::*;
+    clear_all();
+
+    let enable = input().named("enable");
+    let count = scope("counter", || {
+        let count = reg_w::<4>().named("count");
+        let next = add_naive(count.out, Wires::parse_u8(1)).sum;
+        count.set_in(mux2_w(count.out, next, enable));
+        count.out
+    });
+    let mut recorder = VcdRecorder::new("top");
+    recorder.add_wire(enable).add_wires(count);
+    let recorder = external(recorder);
+
+    for i in 0..6 {
+        enable.set((i % 3 != 2).into());
+        simulate();
+    }
+    assert_eq!(6, recorder.borrow().sample_count());
+
+    let vcd = recorder.borrow().to_vcd();
+    let expected = "\
+$version digital-design-code VcdRecorder $end
+$timescale 1ns $end
+$scope module top $end
+$var wire 1 ! enable $end
+$scope module counter $end
+$var wire 4 \" count [3:0] $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+$dumpvars
+1!
+b0000 \"
+$end
+#1
+b0001 \"
+#2
+0!
+b0010 \"
+#3
+1!
+#4
+b0011 \"
+#5
+0!
+b0100 \"
+#6
+";
+    assert_eq!(expected, vcd);
+
+    let path = std::env::temp_dir().join("test_vcd_recorder.vcd");
+    recorder.borrow().write_to_file(&path).unwrap();
+    assert_eq!(vcd, std::fs::read_to_string(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_vcd_recorder_four_state() {
+    use crate::*;
+    clear_all();
+    set_four_state(true);
+
+    // X until the first tick, the high bits never driven
+    let flag = input().named("flag");
+    let bus = input_w::<2>().named("bus");
+    let reg = reg().named("reg");
+    reg.set_in(flag);
+    let mut recorder = VcdRecorder::new("top");
+    recorder.add_wire(reg.out()).add_wires(bus);
+    let recorder = external(recorder);
+
+    flag.set(1);
+    bus.wires[0].set(VALUE_Z);
+    simulate();
+    bus.wires[0].set(1);
+    simulate();
+    let vcd = recorder.borrow().to_vcd();
+    assert!(vcd.contains("$dumpvars\nx!\nbxz \"\n$end\n#1\n1!\nbx1 \"\n#2\n"));
+}
@@ -0,0 +1,332 @@
+use crate::{current, restore, simulate, snapshot, Circuit, Reg, Wire, WireValue, WIRE_0, WIRE_1};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// The wire reads `value` whatever drives it.
+    StuckAt { wire: Wire, value: WireValue },
+    /// The reg output is inverted right before `simulate()` of cycle `cycle`.
+    BitFlip { reg: Reg, cycle: usize },
+}
+
+/// Readers of the netlist before the first stuck-at fault, see `clear_faults()`.
+struct FaultFreeNetlist {
+    gate_inputs: Vec<[Wire; 2]>,
+    reg_inputs: Vec<[Option<Wire>; 2]>, // [in, enable]
+    reset: Option<Wire>,
+}
+
+/// Stuck-at faults injected into a circuit.
+///
+/// Every gate, reg and the reset reading a stuck wire is rewired to `WIRE_0`/`WIRE_1`, and the
+/// wire itself is forced before each external executes and after each `execute_gates()` and
+/// `clock_tick()`, so that externals and testbenches see it too.
+#[derive(Default)]
+pub(crate) struct Faults {
+    pub(crate) stuck: Vec<(Wire, WireValue)>,
+    fault_free: Option<FaultFreeNetlist>,
+}
+
+impl Circuit {
+    pub fn inject_stuck_at(&mut self, wire: Wire, value: WireValue) {
+        assert!(value <= 1, "Stuck-at value must be 0 or 1!");
+        assert!(wire.0 > WIRE_1, "Constant wires cannot be faulted!");
+        if self.faults.fault_free.is_none() {
+            self.faults.fault_free = Some(FaultFreeNetlist {
+                gate_inputs: self.gates.iter().map(|g| [g.wire_a, g.wire_b]).collect(),
+                reg_inputs: self.regs.iter().map(|r| [r.wire_in, r.enable]).collect(),
+                reset: self.reset,
+            });
+        }
+        let constant = Wire(if value == 1 { WIRE_1 } else { WIRE_0 });
+        let rewire = |reader: &mut Wire| {
+            if *reader == wire {
+                *reader = constant;
+            }
+        };
+        for gate in &mut self.gates {
+            rewire(&mut gate.wire_a);
+            rewire(&mut gate.wire_b);
+        }
+        for reg in &mut self.regs {
+            reg.wire_in
+                .iter_mut()
+                .chain(reg.enable.iter_mut())
+                .for_each(rewire);
+        }
+        self.reset.iter_mut().for_each(rewire);
+        self.faults.stuck.push((wire, value));
+        self.wires[wire.0] = value;
+        self.netlist_changed();
+    }
+
+    /// Invert the current value of a reg output, until the reg loads again.
+    pub fn flip_reg(&mut self, reg: Reg) {
+        let wire = self.regs[reg.0].wire_out;
+        self.wires[wire.0] ^= 1;
+    }
+
+    /// Remove every stuck-at fault, wire values stay as they are.
+    pub fn clear_faults(&mut self) {
+        self.faults.stuck.clear();
+        if let Some(fault_free) = self.faults.fault_free.take() {
+            for (gate, [a, b]) in self.gates.iter_mut().zip(fault_free.gate_inputs) {
+                gate.wire_a = a;
+                gate.wire_b = b;
+            }
+            for (reg, [wire_in, enable]) in self.regs.iter_mut().zip(fault_free.reg_inputs) {
+                reg.wire_in = wire_in;
+                reg.enable = enable;
+            }
+            self.reset = fault_free.reset;
+            self.netlist_changed();
+        }
+    }
+
+    pub(crate) fn force_stuck_wires(&mut self) {
+        for (wire, value) in &self.faults.stuck {
+            self.wires[wire.0] = *value;
+        }
+    }
+
+    /// Stuck-at-0 and stuck-at-1 on every wire but the constants.
+    pub fn stuck_at_faults(&self) -> Vec<Fault> {
+        (WIRE_1 + 1..self.wires.len())
+            .flat_map(|index| {
+                [0, 1].map(|value| Fault::StuckAt {
+                    wire: Wire(index),
+                    value,
+                })
+            })
+            .collect()
+    }
+
+    pub fn fault_name(&self, fault: Fault) -> String {
+        match fault {
+            Fault::StuckAt { wire, value } => format!("{} stuck-at-{value}", self.wire_name(wire)),
+            Fault::BitFlip { reg, cycle } => {
+                let wire = self.regs[reg.0].wire_out;
+                format!(
+                    "reg {} ({}) flipped at cycle {cycle}",
+                    reg.0,
+                    self.wire_name(wire)
+                )
+            }
+        }
+    }
+}
+
+pub fn inject_stuck_at(wire: Wire, value: WireValue) {
+    current().inject_stuck_at(wire, value);
+}
+
+pub fn flip_reg(reg: Reg) {
+    current().flip_reg(reg);
+}
+
+pub fn clear_faults() {
+    current().clear_faults();
+}
+
+pub fn stuck_at_faults() -> Vec<Fault> {
+    current().stuck_at_faults()
+}
+
+#[derive(Debug, Clone)]
+pub struct FaultResult {
+    pub fault: Fault,
+    pub name: String,
+    pub detected_at: Option<usize>, // first cycle where an observed wire differs
+}
+
+#[derive(Debug, Clone)]
+pub struct FaultReport {
+    pub cycles: usize,
+    pub results: Vec<FaultResult>,
+}
+
+impl FaultReport {
+    pub fn detected_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.detected_at.is_some())
+            .count()
+    }
+    pub fn undetected(&self) -> impl Iterator<Item = &FaultResult> {
+        self.results.iter().filter(|r| r.detected_at.is_none())
+    }
+    /// Detected share of all faults, 1.0 without faults.
+    pub fn coverage(&self) -> f64 {
+        match self.results.len() {
+            0 => 1.0,
+            n => self.detected_count() as f64 / n as f64,
+        }
+    }
+}
+
+impl Display for FaultReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "fault coverage {:.1}%: {} of {} faults detected in {} cycles",
+            self.coverage() * 100.0,
+            self.detected_count(),
+            self.results.len(),
+            self.cycles
+        )?;
+        const SHOWN: usize = 10;
+        let undetected = self.results.len() - self.detected_count();
+        for result in self.undetected().take(SHOWN) {
+            writeln!(f, "  undetected {}", result.name)?;
+        }
+        if undetected > SHOWN {
+            writeln!(f, "  ... {} more undetected", undetected - SHOWN)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `stimulus(cycle)` then `simulate()` for `cycles` cycles, once fault-free and once per
+/// fault, each run starting from the current state. A fault is detected when any `observe` wire
+/// differs from the fault-free run after some cycle. The circuit is left in its starting state.
+pub fn run_fault_campaign(
+    faults: &[Fault],
+    cycles: usize,
+    observe: &[Wire],
+    mut stimulus: impl FnMut(usize),
+) -> FaultReport {
+    let start = snapshot();
+    let injected = current().faults.stuck.clone(); // stay for every run and after
+    let mut run = |fault: Option<Fault>| {
+        restore(&start);
+        if let Some(Fault::StuckAt { wire, value }) = fault {
+            inject_stuck_at(wire, value);
+        }
+        let trace = (0..cycles)
+            .map(|cycle| {
+                stimulus(cycle);
+                if let Some(Fault::BitFlip { reg, cycle: at }) = fault {
+                    if at == cycle {
+                        flip_reg(reg);
+                    }
+                }
+                simulate();
+                observe.iter().map(|wire| wire.get()).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        clear_faults();
+        for (wire, value) in &injected {
+            inject_stuck_at(*wire, *value);
+        }
+        trace
+    };
+
+    let fault_free = run(None);
+    let results = faults
+        .iter()
+        .map(|fault| {
+            let trace = run(Some(*fault));
+            FaultResult {
+                fault: *fault,
+                name: current().fault_name(*fault),
+                detected_at: trace.iter().zip(&fault_free).position(|(a, b)| a != b),
+            }
+        })
+        .collect();
+    restore(&start);
+    FaultReport { cycles, results }
+}
+
+#[test]
+fn test_stuck_at() {
+    use crate::*;
+    clear_all();
+
+    let a = input();
+    let b = input();
+    let and = a & b;
+    let or = a | b;
+    a.set(1);
+    b.set(1);
+    inject_stuck_at(b, 0);
+    execute_gates();
+    assert_eq!((0, 1, 0), (b.get(), or.get(), and.get()));
+
+    clear_faults();
+    b.set(1);
+    execute_gates();
+    assert_eq!((1, 1), (and.get(), or.get()));
+
+    // an external reading a stuck wire sees the forced value too
+    let logger = external(Logger::new("and".to_string(), and));
+    inject_stuck_at(and, 0);
+    execute_gates();
+    assert_eq!(&vec![0], logger.borrow().get_values());
+}
+
+#[test]
+fn test_fault_campaign() {
+    use crate::*;
+    clear_all();
+
+    // a counter whose top bit is never observed within 6 cycles
+    let count = reg_w::<3>().named("count");
+    let enable = input().named("enable");
+    count.set_in(add_naive(count.out, Wires::parse_u8(1)).sum);
+    count.set_enable(enable);
+    let observe = count.out.wires;
+
+    let faults = [
+        Fault::StuckAt {
+            wire: enable,
+            value: 0,
+        },
+        Fault::StuckAt {
+            wire: enable,
+            value: 1,
+        },
+        Fault::StuckAt {
+            wire: count.out.wires[1],
+            value: 1,
+        },
+        Fault::BitFlip {
+            reg: count.regs[0],
+            cycle: 2,
+        },
+    ];
+    let report = run_fault_campaign(&faults, 6, &observe, |cycle| {
+        enable.set((cycle != 3).into())
+    });
+    assert_eq!(
+        "fault coverage 100.0%: 4 of 4 faults detected in 6 cycles\n",
+        report.to_string()
+    );
+    let detected = report
+        .results
+        .iter()
+        .map(|r| r.detected_at)
+        .collect::<Vec<_>>();
+    // fault-free: 1 2 3 3 4 5
+    assert_eq!(vec![Some(0), Some(3), Some(0), Some(2)], detected);
+    assert_eq!(1.0, report.coverage());
+    assert_eq!(0, count.out.get_u8());
+    assert_eq!("enable stuck-at-1", report.results[1].name);
+
+    let all = stuck_at_faults();
+    assert_eq!(2 * (current().get_statistics().wire_count - 2), all.len());
+    let report = run_fault_campaign(&all, 6, &observe, |_| enable.set(1));
+    assert!(report.coverage() < 1.0);
+    assert!(report.undetected().any(|r| r.name == "enable stuck-at-1"));
+    assert!(report
+        .to_string()
+        .contains("\n  undetected enable stuck-at-1\n"));
+
+    // a fault injected before the campaign applies to every run and stays
+    inject_stuck_at(count.out.wires[2], 0);
+    let report = run_fault_campaign(&faults[..1], 6, &observe, |_| enable.set(1));
+    assert_eq!(Some(0), report.results[0].detected_at);
+    count.out.set_u8(3);
+    enable.set(1);
+    simulate();
+    assert_eq!(0, count.out.get_u8());
+}
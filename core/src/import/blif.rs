@@ -0,0 +1,154 @@
+use crate::import::{group_ports, Cell, ImportError, ImportNetlist, ImportedModule, Latch};
+
+/// Build the first `.model` of a BLIF netlist into the current circuit.
+///
+/// `.names` covers become AND/OR trees of NANDs and every `.latch` a `reg()` on the default clock,
+/// its type and control are not checked. Hierarchy (`.subckt`) is not supported.
+pub fn import_blif(source: &str) -> Result<ImportedModule, ImportError> {
+    // statements with their first line, `\` continues a line and `#` starts a comment
+    let mut statements: Vec<(usize, Vec<&str>)> = vec![];
+    let mut continued = false;
+    for (index, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        let (line, continues) = match line.trim_end().strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        let tokens = line.split_whitespace();
+        match continued {
+            true => statements.last_mut().unwrap().1.extend(tokens),
+            false => statements.push((index + 1, tokens.collect())),
+        }
+        continued = continues;
+    }
+    statements.retain(|(_, tokens)| !tokens.is_empty());
+
+    let mut netlist = ImportNetlist::default();
+    let (mut inputs, mut outputs) = (vec![], vec![]);
+    let mut statements = statements.into_iter().peekable();
+    while let Some((line, tokens)) = statements.next() {
+        let error = |message: String| Err(ImportError::new(line, message));
+        match tokens[0] {
+            ".model" => netlist.name = tokens.get(1).unwrap_or(&"").to_string(),
+            ".inputs" => inputs.extend(tokens[1..].iter().map(|t| t.to_string())),
+            ".outputs" => outputs.extend(tokens[1..].iter().map(|t| t.to_string())),
+            ".names" => {
+                let Some((output, inputs)) = tokens[1..].split_last() else {
+                    return error(".names without an output".to_string());
+                };
+                let mut cubes = vec![];
+                let mut values = vec![];
+                while let Some((cube_line, cube)) =
+                    statements.next_if(|(_, tokens)| !tokens[0].starts_with('.'))
+                {
+                    let (cube, value) = match cube.as_slice() {
+                        [value] if inputs.is_empty() => ("", *value),
+                        [cube, value] => (*cube, *value),
+                        _ => return Err(ImportError::new(cube_line, "bad cube".to_string())),
+                    };
+                    let cube_ok =
+                        cube.len() == inputs.len() && cube.chars().all(|c| "01-".contains(c));
+                    if !cube_ok || (value != "0" && value != "1") {
+                        return Err(ImportError::new(
+                            cube_line,
+                            format!("bad cube {cube} {value}"),
+                        ));
+                    }
+                    cubes.push(cube.to_string());
+                    values.push(value);
+                }
+                values.dedup();
+                let cell = match values.as_slice() {
+                    [] => Cell::Const(0),
+                    [value] if inputs.is_empty() => Cell::Const((*value == "1").into()),
+                    [value] => Cell::Cover {
+                        inputs: inputs.iter().map(|t| t.to_string()).collect(),
+                        cubes,
+                        value: (*value == "1").into(),
+                    },
+                    _ => return error(format!("cover of {output} mixes on-set and off-set")),
+                };
+                netlist.drive(output.to_string(), cell, line)?;
+            }
+            ".latch" => {
+                // .latch input output [type control] [init]
+                let init = match tokens.len() {
+                    3 | 5 => 0,
+                    4 | 6 => match *tokens.last().unwrap() {
+                        "1" => 1,
+                        _ => 0, // 0, 2 (don't care) and 3 (unknown)
+                    },
+                    _ => return error("bad .latch".to_string()),
+                };
+                netlist.latch(Latch {
+                    output: tokens[2].to_string(),
+                    input: tokens[1].to_string(),
+                    enable: None,
+                    init,
+                    line,
+                })?;
+            }
+            ".end" => break,
+            ".attr" | ".param" | ".cname" | ".clock" => {}
+            directive => return error(format!("{directive} is not supported")),
+        }
+    }
+    netlist.inputs = group_ports(inputs);
+    netlist.outputs = group_ports(outputs);
+    netlist.build()
+}
+
+#[test]
+fn test_import_blif() {
+    use crate::*;
+    clear_all();
+
+    // 2 bit counter with enable
+    let blif = "\
+# counter
+.model counter
+.inputs en \\
+  clk
+.outputs q[0] q[1] carry
+.names en q0 d0
+10 1
+01 1
+.names en q0 q1 d1
+0-0 0
+-00 0
+111 0
+.latch d0 q0 re clk 0
+.latch d1 q1 re clk 1
+.names q0 q[0]
+1 1
+.names q1 q[1]
+1 1
+.names q0 q1 carry
+11 1
+.end
+";
+    let module = import_blif(blif).unwrap();
+    assert_eq!("counter", module.name);
+    let enable = module.input("en").unwrap().wire();
+    let q = module.output("q").unwrap().wires::<2>();
+    let carry = module.output("carry").unwrap().wire();
+    assert_eq!("q[1]", q.wires[1].name());
+
+    // d1 = q1 ^ (en & q0) is given by its off-set, q starts at 2
+    let mut values = vec![];
+    for i in 0..6 {
+        enable.set((i != 2).into());
+        simulate();
+        values.push(q.get_u8());
+    }
+    assert_eq!(vec![3, 0, 0, 1, 2, 3], values);
+    execute_gates();
+    assert_eq!(1, carry.get());
+
+    let error =
+        import_blif(".model bad\n.names a b\n1 1\n.names b a\n1 1\n.outputs a\n").unwrap_err();
+    assert_eq!(2, error.line);
+    assert!(error.message.contains("combinational loop"), "{error}");
+    let error = import_blif(".model bad\n.subckt x a=b\n").unwrap_err();
+    assert_eq!("import line 2: .subckt is not supported", error.to_string());
+}
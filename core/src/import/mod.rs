@@ -0,0 +1,393 @@
+mod blif;
+mod verilog;
+
+pub use blif::import_blif;
+pub use verilog::import_verilog;
+
+use crate::{input, input_const, reg_with_init, Wire, WireValue, Wires, WIRE_0, WIRE_1};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+    pub line: usize, // 1-based, 0 when the whole netlist is at fault
+    pub message: String,
+}
+
+impl ImportError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            0 => write!(f, "import: {}", self.message),
+            line => write!(f, "import line {line}: {}", self.message),
+        }
+    }
+}
+impl std::error::Error for ImportError {}
+
+/// A port of an imported module, bit `i` in `wires[i]`.
+#[derive(Debug, Clone)]
+pub struct ImportedPort {
+    pub name: String,
+    pub wires: Vec<Wire>,
+}
+
+impl ImportedPort {
+    pub fn wire(&self) -> Wire {
+        assert_eq!(
+            1,
+            self.wires.len(),
+            "Port {} is not a single bit!",
+            self.name
+        );
+        self.wires[0]
+    }
+    pub fn wires<const W: usize>(&self) -> Wires<W> {
+        assert_eq!(W, self.wires.len(), "Port {} width mismatch!", self.name);
+        Wires {
+            wires: std::array::from_fn(|i| self.wires[i]),
+        }
+    }
+}
+
+/// Ports of a netlist built into the current circuit. Inputs are `input()` wires to `set()`,
+/// every wire is named after its port like `Wires::named()` does.
+#[derive(Debug, Clone)]
+pub struct ImportedModule {
+    pub name: String,
+    pub inputs: Vec<ImportedPort>,
+    pub outputs: Vec<ImportedPort>,
+}
+
+impl ImportedModule {
+    pub fn input(&self, name: &str) -> Option<&ImportedPort> {
+        self.inputs.iter().find(|port| port.name == name)
+    }
+    pub fn output(&self, name: &str) -> Option<&ImportedPort> {
+        self.outputs.iter().find(|port| port.name == name)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum GateKind {
+    And,
+    Nand,
+    Or,
+    Nor,
+    Xor,
+    Xnor,
+    Not,
+    Buf,
+    AndNot, // a & !b
+    OrNot,  // a | !b
+    Mux,    // [a, b, select], select ? b : a
+}
+
+/// Logic driving one net.
+#[derive(Debug, Clone)]
+enum Cell {
+    Const(WireValue),
+    Gate(GateKind, Vec<String>),
+    /// BLIF `.names`: cubes of `0`/`1`/`-` per input, the cover is the on-set when `value` is 1.
+    Cover {
+        inputs: Vec<String>,
+        cubes: Vec<String>,
+        value: WireValue,
+    },
+}
+
+impl Cell {
+    fn inputs(&self) -> &[String] {
+        match self {
+            Cell::Const(_) => &[],
+            Cell::Gate(_, inputs) | Cell::Cover { inputs, .. } => inputs,
+        }
+    }
+}
+
+struct Latch {
+    output: String,
+    input: String,
+    enable: Option<String>,
+    init: WireValue,
+    line: usize,
+}
+
+/// Flat netlist read by a parser, nets by name.
+#[derive(Default)]
+struct ImportNetlist {
+    name: String,
+    inputs: Vec<(String, Vec<String>)>,
+    outputs: Vec<(String, Vec<String>)>,
+    drivers: HashMap<String, (Cell, usize)>, // cell and line
+    latches: Vec<Latch>,
+}
+
+/// Pairwise, so wide gates stay shallow.
+fn balanced(mut wires: Vec<Wire>, f: impl Fn(Wire, Wire) -> Wire) -> Wire {
+    while wires.len() > 1 {
+        wires = wires
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => f(*a, *b),
+                [a] => *a,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    wires[0]
+}
+
+fn lower_gate(kind: GateKind, inputs: &[Wire]) -> Wire {
+    let inputs = inputs.to_vec();
+    match kind {
+        GateKind::And => balanced(inputs, |a, b| a & b),
+        GateKind::Nand => !balanced(inputs, |a, b| a & b),
+        GateKind::Or => balanced(inputs, |a, b| a | b),
+        GateKind::Nor => !balanced(inputs, |a, b| a | b),
+        GateKind::Xor => balanced(inputs, |a, b| a ^ b),
+        GateKind::Xnor => !balanced(inputs, |a, b| a ^ b),
+        GateKind::Not => !inputs[0],
+        GateKind::Buf => inputs[0],
+        GateKind::AndNot => inputs[0] & !inputs[1],
+        GateKind::OrNot => inputs[0] | !inputs[1],
+        GateKind::Mux => crate::mux2(inputs[0], inputs[1], inputs[2]),
+    }
+}
+
+fn lower_cover(inputs: &[Wire], cubes: &[String], value: WireValue) -> Wire {
+    let products = cubes
+        .iter()
+        .map(|cube| {
+            let literals = cube
+                .chars()
+                .zip(inputs)
+                .filter_map(|(c, wire)| match c {
+                    '1' => Some(*wire),
+                    '0' => Some(!*wire),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            match literals.is_empty() {
+                true => input_const(1),
+                false => balanced(literals, |a, b| a & b),
+            }
+        })
+        .collect::<Vec<_>>();
+    let on_set = match products.is_empty() {
+        true => input_const(0),
+        false => balanced(products, |a, b| a | b),
+    };
+    match value {
+        1 => on_set,
+        _ => !on_set,
+    }
+}
+
+fn gate_arity(kind: GateKind) -> Option<usize> {
+    match kind {
+        GateKind::Not | GateKind::Buf => Some(1),
+        GateKind::AndNot | GateKind::OrNot => Some(2),
+        GateKind::Mux => Some(3),
+        _ => None, // 2 or more
+    }
+}
+
+impl ImportNetlist {
+    fn drive(&mut self, net: String, cell: Cell, line: usize) -> Result<(), ImportError> {
+        if let Cell::Gate(kind, inputs) = &cell {
+            let arity_ok = match gate_arity(*kind) {
+                Some(arity) => inputs.len() == arity,
+                None => inputs.len() >= 2,
+            };
+            if !arity_ok {
+                return Err(ImportError::new(
+                    line,
+                    format!("{kind:?} gate driving {net} has {} inputs", inputs.len()),
+                ));
+            }
+        }
+        if self.latches.iter().any(|latch| latch.output == net) || self.drivers.contains_key(&net) {
+            return Err(ImportError::new(
+                line,
+                format!("net {net} has more than one driver"),
+            ));
+        }
+        self.drivers.insert(net, (cell, line));
+        Ok(())
+    }
+
+    fn latch(&mut self, latch: Latch) -> Result<(), ImportError> {
+        if self.drivers.contains_key(&latch.output)
+            || self.latches.iter().any(|l| l.output == latch.output)
+        {
+            return Err(ImportError::new(
+                latch.line,
+                format!("net {} has more than one driver", latch.output),
+            ));
+        }
+        self.latches.push(latch);
+        Ok(())
+    }
+
+    /// Build the netlist into the current circuit.
+    fn build(self) -> Result<ImportedModule, ImportError> {
+        let mut wires: HashMap<&str, Wire> = HashMap::new();
+        let port_wire_name = |name: &str, bits: &[String], i: usize| match bits.len() {
+            1 => name.to_string(),
+            _ => format!("{name}[{i}]"),
+        };
+        let mut inputs = vec![];
+        for (name, bits) in &self.inputs {
+            let port = bits
+                .iter()
+                .enumerate()
+                .map(|(i, net)| {
+                    let wire = input().named(&port_wire_name(name, bits, i));
+                    if self.drivers.contains_key(net) || wires.insert(net, wire).is_some() {
+                        return Err(ImportError::new(0, format!("input {net} has a driver")));
+                    }
+                    Ok(wire)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            inputs.push(ImportedPort {
+                name: name.clone(),
+                wires: port,
+            });
+        }
+        let regs = self
+            .latches
+            .iter()
+            .map(|latch| {
+                let reg = reg_with_init(latch.init);
+                if wires.insert(&latch.output, reg.out()).is_some() {
+                    return Err(ImportError::new(
+                        latch.line,
+                        format!("input {} has a driver", latch.output),
+                    ));
+                }
+                Ok(reg)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // depth first without recursion, a net is built once all its inputs are
+        let roots = (self.outputs.iter().flat_map(|(_, bits)| bits)).chain(
+            self.latches
+                .iter()
+                .flat_map(|l| [&l.input].into_iter().chain(&l.enable)),
+        );
+        let mut visiting = HashMap::new();
+        for root in roots {
+            let mut stack = vec![root.as_str()];
+            while let Some(&net) = stack.last() {
+                if wires.contains_key(net) {
+                    stack.pop();
+                    continue;
+                }
+                let (cell, line) = self
+                    .drivers
+                    .get(net)
+                    .ok_or_else(|| ImportError::new(0, format!("net {net} has no driver")))?;
+                let missing = cell
+                    .inputs()
+                    .iter()
+                    .find(|input| !wires.contains_key(input.as_str()));
+                match missing {
+                    Some(input) => {
+                        if visiting.get(input.as_str()) == Some(&true) {
+                            return Err(ImportError::new(
+                                *line,
+                                format!("combinational loop through {input}"),
+                            ));
+                        }
+                        visiting.insert(net, true);
+                        stack.push(input);
+                    }
+                    None => {
+                        let inputs = cell
+                            .inputs()
+                            .iter()
+                            .map(|i| wires[i.as_str()])
+                            .collect::<Vec<_>>();
+                        let wire = match cell {
+                            Cell::Const(value) => input_const(*value),
+                            Cell::Gate(kind, _) => lower_gate(*kind, &inputs),
+                            Cell::Cover { cubes, value, .. } => lower_cover(&inputs, cubes, *value),
+                        };
+                        visiting.insert(net, false);
+                        wires.insert(net, wire);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        for (latch, reg) in self.latches.iter().zip(regs) {
+            reg.set_in(wires[latch.input.as_str()]);
+            if let Some(enable) = &latch.enable {
+                reg.set_enable(wires[enable.as_str()]);
+            }
+        }
+        // an output on an input, a constant or another output's net gets a buffer to carry its
+        // name, naming the net itself would rename the other one. Identical gates are shared, so
+        // the next output on the net buffers the previous buffer.
+        let mut named: HashSet<usize> = HashSet::from([WIRE_0, WIRE_1]);
+        named.extend(
+            inputs
+                .iter()
+                .flat_map(|port| port.wires.iter().map(|wire| wire.0)),
+        );
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|(name, bits)| ImportedPort {
+                name: name.clone(),
+                wires: bits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, net)| {
+                        let mut wire = wires[net.as_str()];
+                        while !named.insert(wire.0) {
+                            wire = !!wire;
+                        }
+                        wire.named(&port_wire_name(name, bits, i))
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(ImportedModule {
+            name: self.name,
+            inputs,
+            outputs,
+        })
+    }
+}
+
+/// Ports out of bit names, `a[0]`..`a[n-1]` become one vector port `a`.
+fn group_ports(bits: Vec<String>) -> Vec<(String, Vec<String>)> {
+    let mut ports: Vec<(String, Vec<String>)> = vec![];
+    for bit in bits {
+        let bus = bit.strip_suffix(']').and_then(|b| b.rsplit_once('['));
+        if let Some((name, index)) = bus {
+            if let Some(port) = ports.iter_mut().find(|(n, _)| n == name) {
+                if index.parse::<usize>() == Ok(port.1.len()) {
+                    port.1.push(bit);
+                    continue;
+                }
+            } else if index == "0" {
+                ports.push((name.to_string(), vec![bit]));
+                continue;
+            }
+        }
+        ports.push((bit.clone(), vec![bit]));
+    }
+    ports
+}
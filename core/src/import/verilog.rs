@@ -0,0 +1,482 @@
+use crate::import::{Cell, GateKind, ImportError, ImportNetlist, ImportedModule, Latch};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Number(String), // `3`, or a sized literal like `1'b0`
+    Symbol(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ImportError> {
+    let mut tokens = vec![];
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut line = 1;
+    let mut i = 0;
+    let ident_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+    while i < chars.len() {
+        let c = chars[i];
+        let rest = &chars[i..];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if rest.starts_with(&['/', '/']) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if rest.starts_with(&['/', '*']) || rest.starts_with(&['(', '*']) {
+            // comments and attributes
+            let close = if c == '/' { ['*', '/'] } else { ['*', ')'] };
+            i += 2;
+            while i < chars.len() && !chars[i..].starts_with(&close) {
+                line += (chars[i] == '\n') as usize;
+                i += 1;
+            }
+            i += 2;
+        } else if c == '\\' {
+            // escaped identifier, up to the next whitespace
+            let start = i + 1;
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), line));
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && ident_char(chars[i]) {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), line));
+        } else if c.is_ascii_digit() || c == '\'' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '\'') {
+                i += 1;
+            }
+            tokens.push((Token::Number(chars[start..i].iter().collect()), line));
+        } else if "()[]{},;:.=~".contains(c) {
+            tokens.push((Token::Symbol(c), line));
+            i += 1;
+        } else {
+            return Err(ImportError::new(
+                line,
+                format!("unexpected character {c:?}"),
+            ));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    widths: HashMap<String, (usize, usize)>, // declared [msb:lsb] of vectors
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        let index = self.position.min(self.tokens.len().saturating_sub(1));
+        self.tokens.get(index).map_or(0, |(_, line)| *line)
+    }
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ImportError> {
+        Err(ImportError::new(self.line(), message))
+    }
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+    fn next(&mut self) -> Result<Token, ImportError> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token.map_or_else(|| self.error("unexpected end of file"), Ok)
+    }
+    fn is_symbol(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+    fn expect(&mut self, symbol: char) -> Result<(), ImportError> {
+        match self.next()? {
+            Token::Symbol(c) if c == symbol => Ok(()),
+            token => self.error(format!("expected {symbol:?}, found {token:?}")),
+        }
+    }
+    fn ident(&mut self) -> Result<String, ImportError> {
+        match self.next()? {
+            Token::Ident(name) => Ok(name),
+            token => self.error(format!("expected a name, found {token:?}")),
+        }
+    }
+    fn number(&mut self) -> Result<usize, ImportError> {
+        match self.next()? {
+            Token::Number(n) => n.parse().or_else(|_| self.error(format!("bad number {n}"))),
+            token => self.error(format!("expected a number, found {token:?}")),
+        }
+    }
+
+    /// Optional `[msb:lsb]`.
+    fn range(&mut self) -> Result<Option<(usize, usize)>, ImportError> {
+        if !self.is_symbol('[') {
+            return Ok(None);
+        }
+        self.expect('[')?;
+        let msb = self.number()?;
+        self.expect(':')?;
+        let lsb = self.number()?;
+        self.expect(']')?;
+        Ok(Some((msb, lsb)))
+    }
+
+    /// Nets of a declared name, lsb first.
+    fn bits(&self, name: &str) -> Vec<String> {
+        match self.widths.get(name) {
+            None => vec![name.to_string()],
+            Some(&(msb, lsb)) => {
+                let indices: Vec<usize> = match msb >= lsb {
+                    true => (lsb..=msb).collect(),
+                    false => (msb..=lsb).rev().collect(),
+                };
+                indices.iter().map(|i| format!("{name}[{i}]")).collect()
+            }
+        }
+    }
+
+    /// A single bit: `name`, `name[3]`, or a constant `1'b0`/`1'b1`.
+    fn net(&mut self, netlist: &mut ImportNetlist) -> Result<String, ImportError> {
+        match self.next()? {
+            Token::Number(n) => {
+                let value = match n.as_str() {
+                    "1'b0" | "1'h0" | "0" => 0,
+                    "1'b1" | "1'h1" | "1" => 1,
+                    _ => return self.error(format!("constant {n} is not a single bit")),
+                };
+                let net = format!("$const{value}");
+                if !netlist.drivers.contains_key(&net) {
+                    netlist.drive(net.clone(), Cell::Const(value), self.line())?;
+                }
+                Ok(net)
+            }
+            Token::Ident(name) => match self.is_symbol('[') {
+                true => {
+                    self.expect('[')?;
+                    let index = self.number()?;
+                    self.expect(']')?;
+                    Ok(format!("{name}[{index}]"))
+                }
+                false if self.widths.contains_key(&name) => {
+                    self.error(format!("vector {name} used as a single bit"))
+                }
+                false => Ok(name),
+            },
+            token => self.error(format!("expected a net, found {token:?}")),
+        }
+    }
+
+    /// `input [3:0] a, b` up to the `;` or `)`, `ports` keeps the declaration order.
+    fn declaration(
+        &mut self,
+        kind: &str,
+        ports: &mut Vec<(String, String)>,
+    ) -> Result<(), ImportError> {
+        let range = self.range()?;
+        loop {
+            let name = self.ident()?;
+            if let Some(range) = range {
+                self.widths.insert(name.clone(), range);
+            }
+            if kind != "wire" {
+                ports.retain(|(_, port)| *port != name);
+                ports.push((kind.to_string(), name));
+            }
+            // ANSI port lists go on with the next direction
+            let direction = match self.tokens.get(self.position + 1) {
+                Some((Token::Ident(next), _)) => next == "input" || next == "output",
+                _ => false,
+            };
+            if !self.is_symbol(',') || direction {
+                return Ok(());
+            }
+            self.expect(',')?;
+        }
+    }
+}
+
+fn gate_kind(name: &str) -> Option<GateKind> {
+    let kind = match name
+        .trim_start_matches('$')
+        .trim_start_matches('_')
+        .trim_end_matches('_')
+    {
+        "and" | "AND" => GateKind::And,
+        "nand" | "NAND" => GateKind::Nand,
+        "or" | "OR" => GateKind::Or,
+        "nor" | "NOR" => GateKind::Nor,
+        "xor" | "XOR" => GateKind::Xor,
+        "xnor" | "XNOR" => GateKind::Xnor,
+        "not" | "NOT" => GateKind::Not,
+        "buf" | "BUF" => GateKind::Buf,
+        "ANDNOT" => GateKind::AndNot,
+        "ORNOT" => GateKind::OrNot,
+        "MUX" => GateKind::Mux,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// Build a flat structural Verilog module into the current circuit.
+///
+/// Supported are the gate primitives (`and`, `nand`, `or`, `nor`, `xor`, `xnor`, `not`, `buf`,
+/// output first), the Yosys internal cells (`$_AND_`, `$_NOT_`, `$_MUX_`... with named ports),
+/// `$_DFF_P_`/`$_DFFE_PP_` flip-flops on the default clock, and `assign` of a net or a constant.
+pub fn import_verilog(source: &str) -> Result<ImportedModule, ImportError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+        widths: HashMap::new(),
+    };
+    let mut netlist = ImportNetlist::default();
+    let mut ports = vec![];
+
+    if parser.ident()? != "module" {
+        return parser.error("expected a module");
+    }
+    netlist.name = parser.ident()?;
+    if parser.is_symbol('(') {
+        parser.expect('(')?;
+        while !parser.is_symbol(')') {
+            let name = parser.ident()?;
+            match name.as_str() {
+                "input" | "output" => parser.declaration(&name, &mut ports)?,
+                _ => ports.push((String::new(), name)), // direction declared in the body
+            }
+            if parser.is_symbol(',') {
+                parser.expect(',')?;
+            }
+        }
+        parser.expect(')')?;
+    }
+    parser.expect(';')?;
+
+    loop {
+        let line = parser.line();
+        let keyword = parser.ident()?;
+        match keyword.as_str() {
+            "endmodule" => break,
+            "input" | "output" | "wire" => {
+                parser.declaration(&keyword, &mut ports)?;
+                parser.expect(';')?;
+            }
+            "assign" => {
+                let output = parser.net(&mut netlist)?;
+                parser.expect('=')?;
+                let inverted = parser.is_symbol('~');
+                if inverted {
+                    parser.expect('~')?;
+                }
+                let input = parser.net(&mut netlist)?;
+                let kind = if inverted {
+                    GateKind::Not
+                } else {
+                    GateKind::Buf
+                };
+                netlist.drive(output, Cell::Gate(kind, vec![input]), line)?;
+                parser.expect(';')?;
+            }
+            cell_type => {
+                // primitive or cell, the instance name is optional for primitives
+                if !parser.is_symbol('(') {
+                    parser.ident()?;
+                }
+                parser.expect('(')?;
+                let mut positional = vec![];
+                let mut named = HashMap::new();
+                while !parser.is_symbol(')') {
+                    if parser.is_symbol('.') {
+                        parser.expect('.')?;
+                        let port = parser.ident()?;
+                        parser.expect('(')?;
+                        named.insert(port, parser.net(&mut netlist)?);
+                        parser.expect(')')?;
+                    } else {
+                        positional.push(parser.net(&mut netlist)?);
+                    }
+                    if parser.is_symbol(',') {
+                        parser.expect(',')?;
+                    }
+                }
+                parser.expect(')')?;
+                parser.expect(';')?;
+
+                let mut pin = |name: &str| {
+                    named.remove(name).ok_or_else(|| {
+                        ImportError::new(line, format!("{cell_type} without port {name}"))
+                    })
+                };
+                match (cell_type, gate_kind(cell_type)) {
+                    ("$_DFF_P_" | "$_DFFE_PP_", _) => {
+                        pin("C")?; // the default clock
+                        let enable = match cell_type {
+                            "$_DFFE_PP_" => Some(pin("E")?),
+                            _ => None,
+                        };
+                        netlist.latch(Latch {
+                            input: pin("D")?,
+                            output: pin("Q")?,
+                            enable,
+                            init: 0,
+                            line,
+                        })?;
+                    }
+                    (_, Some(kind)) if cell_type.starts_with('$') => {
+                        let pins: &[&str] = match kind {
+                            GateKind::Not | GateKind::Buf => &["A"],
+                            GateKind::Mux => &["A", "B", "S"],
+                            _ => &["A", "B"],
+                        };
+                        let inputs = pins.iter().map(|p| pin(p)).collect::<Result<Vec<_>, _>>()?;
+                        netlist.drive(pin("Y")?, Cell::Gate(kind, inputs), line)?;
+                    }
+                    (_, Some(kind)) if !positional.is_empty() => {
+                        let output = positional.remove(0);
+                        netlist.drive(output, Cell::Gate(kind, positional), line)?;
+                    }
+                    _ => {
+                        return Err(ImportError::new(
+                            line,
+                            format!("cell {cell_type} is not supported"),
+                        ));
+                    }
+                }
+                // a pin the cell does not have, or a typo
+                if let Some(port) = named.keys().min() {
+                    return Err(ImportError::new(
+                        line,
+                        format!("{cell_type} has no port {port}"),
+                    ));
+                }
+            }
+        }
+    }
+
+    for (kind, name) in &ports {
+        let bits = parser.bits(name);
+        match kind.as_str() {
+            "input" => netlist.inputs.push((name.clone(), bits)),
+            "output" => netlist.outputs.push((name.clone(), bits)),
+            _ => {
+                return Err(ImportError::new(
+                    0,
+                    format!("port {name} without a direction"),
+                ))
+            }
+        }
+    }
+    netlist.build()
+}
+
+#[test]
+fn test_import_verilog() {
+    use crate::*;
+    clear_all();
+
+    let verilog = "
+// 4 bit accumulator, as written by yosys write_verilog -noattr
+module acc(clk, en, d, sum, zero);
+  input clk;
+  input en;
+  input [3:0] d;
+  output [3:0] sum;
+  output zero;
+  wire [3:0] q;
+  wire [3:0] c;
+  wire n0, n1, n2;
+  (* src = \"acc.v:3\" *)
+  xor x0 (sum[0], q[0], d[0]);
+  and a0 (c[0], q[0], d[0]);
+  \\$_XOR_  x1 (.A(q[1]), .B(d[1]), .Y(n0));
+  \\$_XOR_  x2 (.A(n0), .B(c[0]), .Y(sum[1]));
+  \\$_MUX_  m1 (.A(q[1]), .B(c[0]), .S(n0), .Y(c[1]));
+  xor (n1, q[2], d[2]);
+  xor (sum[2], n1, c[1]);
+  \\$_MUX_  m2 (.A(q[2]), .B(c[1]), .S(n1), .Y(c[2]));
+  xnor x3 (n2, q[3], d[3]);
+  \\$_XNOR_ x4 (.A(n2), .B(c[2]), .Y(sum[3]));
+  nor z (zero, q[0], q[1], q[2], q[3]);
+  /* the accumulator regs */
+  \\$_DFFE_PP_ r0 (.C(clk), .D(sum[0]), .E(en), .Q(q[0]));
+  \\$_DFFE_PP_ r1 (.C(clk), .D(sum[1]), .E(en), .Q(q[1]));
+  \\$_DFFE_PP_ r2 (.C(clk), .D(sum[2]), .E(en), .Q(q[2]));
+  \\$_DFFE_PP_ r3 (.C(clk), .D(sum[3]), .E(en), .Q(q[3]));
+endmodule
+";
+    let module = import_verilog(verilog).unwrap();
+    assert_eq!("acc", module.name);
+    let names = module
+        .inputs
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["clk", "en", "d"], names);
+    let enable = module.input("en").unwrap().wire();
+    let d = module.input("d").unwrap().wires::<4>();
+    let sum = module.output("sum").unwrap().wires::<4>();
+    let zero = module.output("zero").unwrap().wire();
+    assert_eq!("d[2]", d.wires[2].name());
+
+    let mut total = 0u8;
+    for i in 0..10u8 {
+        let value = i * 7 % 16;
+        d.set_u8(value);
+        enable.set((i != 4).into());
+        execute_gates();
+        assert_eq!(total == 0, zero.is_one());
+        assert_eq!((total + value) % 16, sum.get_u8(), "cycle {i}");
+        clock_tick();
+        if i != 4 {
+            total = (total + value) % 16;
+        }
+    }
+
+    // ANSI ports and assign
+    clear_all();
+    let module = import_verilog(
+        "module inv(input [1:0] a, output [1:0] y, output one);\n\
+         assign y[0] = ~a[0];\n  assign y[1] = a[1];\n  assign one = 1'b1;\nendmodule",
+    )
+    .unwrap();
+    let a = module.input("a").unwrap().wires::<2>();
+    let y = module.output("y").unwrap().wires::<2>();
+    a.set_u8(0b10);
+    execute_gates();
+    assert_eq!(0b11, y.get_u8());
+    assert_eq!(1, module.output("one").unwrap().wire().get());
+    // the output buffers a[1] and the constant instead of renaming them
+    assert_eq!(("a[1]", "y[1]"), (&*a.wires[1].name(), &*y.wires[1].name()));
+    assert_ne!(y.wires[1], a.wires[1]);
+    assert_ne!(WIRE_1, module.output("one").unwrap().wire().0);
+    assert_eq!(None, current().given_wire_name(input_const(1)));
+
+    // three outputs on one net
+    clear_all();
+    let module = import_verilog(
+        "module m(a, x, y, z);\n input a;\n output x, y, z;\n \
+         not (x, a);\n assign y = x;\n assign z = x;\nendmodule",
+    )
+    .unwrap();
+    let outputs = ["x", "y", "z"].map(|name| module.output(name).unwrap().wire());
+    assert_eq!(["x", "y", "z"], outputs.map(|wire| wire.name()));
+    module.input("a").unwrap().wire().set(0);
+    execute_gates();
+    assert_eq!([1, 1, 1], outputs.map(|wire| wire.get()));
+
+    let error = import_verilog("module m(a);\n input a;\n foo f (a);\nendmodule").unwrap_err();
+    assert_eq!(
+        "import line 3: cell foo is not supported",
+        error.to_string()
+    );
+    let error = import_verilog("module m(y);\n output y;\n and (y, a, b);\nendmodule").unwrap_err();
+    assert_eq!("import: net a has no driver", error.to_string());
+    let error = import_verilog(
+        "module m(c, d, q);\n input c, d;\n output q;\n \
+         \\$_DFF_P_ r (.C(c), .D(d), .R(d), .Q(q));\nendmodule",
+    )
+    .unwrap_err();
+    assert_eq!("import line 4: $_DFF_P_ has no port R", error.to_string());
+}
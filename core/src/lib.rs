@@ -4,23 +4,48 @@
 #![allow(clippy::needless_range_loop)]
 
 mod basic;
+mod clock;
 mod component_lib;
+mod equivalence;
 mod export;
 mod external;
+mod fault;
+mod import;
+mod model_check;
+mod optimize;
 mod reg;
+mod report;
+mod sat;
+mod scope;
+mod simulator;
+mod timing;
+mod validate;
 mod wires;
 
 pub use basic::*;
+pub use clock::*;
 pub use component_lib::*;
+pub use equivalence::*;
 pub use export::*;
 pub use external::*;
+pub use fault::*;
+pub use import::*;
+pub use model_check::*;
+pub use optimize::*;
 pub use reg::*;
+pub use report::*;
+pub use scope::*;
+pub use simulator::*;
+pub use timing::*;
+pub use validate::*;
 pub use wires::*;
 
 mod tests;
 pub use tests::*;
 
 use std::sync::{LockResult, Mutex, MutexGuard};
+// Netlists are per-thread (see `Circuit`), tests don't need this. It only guards the one piece
+// of process-wide state left, the ROM device content of cpu_v1 (`set_rom_content()`).
 static GLOBAL_LOCK: Mutex<()> = Mutex::new(());
 pub fn global_lock() -> LockResult<MutexGuard<'static, ()>> {
     GLOBAL_LOCK.lock()
@@ -0,0 +1,267 @@
+use crate::sat::{Lit, Solver};
+use crate::{current, Circuit, Wire, WireValue, WIRE_0, WIRE_1};
+use std::fmt::{Display, Formatter};
+
+/// Inputs, cycle by cycle, that make an assertion 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssertionTrace {
+    pub assertion: Wire,
+    pub assertion_name: String,
+    pub cycle: usize, // the assertion is 0 after `execute_gates()` of this cycle
+    pub inputs: Vec<Wire>,
+    pub input_names: Vec<String>,
+    pub values: Vec<Vec<WireValue>>, // per cycle up to `cycle`, per input
+}
+
+impl AssertionTrace {
+    /// Set the inputs of `cycle`.
+    pub fn apply(&self, cycle: usize) {
+        for (wire, value) in self.inputs.iter().zip(&self.values[cycle]) {
+            wire.set(*value);
+        }
+    }
+
+    /// Run the trace from the state the check started in, up to `execute_gates()` of the
+    /// failing cycle.
+    pub fn replay(&self) {
+        for cycle in 0..=self.cycle {
+            self.apply(cycle);
+            current().execute_gates();
+            if cycle < self.cycle {
+                current().clock_tick();
+            }
+        }
+    }
+}
+
+impl Display for AssertionTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "assertion {} fails in cycle {}",
+            self.assertion_name, self.cycle
+        )?;
+        for (cycle, values) in self.values.iter().enumerate() {
+            let inputs = self.input_names.iter().zip(values);
+            let inputs = inputs.map(|(name, value)| format!(" {name}={value}"));
+            writeln!(f, "  cycle {cycle}:{}", inputs.collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelCheck {
+    /// Every assertion is 1 in all cycles checked, whatever the inputs.
+    Holds,
+    Violated(AssertionTrace),
+}
+
+impl ModelCheck {
+    pub fn holds(&self) -> bool {
+        matches!(self, ModelCheck::Holds)
+    }
+}
+
+impl Circuit {
+    /// Regs and free wires `wires` depend on, through any number of clock ticks.
+    fn cone_of_influence(&self, wires: &[Wire]) -> (Vec<usize>, Vec<Wire>) {
+        let mut is_gate = vec![None; self.wires.len()];
+        for gate in &self.gates {
+            is_gate[gate.wire_out.0] = Some([gate.wire_a, gate.wire_b]);
+        }
+        let mut reg_of = vec![None; self.wires.len()];
+        for (index, reg) in self.regs.iter().enumerate() {
+            reg_of[reg.wire_out.0] = Some(index);
+        }
+        let (mut regs, mut inputs) = (vec![], vec![]);
+        let mut visited = vec![false; self.wires.len()];
+        let mut stack = wires.to_vec();
+        while let Some(wire) = stack.pop() {
+            if visited[wire.0] || wire.0 == WIRE_0 || wire.0 == WIRE_1 {
+                continue;
+            }
+            visited[wire.0] = true;
+            if let Some(gate_inputs) = is_gate[wire.0] {
+                stack.extend(gate_inputs);
+            } else if let Some(index) = reg_of[wire.0] {
+                let reg = &self.regs[index];
+                stack.extend(reg.wire_in.iter().chain(&reg.enable).chain(&self.reset));
+                regs.push(index);
+            } else {
+                inputs.push(wire);
+            }
+        }
+        regs.sort();
+        inputs.sort_by_key(|wire| wire.0);
+        (regs, inputs)
+    }
+
+    /// Bounded model check: look for inputs that make any of the `assertions` 0 within `cycles`
+    /// cycles of `execute_gates()` then `clock_tick()`, starting from the current reg values.
+    ///
+    /// The netlist is unrolled cycle by cycle into SAT, with the reset, enables and clock domain
+    /// ratios of `clock_tick()`, and the shortest failing trace is returned. Every wire that is
+    /// neither a gate nor a reg output is a free input in each cycle, including wires driven by
+    /// externals, so a trace through an external may not be reachable in simulation.
+    pub fn bounded_model_check(&self, assertions: &[Wire], cycles: usize) -> ModelCheck {
+        let (regs, inputs) = self.cone_of_influence(assertions);
+        let mut solver = Solver::new();
+        let one = solver.true_lit();
+        let constant = |value: WireValue| if value == 1 { one } else { !one };
+        let mut state = regs
+            .iter()
+            .map(|index| constant(self.wires[self.regs[*index].wire_out.0]))
+            .collect::<Vec<_>>();
+        let ticks = self.clock_domains.upcoming(cycles);
+
+        let mut frames: Vec<Vec<Lit>> = vec![];
+        for cycle in 0..cycles {
+            let mut literals = vec![None; self.wires.len()];
+            for (index, lit) in regs.iter().zip(&state) {
+                literals[self.regs[*index].wire_out.0] = Some(*lit);
+            }
+            let frame = inputs
+                .iter()
+                .map(|wire| {
+                    let lit = solver.new_var();
+                    literals[wire.0] = Some(lit);
+                    lit
+                })
+                .collect::<Vec<_>>();
+            frames.push(frame);
+            let mut outputs = assertions.to_vec();
+            for index in &regs {
+                let reg = &self.regs[*index];
+                outputs.extend(reg.wire_in.iter().chain(&reg.enable));
+            }
+            if !regs.is_empty() {
+                outputs.extend(self.reset);
+            }
+            if let Err(wire) = self.encode_cone(&mut solver, &mut literals, &outputs) {
+                unreachable!("{} is outside the cone of influence", self.wire_name(wire));
+            }
+
+            let failing = assertions
+                .iter()
+                .map(|wire| !literals[wire.0].unwrap())
+                .collect::<Vec<_>>();
+            let bad = solver.or(&failing);
+            if solver.solve(&[bad]) {
+                let assertion = assertions[failing
+                    .iter()
+                    .position(|lit| solver.model_value(*lit))
+                    .unwrap()];
+                let values = frames
+                    .iter()
+                    .map(|frame| {
+                        let values = frame
+                            .iter()
+                            .map(|lit| solver.model_value(*lit) as WireValue);
+                        values.collect()
+                    })
+                    .collect();
+                return ModelCheck::Violated(AssertionTrace {
+                    assertion,
+                    assertion_name: self.wire_name(assertion),
+                    cycle,
+                    inputs: inputs.clone(),
+                    input_names: inputs.iter().map(|wire| self.wire_name(*wire)).collect(),
+                    values,
+                });
+            }
+            // holds in this cycle, which also helps the later ones
+            solver.add_clause(&[!bad]);
+
+            let reset = self.reset.map(|wire| literals[wire.0].unwrap());
+            state = regs
+                .iter()
+                .zip(&state)
+                .map(|(index, keep)| {
+                    let reg = &self.regs[*index];
+                    if (ticks[cycle] >> reg.domain.0) & 1 == 0 {
+                        return *keep;
+                    }
+                    let value = reg.wire_in.map_or(!one, |wire| literals[wire.0].unwrap());
+                    let loaded = match reg.enable {
+                        Some(enable) => solver.mux(*keep, value, literals[enable.0].unwrap()),
+                        None => value,
+                    };
+                    match reset {
+                        Some(reset) => solver.mux(loaded, constant(reg.init.unwrap_or(0)), reset),
+                        None => loaded,
+                    }
+                })
+                .collect();
+        }
+        ModelCheck::Holds
+    }
+}
+
+pub fn bounded_model_check(assertions: &[Wire], cycles: usize) -> ModelCheck {
+    current().bounded_model_check(assertions, cycles)
+}
+
+#[test]
+fn test_bounded_model_check() {
+    use crate::*;
+    clear_all();
+
+    // a 3 bit counter, count < 6 fails after 6 enabled ticks without reset
+    let reset = reset_signal().named("reset");
+    let enable = input().named("enable");
+    let count = reg_w::<3>().named("count");
+    count.set_in(add_naive(count.out, Wires::parse_u8(1)).sum);
+    count.set_enable(enable);
+    let below_6 = (!(count.out.wires[2] & count.out.wires[1])).named("below_6");
+
+    assert!(bounded_model_check(&[below_6], 6).holds());
+    let ModelCheck::Violated(trace) = bounded_model_check(&[below_6], 10) else {
+        panic!("no trace");
+    };
+    assert_eq!(6, trace.cycle);
+    assert_eq!("below_6", trace.assertion_name);
+    assert_eq!(vec!["reset", "enable"], trace.input_names);
+    assert!(trace.values[..6].iter().all(|values| values == &[0, 1]));
+    let text = trace.to_string();
+    assert!(text.starts_with("assertion below_6 fails in cycle 6\n  cycle 0: reset=0 enable=1\n"));
+    assert_eq!(
+        7,
+        text.lines()
+            .filter(|line| line.starts_with("  cycle "))
+            .count()
+    );
+    let start = snapshot();
+    trace.replay();
+    assert_eq!((6, 0), (count.out.get_u8(), below_6.get()));
+    reset.set(1);
+    simulate();
+    assert_eq!(0, count.out.get_u8());
+    restore(&start);
+
+    // wrapping at 5 keeps it below 6, and the state the check starts in counts
+    clear_all();
+    let count = reg_w::<3>();
+    let at_5 = count.out.wires[2] & !count.out.wires[1] & count.out.wires[0];
+    let next = add_naive(count.out, Wires::parse_u8(1)).sum;
+    count.set_in(mux2_w(next, Wires::parse_u8(0), at_5));
+    let below_6 = !(count.out.wires[2] & count.out.wires[1]);
+    assert!(bounded_model_check(&[below_6], 20).holds());
+    count.out.set_u8(7);
+    let ModelCheck::Violated(trace) = bounded_model_check(&[below_6], 20) else {
+        panic!("no trace");
+    };
+    assert_eq!(0, trace.cycle);
+    assert!(trace.inputs.is_empty());
+
+    // a slow domain ticks every other cycle
+    clear_all();
+    let slow = add_clock_domain("slow", 1, 2);
+    let count = clock_domain(slow, reg_w::<2>);
+    count.set_in(add_naive(count.out, Wires::parse_u8(1)).sum);
+    let below_3 = !(count.out.wires[1] & count.out.wires[0]);
+    let ModelCheck::Violated(trace) = bounded_model_check(&[below_3], 10) else {
+        panic!("no trace");
+    };
+    assert_eq!(6, trace.cycle);
+}
@@ -0,0 +1,367 @@
+use crate::{
+    current, Circuit, ExecuteSegment, Gate, RegValue, Regs, Wire, WireSetter, Wires, WIRE_0, WIRE_1,
+};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// Gate counts of each pass, and where every old wire went.
+#[derive(Debug, Clone)]
+pub struct OptimizeResult {
+    pub gates_before: usize,
+    pub gates_folded: usize, // after constant and double negation folding
+    pub gates_after: usize,  // after dead gate removal
+    pub wires_before: usize,
+    pub wires_after: usize,
+    wire_map: Vec<Option<usize>>, // old wire -> new wire, None if removed
+}
+
+impl OptimizeResult {
+    /// New handle of a wire, panics if it was removed as dead.
+    pub fn wire(&self, wire: Wire) -> Wire {
+        match self.wire_map[wire.0] {
+            Some(index) => Wire(index),
+            None => panic!("w{} has been removed, keep it in optimize()", wire.0),
+        }
+    }
+    pub fn wires<const W: usize>(&self, wires: Wires<W>) -> Wires<W> {
+        Wires {
+            wires: wires.wires.map(|wire| self.wire(wire)),
+        }
+    }
+    /// Regs keep their index, only their output wires move.
+    pub fn regs<const W: usize>(&self, mut regs: Regs<W>) -> Regs<W> {
+        regs.out = self.wires(regs.out);
+        regs
+    }
+}
+
+impl Display for OptimizeResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "gates {} -> {} (folding) -> {} (dead gates), wires {} -> {}",
+            self.gates_before,
+            self.gates_folded,
+            self.gates_after,
+            self.wires_before,
+            self.wires_after
+        )
+    }
+}
+
+/// Gates rebuilt on folded inputs, indexed like a netlist of its own.
+struct Folder {
+    origin: Vec<usize>, // first old wire mapped to each new wire
+    gates: Vec<Gate>,
+    driver: Vec<Option<usize>>, // gate of each new wire
+    gates_map: HashMap<(usize, usize), Wire>,
+    not_of: HashMap<usize, Wire>, // nand(x, x) -> x
+}
+
+impl Folder {
+    fn new() -> Self {
+        Self {
+            origin: vec![WIRE_0, WIRE_1],
+            gates: Vec::new(),
+            driver: vec![None, None],
+            gates_map: HashMap::new(),
+            not_of: HashMap::new(),
+        }
+    }
+
+    fn source(&mut self, old: usize) -> Wire {
+        self.origin.push(old);
+        self.driver.push(None);
+        Wire(self.origin.len() - 1)
+    }
+
+    fn nand(&mut self, old: usize, a: Wire, b: Wire) -> Wire {
+        match (a.0, b.0) {
+            (WIRE_0, _) | (_, WIRE_0) => Wire(WIRE_1),
+            (WIRE_1, WIRE_1) => Wire(WIRE_0),
+            (WIRE_1, _) => self.not(old, b),
+            (_, WIRE_1) => self.not(old, a),
+            (a, b) if a == b => self.not(old, Wire(a)),
+            _ => self.gate(old, a, b),
+        }
+    }
+
+    fn not(&mut self, old: usize, a: Wire) -> Wire {
+        match self.not_of.get(&a.0) {
+            Some(inner) => *inner,
+            None => self.gate(old, a, a),
+        }
+    }
+
+    fn gate(&mut self, old: usize, a: Wire, b: Wire) -> Wire {
+        let key = (a.0.min(b.0), a.0.max(b.0));
+        if let Some(out) = self.gates_map.get(&key) {
+            return *out;
+        }
+        let out = self.source(old);
+        self.driver[out.0] = Some(self.gates.len());
+        self.gates.push(Gate {
+            wire_a: a,
+            wire_b: b,
+            wire_out: out,
+        });
+        self.gates_map.insert(key, out);
+        if a.0 == b.0 {
+            self.not_of.insert(out.0, a);
+        }
+        out
+    }
+}
+
+impl Circuit {
+    /// Fold constants and double negations, then drop gates that reach neither a reg input,
+    /// enable or reset nor one of the `keep` wires. Wire indices change, map old handles through the result.
+    ///
+    /// Stuck-at faults have to be cleared first, toggle counting starts over on the new wires.
+    pub fn optimize(&mut self, keep: &[Wire]) -> OptimizeResult {
+        assert!(self.frozen, "Optimize a frozen netlist only, see freeze()!");
+        assert!(
+            self.externals.is_empty(),
+            "Optimize wire/reg only! Externals are not supported!"
+        );
+        // folding would keep the rewired inputs of the faults for good
+        assert!(
+            self.faults.stuck.is_empty(),
+            "Clear faults before optimize()!"
+        );
+        let wire_count = self.wires.len();
+        let mut driver = vec![None; wire_count];
+        for gate in &self.gates {
+            driver[gate.wire_out.0] = Some(gate);
+        }
+
+        // fold, gate inputs are always created before the gate
+        let mut folder = Folder::new();
+        let mut folded = vec![Wire(WIRE_0), Wire(WIRE_1)];
+        for old in 2..wire_count {
+            let wire = match driver[old] {
+                Some(gate) => folder.nand(old, folded[gate.wire_a.0], folded[gate.wire_b.0]),
+                None => folder.source(old),
+            };
+            folded.push(wire);
+        }
+
+        // dead gates
+        let mut live = vec![false; folder.origin.len()];
+        let reg_ins = self
+            .regs
+            .iter()
+            .flat_map(|reg| reg.wire_in.into_iter().chain(reg.enable));
+        for wire in keep.iter().copied().chain(reg_ins).chain(self.reset) {
+            live[folded[wire.0].0] = true;
+        }
+        for gate in folder.gates.iter().rev() {
+            if live[gate.wire_out.0] {
+                live[gate.wire_a.0] = true;
+                live[gate.wire_b.0] = true;
+            }
+        }
+
+        // remap
+        let mut compact = vec![None; folder.origin.len()];
+        let mut origin = vec![];
+        for (wire, old) in folder.origin.iter().enumerate() {
+            if folder.driver[wire].is_none() || live[wire] {
+                compact[wire] = Some(origin.len());
+                origin.push(*old);
+            }
+        }
+        let map = |wire: Wire| Wire(compact[wire.0].unwrap());
+        let gates = folder
+            .gates
+            .iter()
+            .filter(|gate| live[gate.wire_out.0])
+            .map(|gate| Gate {
+                wire_a: map(gate.wire_a),
+                wire_b: map(gate.wire_b),
+                wire_out: map(gate.wire_out),
+            })
+            .collect::<Vec<_>>();
+        let wire_map = folded
+            .iter()
+            .map(|wire| compact[wire.0])
+            .collect::<Vec<_>>();
+
+        let result = OptimizeResult {
+            gates_before: self.gates.len(),
+            gates_folded: folder.gates.len(),
+            gates_after: gates.len(),
+            wires_before: wire_count,
+            wires_after: origin.len(),
+            wire_map,
+        };
+
+        self.wires = origin.iter().map(|old| self.wires[*old]).collect();
+        self.latencies = origin.iter().map(|old| self.latencies[*old]).collect();
+        for gate in &gates {
+            let latency = self.latencies[gate.wire_a.0].max(self.latencies[gate.wire_b.0]) + 1;
+            self.latencies[gate.wire_out.0] = latency;
+        }
+        self.set_by = origin
+            .iter()
+            .map(|old| self.set_by.get(*old).copied().unwrap_or(WireSetter::Nobody))
+            .collect();
+        self.regs = self
+            .regs
+            .iter()
+            .map(|reg| RegValue {
+                wire_in: reg.wire_in.map(|wire| result.wire(wire)),
+                wire_out: result.wire(reg.wire_out),
+                temp_value: reg.temp_value,
+                init: reg.init,
+                enable: reg.enable.map(|wire| result.wire(wire)),
+                domain: reg.domain,
+            })
+            .collect();
+        self.reset = self.reset.map(|wire| result.wire(wire));
+        self.scopes.remap(&origin, &result.wire_map);
+        self.gates_map = gates
+            .iter()
+            .map(|gate| ((gate.wire_a.0, gate.wire_b.0), gate.wire_out))
+            .collect();
+        self.execute_segments = match gates.len() {
+            0 => vec![],
+            n => vec![ExecuteSegment::Gates(0..n)],
+        };
+        self.gates = gates;
+        self.lanes.clear();
+        self.compiled = None;
+        self.event_driven = None;
+        if self.is_toggle_counting() {
+            self.set_toggle_counting(true);
+        }
+        result
+    }
+}
+
+pub fn optimize(keep: &[Wire]) -> OptimizeResult {
+    current().optimize(keep)
+}
+
+#[test]
+fn test_optimize() {
+    use crate::*;
+
+    // rom lookup, double negations, constant inputs and an unused adder
+    let build = || {
+        let rom = [3u8, 1, 4, 1, 5, 9, 2, 6].map(Wires::<4>::parse_u8);
+        let pc = reg_w::<3>();
+        pc.set_in(add_naive(pc.out, Wires::parse_u8(1)).sum);
+        let data = mux8_w(&rom, pc.out);
+        let step = input_w::<4>();
+        step.set_u8(5);
+        let acc = reg_w::<4>();
+        let sum = add_naive(acc.out, !!data).sum;
+        acc.set_in(sum & !input_w_const::<4>(0) | (step & Wires::parse_u8(0)));
+        let _unused = add_naive(step, data);
+        (pc, acc, sum)
+    };
+    let run = |acc: Regs<4>, sum: Wires<4>| {
+        (0..20)
+            .map(|_| {
+                simulate();
+                (acc.out.get_u8(), sum.get_u8())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut original = Circuit::new();
+    let expected = original.enter(|| {
+        let (_, acc, sum) = build();
+        run(acc, sum)
+    });
+
+    let mut optimized = Circuit::new();
+    let (result, actual) = optimized.enter(|| {
+        let (pc, acc, sum) = build();
+        pc.named("pc");
+        freeze().unwrap();
+        let result = optimize(&sum.wires);
+        let (acc, sum) = (result.regs(acc), result.wires(sum));
+        validate().unwrap();
+        assert_eq!("pc[2]", result.wires(pc.out).wires[2].name());
+        (result, run(acc, sum))
+    });
+
+    assert_eq!(expected, actual);
+    assert_eq!(
+        "gates 269 -> 129 (folding) -> 68 (dead gates), wires 282 -> 81",
+        result.to_string()
+    );
+    assert_eq!(result.gates_before, original.get_statistics().gate_count);
+    assert_eq!(result.gates_after, optimized.get_statistics().gate_count);
+    assert!(result.gates_folded < result.gates_before);
+    assert!(result.gates_after < result.gates_folded);
+    assert_eq!(result.wires_after, optimized.get_statistics().wire_count);
+}
+
+#[test]
+fn test_optimize_double_negation() {
+    use crate::*;
+    clear_all();
+
+    let a = input();
+    let b = input();
+    let out = !!!!(a & b) | !!(b & input_const(1));
+    let nots = !!!a;
+    a.set(0);
+    b.set(0);
+    freeze().unwrap();
+    let result = optimize(&[out, nots]);
+    // a & b = !nand(a, b), the or is nand(!(a & b), !b)
+    assert_eq!(4, result.gates_after);
+    for (va, vb) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        result.wire(a).set(va);
+        result.wire(b).set(vb);
+        execute_gates();
+        assert_eq!((va & vb) | vb, result.wire(out).get());
+        assert_eq!(1 - va, result.wire(nots).get());
+    }
+}
+
+#[test]
+fn test_optimize_faults() {
+    use crate::*;
+    clear_all();
+
+    let a = input();
+    let out = !!a;
+    a.set(0);
+    freeze().unwrap();
+    inject_stuck_at(a, 1);
+    assert!(std::panic::catch_unwind(|| optimize(&[out])).is_err());
+
+    clear_faults();
+    let result = optimize(&[out]);
+    result.wire(a).set(0); // clearing keeps the forced value
+    simulate();
+    assert_eq!(0, result.wire(out).get());
+}
+
+#[test]
+fn test_optimize_toggles() {
+    use crate::*;
+    clear_all();
+
+    let count = reg_w::<2>().named("count");
+    count.set_in(add_naive(count.out, Wires::parse_u8(1)).sum);
+    let _unused = !!count.out.wires[1];
+    set_toggle_counting(true);
+    simulate();
+    freeze().unwrap();
+    let result = optimize(&[]);
+    assert!(result.wires_after < result.wires_before);
+
+    // counted from the optimize() on, on the new wires
+    for _ in 0..4 {
+        simulate();
+    }
+    let report = toggle_report(1);
+    assert_eq!((4, result.wires_after), (report.cycles, report.wire_count));
+    assert_eq!(4, result.wires(count.out).wires[0].toggle_count());
+}
@@ -82,3 +82,53 @@ fn test_flipflop_w() {
         }
     }
 }
+
+#[test]
+fn test_reg_init_reset_enable() {
+    use crate::*;
+
+    // counter starting at 5 that only counts while enabled, reset loads 5 again
+    let build = || {
+        let reset = reset_signal();
+        let enable = input();
+        let count = reg_w_with_init::<4>(5);
+        count.set_in(add_naive(count.out, Wires::parse_u8(1)).sum);
+        count.set_enable(enable);
+        (reset, enable, count.out)
+    };
+    let expected = [5, 6, 7, 7, 8, 5, 5, 5, 6, 7];
+    let stimulus = |i: usize| ((i == 4 || i == 5) as u8, (i % 4 != 2) as u8);
+
+    for backend in [
+        SimulationBackend::Interpreted,
+        SimulationBackend::Compiled,
+        SimulationBackend::EventDriven,
+    ] {
+        let mut circuit = Circuit::new();
+        circuit.set_backend(backend);
+        circuit.enter(|| {
+            let (reset, enable, count) = build();
+            for (i, expected) in expected.iter().enumerate() {
+                assert_eq!(*expected, count.get_u8(), "{backend:?} cycle {i}");
+                let (r, e) = stimulus(i);
+                reset.set(r);
+                enable.set(e);
+                simulate();
+            }
+        });
+    }
+
+    // lane 1 never resets
+    let mut circuit = Circuit::new();
+    circuit.enter(|| {
+        let (reset, enable, count) = build();
+        for (i, expected) in expected.iter().enumerate() {
+            assert_eq!(*expected, count.get_u8_lane(0), "lanes cycle {i}");
+            let (r, e) = stimulus(i);
+            reset.set_lanes(r as LaneValue);
+            enable.set_lanes(if e == 1 { LaneValue::MAX } else { 0 });
+            simulate_lanes();
+        }
+        assert_eq!(13, count.get_u8_lane(1));
+    });
+}
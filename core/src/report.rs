@@ -0,0 +1,292 @@
+use crate::{current, Circuit, LatencyValue, Wire};
+use std::fmt::{Display, Formatter, Write};
+
+/// Area of one scope (not including its children), or of the whole design.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentReport {
+    pub scope: String,
+    pub wire_count: usize,
+    pub gate_count: usize,
+    pub reg_count: usize,
+    pub external_count: usize,
+    /// Readers (gates, reg inputs/enables, reset) of the wires created here, wherever they are.
+    pub max_fanout: usize,
+    pub avg_fanout: f64,
+    pub max_depth: LatencyValue,
+    /// Gates by the latency of their output, index is the depth.
+    pub depth_histogram: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesignReport {
+    pub total: ComponentReport,
+    pub components: Vec<ComponentReport>, // in scope creation order, empty scopes left out
+}
+
+impl ComponentReport {
+    fn new(scope: String) -> Self {
+        Self {
+            scope,
+            wire_count: 0,
+            gate_count: 0,
+            reg_count: 0,
+            external_count: 0,
+            max_fanout: 0,
+            avg_fanout: 0.0,
+            max_depth: 0,
+            depth_histogram: vec![],
+        }
+    }
+
+    fn add_wire(&mut self, fanout: usize) {
+        self.wire_count += 1;
+        self.max_fanout = self.max_fanout.max(fanout);
+        self.avg_fanout += fanout as f64; // summed until `finish()`
+    }
+
+    fn add_gate(&mut self, depth: LatencyValue) {
+        self.gate_count += 1;
+        self.max_depth = self.max_depth.max(depth);
+        let depth = depth as usize;
+        if self.depth_histogram.len() <= depth {
+            self.depth_histogram.resize(depth + 1, 0);
+        }
+        self.depth_histogram[depth] += 1;
+    }
+
+    fn finish(&mut self) {
+        if self.wire_count > 0 {
+            self.avg_fanout /= self.wire_count as f64;
+        }
+    }
+
+    fn write_json(&self, out: &mut String) {
+        let histogram = self
+            .depth_histogram
+            .iter()
+            .map(|count| count.to_string())
+            .collect::<Vec<_>>();
+        write!(
+            out,
+            "{{\"scope\":{},\"wire_count\":{},\"gate_count\":{},\"reg_count\":{},\
+             \"external_count\":{},\"max_fanout\":{},\"avg_fanout\":{:.3},\"max_depth\":{},\
+             \"depth_histogram\":[{}]}}",
+            json_string(&self.scope),
+            self.wire_count,
+            self.gate_count,
+            self.reg_count,
+            self.external_count,
+            self.max_fanout,
+            self.avg_fanout,
+            self.max_depth,
+            histogram.join(",")
+        )
+        .unwrap();
+    }
+}
+
+pub(crate) fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl DesignReport {
+    /// One line, components in the same order as the text table.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"total\":");
+        self.total.write_json(&mut out);
+        out.push_str(",\"components\":[");
+        for (index, component) in self.components.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            component.write_json(&mut out);
+        }
+        out.push_str("]}");
+        out
+    }
+
+    pub fn component(&self, scope: &str) -> Option<&ComponentReport> {
+        self.components.iter().find(|c| c.scope == scope)
+    }
+}
+
+impl Circuit {
+    pub fn design_report(&self) -> DesignReport {
+        let mut fanout = vec![0; self.wires.len()];
+        for gate in &self.gates {
+            fanout[gate.wire_a.0] += 1;
+            if gate.wire_b.0 != gate.wire_a.0 {
+                fanout[gate.wire_b.0] += 1;
+            }
+        }
+        for reg in &self.regs {
+            for wire in reg.wire_in.into_iter().chain(reg.enable) {
+                fanout[wire.0] += 1;
+            }
+        }
+        if let Some(reset) = self.reset {
+            fanout[reset.0] += 1;
+        }
+
+        let mut total = ComponentReport::new("(total)".to_string());
+        let mut components = (0..self.scope_count())
+            .map(|scope| ComponentReport::new(self.scope_path(scope)))
+            .collect::<Vec<_>>();
+        let scope_of = |wire: Wire| self.wire_scope(wire);
+        // constants belong to nobody
+        for index in 2..self.wires.len() {
+            components[scope_of(Wire(index))].add_wire(fanout[index]);
+            total.add_wire(fanout[index]);
+        }
+        for gate in &self.gates {
+            let depth = self.latencies[gate.wire_out.0];
+            components[scope_of(gate.wire_out)].add_gate(depth);
+            total.add_gate(depth);
+        }
+        for reg in &self.regs {
+            components[scope_of(reg.wire_out)].reg_count += 1;
+        }
+        for index in 0..self.externals.len() {
+            components[self.external_scope(index)].external_count += 1;
+        }
+        total.reg_count = self.regs.len();
+        total.external_count = self.externals.len();
+
+        total.finish();
+        components.iter_mut().for_each(ComponentReport::finish);
+        components.retain(|c| c.wire_count + c.external_count > 0);
+        DesignReport { total, components }
+    }
+}
+
+pub fn design_report() -> DesignReport {
+    current().design_report()
+}
+
+impl Display for DesignReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = |c: &ComponentReport| match c.scope.as_str() {
+            "" => "(root)".to_string(),
+            scope => scope.to_string(),
+        };
+        let rows = self.components.iter().chain([&self.total]);
+        let width = rows
+            .clone()
+            .map(|c| name(c).len())
+            .max()
+            .unwrap_or(0)
+            .max(9);
+        writeln!(
+            f,
+            "{:width$} {:>7} {:>6} {:>5} {:>7} {:>7} {:>7} {:>5}",
+            "component", "gates", "regs", "ext", "wires", "fo max", "fo avg", "depth"
+        )?;
+        for c in rows {
+            writeln!(
+                f,
+                "{:width$} {:>7} {:>6} {:>5} {:>7} {:>7} {:>7.2} {:>5}",
+                name(c),
+                c.gate_count,
+                c.reg_count,
+                c.external_count,
+                c.wire_count,
+                c.max_fanout,
+                c.avg_fanout,
+                c.max_depth
+            )?;
+        }
+        writeln!(f, "depth histogram (gates):")?;
+        for (depth, count) in self.total.depth_histogram.iter().enumerate() {
+            if *count > 0 {
+                writeln!(f, "  {depth:>4}: {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_design_report() {
+    use crate::*;
+    clear_all();
+
+    let a = input();
+    let b = input();
+    let sum = scope("adder", || add_naive(a.expand::<2>(), b.expand()).sum);
+    let count = scope("counter", || {
+        let count = reg_w::<2>();
+        count.set_in(sum);
+        count
+    });
+    scope("counter", || {
+        external(Logger::from_wire(count.out.wires[0]))
+    });
+
+    let report = design_report();
+    let text = report.to_string();
+    assert!(text.contains(
+        "(root)          0      0     0       2       2    2.00     0\n\
+         adder          20      0     0      20       4    1.50    11\n\
+         counter         0      2     1       2       0    0.00     0\n\
+         (total)        20      2     1      24       4    1.42    11\n"
+    ));
+    assert!(text.contains("depth histogram (gates):\n     1: 1\n     2: 3\n"));
+    assert!(text.ends_with("    11: 1\n"));
+    let histogram = "[0,1,3,2,1,3,2,1,1,3,2,1]";
+    assert_eq!(
+        format!(
+            "{{\"total\":{{\"scope\":\"(total)\",\"wire_count\":24,\"gate_count\":20,\
+             \"reg_count\":2,\"external_count\":1,\"max_fanout\":4,\"avg_fanout\":1.417,\
+             \"max_depth\":11,\"depth_histogram\":{histogram}}},\"components\":[\
+             {{\"scope\":\"\",\"wire_count\":2,\"gate_count\":0,\"reg_count\":0,\
+             \"external_count\":0,\"max_fanout\":2,\"avg_fanout\":2.000,\"max_depth\":0,\
+             \"depth_histogram\":[]}},\
+             {{\"scope\":\"adder\",\"wire_count\":20,\"gate_count\":20,\"reg_count\":0,\
+             \"external_count\":0,\"max_fanout\":4,\"avg_fanout\":1.500,\"max_depth\":11,\
+             \"depth_histogram\":{histogram}}},\
+             {{\"scope\":\"counter\",\"wire_count\":2,\"gate_count\":0,\"reg_count\":2,\
+             \"external_count\":1,\"max_fanout\":0,\"avg_fanout\":0.000,\"max_depth\":0,\
+             \"depth_histogram\":[]}}]}}"
+        ),
+        report.to_json()
+    );
+    let stats = get_statistics();
+    assert_eq!(stats.gate_count, report.total.gate_count);
+    assert_eq!(stats.wire_count - 2, report.total.wire_count);
+    assert_eq!(stats.max_latency, report.total.max_depth);
+    let histogram_gates: usize = report.total.depth_histogram.iter().sum();
+    assert_eq!(stats.gate_count, histogram_gates);
+
+    let adder = report.component("adder").unwrap();
+    let counter = report.component("counter").unwrap();
+    assert_eq!(stats.gate_count, adder.gate_count);
+    assert_eq!(
+        (0, 2, 1),
+        (
+            counter.gate_count,
+            counter.reg_count,
+            counter.external_count
+        )
+    );
+    // a and b feed the adder
+    let root = report.component("").unwrap();
+    assert_eq!(2, root.wire_count);
+    assert!(root.max_fanout >= 2);
+
+    let json = report.to_json();
+    assert!(json.starts_with("{\"total\":{\"scope\":\"(total)\","));
+    assert!(
+        json.contains("{\"scope\":\"counter\",\"wire_count\":2,\"gate_count\":0,\"reg_count\":2,")
+    );
+    assert_eq!("\"a\\\"b\\\\\"", json_string("a\"b\\"));
+}
@@ -0,0 +1,427 @@
+use std::collections::BinaryHeap;
+use std::ops::Not;
+
+/// A variable or its negation, `2 * var + negated`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Lit(u32);
+
+impl Lit {
+    fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+    fn is_negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+fn lit_value(assigns: &[Option<bool>], lit: Lit) -> Option<bool> {
+    assigns[lit.var()].map(|value| value != lit.is_negated())
+}
+
+/// Luby sequence 1 1 2 1 1 2 4 1 1 2 ..., `i` from 0.
+fn luby(mut i: u64) -> u64 {
+    let (mut size, mut power) = (1, 1);
+    while size < i + 1 {
+        size = 2 * size + 1;
+        power *= 2;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        power /= 2;
+        i %= size;
+    }
+    power
+}
+
+/// Conflict driven clause learning SAT solver.
+///
+/// Two watched literals, first UIP learning, activity based decisions with saved phases and
+/// Luby restarts. Clauses can be added between `solve()` calls, learned clauses are kept, and
+/// `solve()` takes assumptions that only hold for that call.
+#[derive(Default)]
+pub(crate) struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    watches: Vec<Vec<usize>>, // per literal, clauses with it in position 0 or 1
+    assigns: Vec<Option<bool>>,
+    level: Vec<usize>,
+    reason: Vec<Option<usize>>, // clause that implied the var, its literal is in position 0
+    trail: Vec<Lit>,
+    trail_lim: Vec<usize>, // trail length at each decision
+    queue_head: usize,
+    activity: Vec<f64>,
+    activity_inc: f64,
+    order: BinaryHeap<(u64, usize)>, // (activity bits, var), stale entries are skipped
+    phase: Vec<bool>,
+    model: Vec<bool>,
+    true_lit: Option<Lit>,
+    unsat: bool, // a conflict without decisions
+}
+
+impl Solver {
+    pub(crate) fn new() -> Self {
+        Self {
+            activity_inc: 1.0,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn new_var(&mut self) -> Lit {
+        let var = self.assigns.len();
+        self.assigns.push(None);
+        self.level.push(0);
+        self.reason.push(None);
+        self.activity.push(0.0);
+        self.phase.push(false);
+        self.watches.extend([vec![], vec![]]);
+        self.order.push((0, var));
+        Lit(2 * var as u32)
+    }
+
+    /// A literal that is always true.
+    pub(crate) fn true_lit(&mut self) -> Lit {
+        match self.true_lit {
+            Some(lit) => lit,
+            None => {
+                let lit = self.new_var();
+                self.add_clause(&[lit]);
+                self.true_lit = Some(lit);
+                lit
+            }
+        }
+    }
+
+    pub(crate) fn add_clause(&mut self, lits: &[Lit]) {
+        if self.unsat {
+            return;
+        }
+        debug_assert!(self.trail_lim.is_empty());
+        let mut clause = vec![];
+        for &lit in lits {
+            match lit_value(&self.assigns, lit) {
+                Some(true) => return,
+                Some(false) => {}
+                None if clause.contains(&!lit) => return,
+                None if !clause.contains(&lit) => clause.push(lit),
+                None => {}
+            }
+        }
+        match clause.len() {
+            0 => self.unsat = true,
+            1 => {
+                self.assign(clause[0], None);
+                self.unsat = self.propagate().is_some();
+            }
+            _ => {
+                self.watch(&clause, self.clauses.len());
+                self.clauses.push(clause);
+            }
+        }
+    }
+
+    /// `out = !(a & b)`
+    pub(crate) fn nand(&mut self, a: Lit, b: Lit) -> Lit {
+        let out = self.new_var();
+        self.add_clause(&[out, a]);
+        self.add_clause(&[out, b]);
+        self.add_clause(&[!out, !a, !b]);
+        out
+    }
+
+    /// `out = a ^ b`
+    pub(crate) fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        let out = self.new_var();
+        self.add_clause(&[!out, a, b]);
+        self.add_clause(&[!out, !a, !b]);
+        self.add_clause(&[out, !a, b]);
+        self.add_clause(&[out, a, !b]);
+        out
+    }
+
+    /// `out = select ? b : a`
+    pub(crate) fn mux(&mut self, a: Lit, b: Lit, select: Lit) -> Lit {
+        let out = self.new_var();
+        self.add_clause(&[select, !a, out]);
+        self.add_clause(&[select, a, !out]);
+        self.add_clause(&[!select, !b, out]);
+        self.add_clause(&[!select, b, !out]);
+        out
+    }
+
+    /// `out = lits[0] | lits[1] | ...`, false without literals.
+    pub(crate) fn or(&mut self, lits: &[Lit]) -> Lit {
+        let out = self.new_var();
+        let mut clause = vec![!out];
+        clause.extend(lits);
+        self.add_clause(&clause);
+        for &lit in lits {
+            self.add_clause(&[out, !lit]);
+        }
+        out
+    }
+
+    /// Value of a literal in the model of the last satisfiable `solve()`.
+    pub(crate) fn model_value(&self, lit: Lit) -> bool {
+        self.model[lit.var()] != lit.is_negated()
+    }
+
+    /// Whether the clauses can be satisfied with all `assumptions` true.
+    pub(crate) fn solve(&mut self, assumptions: &[Lit]) -> bool {
+        if self.unsat {
+            return false;
+        }
+        let mut restart = 0;
+        let mut restart_conflicts = 100 * luby(restart);
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.trail_lim.is_empty() {
+                    self.unsat = true;
+                    return false;
+                }
+                let (learnt, back_level) = self.analyze(conflict);
+                self.backtrack(back_level);
+                match learnt.len() {
+                    1 => self.assign(learnt[0], None),
+                    _ => {
+                        let index = self.clauses.len();
+                        self.watch(&learnt, index);
+                        self.assign(learnt[0], Some(index));
+                        self.clauses.push(learnt);
+                    }
+                }
+                self.decay_activity();
+                restart_conflicts = restart_conflicts.saturating_sub(1);
+                continue;
+            }
+            if restart_conflicts == 0 {
+                restart += 1;
+                restart_conflicts = 100 * luby(restart);
+                self.backtrack(0);
+                continue;
+            }
+
+            // assumptions first, one decision level each
+            let level = self.trail_lim.len();
+            let decision = match assumptions.get(level) {
+                Some(&lit) => match lit_value(&self.assigns, lit) {
+                    Some(false) => {
+                        self.backtrack(0);
+                        return false;
+                    }
+                    Some(true) => None,
+                    None => Some(lit),
+                },
+                None => match self.pick_var() {
+                    Some(var) => Some(Lit(2 * var as u32 + !self.phase[var] as u32)),
+                    None => {
+                        self.model = self.assigns.iter().map(|v| v.unwrap()).collect();
+                        self.backtrack(0);
+                        return true;
+                    }
+                },
+            };
+            self.trail_lim.push(self.trail.len());
+            if let Some(lit) = decision {
+                self.assign(lit, None);
+            }
+        }
+    }
+
+    fn watch(&mut self, clause: &[Lit], index: usize) {
+        self.watches[clause[0].index()].push(index);
+        self.watches[clause[1].index()].push(index);
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.assigns[var] = Some(!lit.is_negated());
+        self.level[var] = self.trail_lim.len();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Unit propagation of the trail, the conflicting clause if any.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.queue_head < self.trail.len() {
+            let false_lit = !self.trail[self.queue_head];
+            self.queue_head += 1;
+            let mut watching = std::mem::take(&mut self.watches[false_lit.index()]);
+            let mut i = 0;
+            while i < watching.len() {
+                let index = watching[i];
+                let clause = &mut self.clauses[index];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                if lit_value(&self.assigns, clause[0]) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+                let other =
+                    (2..clause.len()).find(|&k| lit_value(&self.assigns, clause[k]) != Some(false));
+                if let Some(k) = other {
+                    clause.swap(1, k);
+                    self.watches[clause[1].index()].push(index);
+                    watching.swap_remove(i);
+                    continue;
+                }
+                let unit = clause[0];
+                if lit_value(&self.assigns, unit) == Some(false) {
+                    self.watches[false_lit.index()] = watching;
+                    self.queue_head = self.trail.len();
+                    return Some(index);
+                }
+                self.assign(unit, Some(index));
+                i += 1;
+            }
+            self.watches[false_lit.index()] = watching;
+        }
+        None
+    }
+
+    /// First UIP clause of a conflict, asserting literal first and one of the highest level
+    /// second, with the level to jump back to.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let level = self.trail_lim.len();
+        let mut seen = vec![false; self.assigns.len()];
+        let mut learnt = vec![Lit(0)];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut clause = conflict;
+        let mut implied: Option<Lit> = None;
+        loop {
+            let skip = implied.is_some() as usize; // the implied literal of a reason
+            for k in skip..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                let var = lit.var();
+                if seen[var] || self.level[var] == 0 {
+                    continue;
+                }
+                seen[var] = true;
+                self.bump(var);
+                match self.level[var] == level {
+                    true => pending += 1,
+                    false => learnt.push(lit),
+                }
+            }
+            loop {
+                index -= 1;
+                if seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            pending -= 1;
+            if pending == 0 {
+                learnt[0] = !lit;
+                break;
+            }
+            implied = Some(lit);
+            clause = self.reason[lit.var()].unwrap();
+        }
+        let mut back_level = 0;
+        for k in 1..learnt.len() {
+            let lit_level = self.level[learnt[k].var()];
+            if lit_level > back_level {
+                back_level = lit_level;
+                learnt.swap(1, k);
+            }
+        }
+        (learnt, back_level)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.trail_lim.len() <= level {
+            return;
+        }
+        let start = self.trail_lim[level];
+        for lit in self.trail.drain(start..) {
+            let var = lit.var();
+            self.phase[var] = !lit.is_negated();
+            self.assigns[var] = None;
+            self.reason[var] = None;
+            self.order.push((self.activity[var].to_bits(), var));
+        }
+        self.trail_lim.truncate(level);
+        self.queue_head = self.trail.len();
+    }
+
+    fn pick_var(&mut self) -> Option<usize> {
+        while let Some((activity, var)) = self.order.pop() {
+            if self.assigns[var].is_none() && activity == self.activity[var].to_bits() {
+                return Some(var);
+            }
+        }
+        // stale entries may have hidden a var
+        self.assigns.iter().position(|value| value.is_none())
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.activity_inc;
+        if self.activity[var] > 1e100 {
+            self.activity.iter_mut().for_each(|a| *a *= 1e-100);
+            self.activity_inc *= 1e-100;
+            let unassigned = (0..self.assigns.len()).filter(|&v| self.assigns[v].is_none());
+            self.order = unassigned
+                .map(|v| (self.activity[v].to_bits(), v))
+                .collect();
+        }
+        if self.assigns[var].is_none() {
+            self.order.push((self.activity[var].to_bits(), var));
+        }
+    }
+
+    fn decay_activity(&mut self) {
+        self.activity_inc /= 0.95;
+    }
+}
+
+#[test]
+fn test_solver() {
+    assert_eq!(
+        vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8],
+        (0..15).map(luby).collect::<Vec<_>>()
+    );
+
+    // 4 pigeons do not fit in 3 holes
+    let mut solver = Solver::new();
+    let holes = 3;
+    let p = (0..holes + 1)
+        .map(|_| (0..holes).map(|_| solver.new_var()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    for pigeon in &p {
+        solver.add_clause(pigeon);
+    }
+    for hole in 0..holes {
+        for a in 0..p.len() {
+            for b in a + 1..p.len() {
+                solver.add_clause(&[!p[a][hole], !p[b][hole]]);
+            }
+        }
+    }
+    assert!(!solver.solve(&[]));
+
+    // x = a ^ b ^ c, solved forwards and backwards under assumptions
+    let mut solver = Solver::new();
+    let [a, b, c] = [(); 3].map(|_| solver.new_var());
+    let ab = solver.xor(a, b);
+    let x = solver.xor(ab, c);
+    assert!(solver.solve(&[a, !b, c]));
+    assert!(!solver.model_value(x));
+    assert!(solver.solve(&[x, !a, !b]));
+    assert!(solver.model_value(c));
+    assert!(!solver.solve(&[x, a, b, !c]));
+    let one = solver.true_lit();
+    let nand = solver.nand(one, a);
+    assert!(solver.solve(&[nand]));
+    assert!(!solver.model_value(a));
+}
@@ -0,0 +1,246 @@
+use crate::{current, Circuit, Reg, Regs, Wire, Wires, WIRE_0, WIRE_1};
+use std::collections::HashMap;
+
+pub type ScopeId = usize;
+pub const ROOT_SCOPE: ScopeId = 0;
+
+/// Module hierarchy of a circuit and the names given to its wires.
+///
+/// Every wire (and so every gate and reg) and every external remembers the scope that was open
+/// when it was created.
+pub(crate) struct Scopes {
+    scopes: Vec<(String, ScopeId)>, // (name, parent), root is ("", 0)
+    children: HashMap<(ScopeId, String), ScopeId>,
+    pub(crate) current: ScopeId,
+    pub(crate) wire_scopes: Vec<ScopeId>,
+    pub(crate) external_scopes: Vec<ScopeId>,
+    wire_names: HashMap<usize, String>,
+}
+
+impl Scopes {
+    pub(crate) fn new() -> Self {
+        Self {
+            scopes: vec![(String::new(), ROOT_SCOPE)],
+            children: HashMap::new(),
+            current: ROOT_SCOPE,
+            wire_scopes: vec![ROOT_SCOPE, ROOT_SCOPE], // => WIRE_0, WIRE_1
+            external_scopes: Vec::new(),
+            wire_names: HashMap::new(),
+        }
+    }
+
+    /// Follow `Circuit::optimize()`, `origin` is the old wire of each new wire.
+    pub(crate) fn remap(&mut self, origin: &[usize], wire_map: &[Option<usize>]) {
+        self.wire_scopes = origin.iter().map(|old| self.wire_scopes[*old]).collect();
+        let mut names = std::mem::take(&mut self.wire_names)
+            .into_iter()
+            .collect::<Vec<_>>();
+        // the first named old wire wins, like `set_wire_name()`
+        names.sort();
+        for (old, name) in names {
+            match wire_map[old] {
+                Some(WIRE_0 | WIRE_1) | None => {}
+                Some(new) => {
+                    self.wire_names.entry(new).or_insert(name);
+                }
+            }
+        }
+    }
+
+    fn child(&mut self, name: &str) -> ScopeId {
+        let key = (self.current, name.to_string());
+        if let Some(id) = self.children.get(&key) {
+            return *id;
+        }
+        let id = self.scopes.len();
+        self.scopes.push((name.to_string(), self.current));
+        self.children.insert(key, id);
+        id
+    }
+
+    fn path(&self, mut id: ScopeId) -> String {
+        let mut names = vec![];
+        while id != ROOT_SCOPE {
+            let (name, parent) = &self.scopes[id];
+            names.push(name.as_str());
+            id = *parent;
+        }
+        names.reverse();
+        names.join(".")
+    }
+
+    fn full_name(&self, name: &str) -> String {
+        match self.current {
+            ROOT_SCOPE => name.to_string(),
+            scope => format!("{}.{name}", self.path(scope)),
+        }
+    }
+}
+
+impl Circuit {
+    /// Hierarchical name of a wire, like `cpu.alu.sum[3]`, or `cpu.alu.w1234` if unnamed.
+    pub fn wire_name(&self, wire: Wire) -> String {
+        if let Some(name) = self.scopes.wire_names.get(&wire.0) {
+            return name.clone();
+        }
+        match self.wire_scope(wire) {
+            ROOT_SCOPE => format!("w{}", wire.0),
+            scope => format!("{}.w{}", self.scope_path(scope), wire.0),
+        }
+    }
+    /// Name given with `named()`, without falling back to the index.
+    pub fn given_wire_name(&self, wire: Wire) -> Option<&str> {
+        self.scopes.wire_names.get(&wire.0).map(|s| s.as_str())
+    }
+    /// A wire keeps the first name it is given, constants are never named.
+    pub fn set_wire_name(&mut self, wire: Wire, name: &str) {
+        if wire.0 == WIRE_0 || wire.0 == WIRE_1 {
+            return;
+        }
+        let name = self.scopes.full_name(name);
+        self.scopes.wire_names.entry(wire.0).or_insert(name);
+    }
+
+    pub fn wire_scope(&self, wire: Wire) -> ScopeId {
+        self.scopes.wire_scopes[wire.0]
+    }
+    pub fn external_scope(&self, index: usize) -> ScopeId {
+        self.scopes.external_scopes[index]
+    }
+    pub fn scope_path(&self, scope: ScopeId) -> String {
+        self.scopes.path(scope)
+    }
+    pub fn scope_parent(&self, scope: ScopeId) -> Option<ScopeId> {
+        match scope {
+            ROOT_SCOPE => None,
+            scope => Some(self.scopes.scopes[scope].1),
+        }
+    }
+    pub fn scope_count(&self) -> usize {
+        self.scopes.scopes.len()
+    }
+
+    pub fn enter_scope(&mut self, name: &str) -> ScopeId {
+        let previous = self.scopes.current;
+        self.scopes.current = self.scopes.child(name);
+        previous
+    }
+    pub fn exit_scope(&mut self, previous: ScopeId) {
+        self.scopes.current = previous;
+    }
+}
+
+/// Leaves the scope `scope()` entered, also when `f` panics.
+struct ScopeGuard {
+    previous: ScopeId,
+}
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        current().exit_scope(self.previous);
+    }
+}
+
+/// Build everything inside `f` in a nested module scope named `name`.
+pub fn scope<R>(name: &str, f: impl FnOnce() -> R) -> R {
+    let _guard = ScopeGuard {
+        previous: current().enter_scope(name),
+    };
+    f()
+}
+
+pub fn wire_name(wire: Wire) -> String {
+    current().wire_name(wire)
+}
+
+impl Wire {
+    pub fn named(self, name: &str) -> Wire {
+        current().set_wire_name(self, name);
+        self
+    }
+    pub fn name(self) -> String {
+        current().wire_name(self)
+    }
+}
+
+impl<const W: usize> Wires<W> {
+    /// Name each bit as `name[i]`.
+    pub fn named(self, name: &str) -> Wires<W> {
+        for i in 0..W {
+            self.wires[i].named(&format!("{name}[{i}]"));
+        }
+        self
+    }
+    /// Name of the bus, the name of bit 0 without its `[0]`.
+    pub fn name(&self) -> String {
+        let name = self.wires[0].name();
+        match name.strip_suffix("[0]") {
+            Some(bus) => bus.to_string(),
+            None => name,
+        }
+    }
+}
+
+impl Reg {
+    pub fn named(self, name: &str) -> Reg {
+        self.out().named(name);
+        self
+    }
+}
+
+impl<const W: usize> Regs<W> {
+    pub fn named(self, name: &str) -> Regs<W> {
+        self.out.named(name);
+        self
+    }
+}
+
+#[test]
+fn test_scope() {
+    use crate::*;
+    clear_all();
+
+    let a = input_w::<4>().named("a");
+    let (b, sum, carry) = scope("cpu", || {
+        let b = input_w::<4>().named("b");
+        scope("alu", || {
+            let r = add_naive(a, b);
+            (b, r.sum.named("sum"), r.carry)
+        })
+    });
+    let pc = scope("cpu", || reg_w::<4>().named("pc"));
+    pc.set_in(sum);
+
+    assert_eq!("a[2]", a.wires[2].name());
+    assert_eq!("a", a.name());
+    assert_eq!("cpu.b", b.name());
+    assert_eq!("cpu.alu.sum[3]", sum.wires[3].name());
+    assert_eq!("cpu.alu.sum", sum.name());
+    assert_eq!(format!("cpu.alu.w{}", carry.0), carry.name());
+    assert_eq!("cpu.pc", pc.out.name());
+    assert_eq!(
+        format!("w{}", input_const(1).0),
+        input_const(1).named("one").name()
+    );
+
+    // gates know the module that built them, re-entering a scope reuses it
+    let circuit = current();
+    let alu = circuit.wire_scope(carry);
+    assert_eq!("cpu.alu", circuit.scope_path(alu));
+    assert_eq!(
+        circuit.wire_scope(b.wires[0]),
+        circuit.scope_parent(alu).unwrap()
+    );
+    assert_eq!(
+        circuit.wire_scope(b.wires[0]),
+        circuit.wire_scope(pc.out.wires[0])
+    );
+    assert_eq!(3, circuit.scope_count());
+
+    // reports use the names too
+    let error = validate().unwrap_err().to_string();
+    assert!(error.contains("input cpu.b[0] is never set"), "{error}");
+
+    // a panic inside leaves the scope too
+    assert!(std::panic::catch_unwind(|| scope("broken", || panic!("build failed"))).is_err());
+    assert_eq!(ROOT_SCOPE, current().wire_scope(input()));
+}
@@ -0,0 +1,211 @@
+use crate::{
+    current, execute_externals, Circuit, ExecuteSegment, ExternalContext, WireValue, WIRE_0, WIRE_1,
+};
+use std::ops::Range;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum SimulationBackend {
+    /// Walk the gates and externals of each execute segment.
+    #[default]
+    Interpreted,
+    /// Run a flat program compiled from the frozen netlist, see `CompiledProgram`.
+    Compiled,
+    /// Only evaluate gates whose inputs changed, see `EventDrivenState`.
+    EventDriven,
+}
+
+enum CompiledStep {
+    Gates(Range<usize>),
+    Externals(Range<usize>),
+}
+
+/// Straight-line evaluation program of a netlist.
+///
+/// Gates and regs are packed as `u32` wire indices and evaluated without bounds checks, the
+/// execute segments are flattened into steps. It is rebuilt when the netlist has changed.
+pub struct CompiledProgram {
+    wire_count: usize,
+    external_count: usize,
+    steps: Vec<CompiledStep>,
+    gates: Vec<[u32; 3]>, // [a, b, out]
+    regs: Vec<[u32; 5]>,  // [in, out, enable, init, domain], no in reads WIRE_0, no enable WIRE_1
+    reset: u32,           // WIRE_0 without reset
+    reg_temp: Vec<WireValue>,
+}
+
+impl CompiledProgram {
+    fn compile(circuit: &Circuit) -> Self {
+        let gates = circuit
+            .gates
+            .iter()
+            .map(|gate| {
+                [
+                    gate.wire_a.0 as u32,
+                    gate.wire_b.0 as u32,
+                    gate.wire_out.0 as u32,
+                ]
+            })
+            .collect::<Vec<_>>();
+        let regs = circuit
+            .regs
+            .iter()
+            .map(|reg| {
+                let wire_in = reg.wire_in.map_or(WIRE_0, |w| w.0);
+                let enable = reg.enable.map_or(WIRE_1, |w| w.0);
+                let init = reg.init.unwrap_or(0);
+                [
+                    wire_in as u32,
+                    reg.wire_out.0 as u32,
+                    enable as u32,
+                    init as u32,
+                    reg.domain.0 as u32,
+                ]
+            })
+            .collect::<Vec<_>>();
+        let steps = circuit
+            .execute_segments
+            .iter()
+            .map(|segment| match segment {
+                ExecuteSegment::Gates(range) => CompiledStep::Gates(range.clone()),
+                ExecuteSegment::Externals(range) => CompiledStep::Externals(range.clone()),
+            })
+            .collect::<Vec<_>>();
+        assert!(circuit.wires.len() <= u32::MAX as usize);
+
+        Self {
+            wire_count: circuit.wires.len(),
+            external_count: circuit.externals.len(),
+            steps,
+            gates,
+            reg_temp: vec![0; regs.len()],
+            regs,
+            reset: circuit.reset.map_or(WIRE_0, |w| w.0) as u32,
+        }
+    }
+
+    fn is_outdated(&self, circuit: &Circuit) -> bool {
+        // wires only grow, every new gate or reg creates a new wire
+        self.wire_count != circuit.wires.len() || self.external_count != circuit.externals.len()
+    }
+}
+
+impl Circuit {
+    pub fn set_backend(&mut self, backend: SimulationBackend) {
+        self.backend = backend;
+    }
+    pub fn backend(&self) -> SimulationBackend {
+        self.backend
+    }
+
+    /// Compile the netlist now instead of on the first compiled `execute_gates()`.
+    pub fn compile(&mut self) -> &CompiledProgram {
+        let outdated = self
+            .compiled
+            .as_ref()
+            .map_or(true, |program| program.is_outdated(self));
+        if outdated {
+            self.compiled = Some(CompiledProgram::compile(self));
+        }
+        self.compiled.as_ref().unwrap()
+    }
+
+    pub(crate) fn execute_gates_compiled(&mut self) {
+        self.compile();
+        let program = self.compiled.as_ref().unwrap();
+        let mut ctx = ExternalContext::new(
+            &mut self.wires,
+            &mut self.latencies,
+            &mut self.set_by,
+            self.frozen,
+            &self.faults.stuck,
+        );
+        for step in &program.steps {
+            match step {
+                CompiledStep::Gates(range) => {
+                    let wires = &mut *ctx.wires;
+                    for [a, b, out] in &program.gates[range.start..range.end] {
+                        // SAFETY: indices come from this netlist and wires never shrink
+                        unsafe {
+                            let a = *wires.get_unchecked(*a as usize);
+                            let b = *wires.get_unchecked(*b as usize);
+                            *wires.get_unchecked_mut(*out as usize) = !(a & b) & 1;
+                        }
+                    }
+                }
+                CompiledStep::Externals(range) => {
+                    execute_externals(&self.externals, range, &mut ctx);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn clock_tick_compiled(&mut self, due: u64) {
+        self.compile();
+        let program = self.compiled.as_mut().unwrap();
+        let wires = self.wires.as_mut_slice();
+        // SAFETY: indices come from this netlist and wires never shrink
+        unsafe {
+            let reset = *wires.get_unchecked(program.reset as usize) == 1;
+            for (temp, [wire_in, wire_out, enable, init, domain]) in
+                program.reg_temp.iter_mut().zip(&program.regs)
+            {
+                *temp = if (due >> domain) & 1 == 0 {
+                    *wires.get_unchecked(*wire_out as usize)
+                } else if reset {
+                    *init as WireValue
+                } else if *wires.get_unchecked(*enable as usize) == 1 {
+                    *wires.get_unchecked(*wire_in as usize)
+                } else {
+                    *wires.get_unchecked(*wire_out as usize)
+                };
+            }
+            for (temp, [_, wire_out, _, _, _]) in program.reg_temp.iter().zip(&program.regs) {
+                *wires.get_unchecked_mut(*wire_out as usize) = *temp;
+            }
+        }
+    }
+}
+
+pub fn set_simulation_backend(backend: SimulationBackend) {
+    current().set_backend(backend);
+}
+
+#[test]
+fn test_compiled_matches_interpreted() {
+    use crate::{add_naive, external, input_w, reg_w, simulate, ExternalHandle, LoggerU8, Wires};
+
+    // counter -> logger -> adder, so gates also run after an external segment
+    let build = || {
+        let step = input_w::<4>();
+        let curr = reg_w::<4>();
+        curr.set_in(add_naive(curr.out, step).sum);
+        let logger = external(LoggerU8::new("curr".to_string(), curr.out));
+        let doubled = add_naive(curr.out, curr.out).sum;
+        step.set_u8(3);
+        (logger, doubled)
+    };
+    let run = |logger: ExternalHandle<LoggerU8<4>>, doubled: Wires<4>| {
+        let mut values = vec![];
+        for _ in 0..20 {
+            simulate();
+            values.push(doubled.get_u8());
+        }
+        let logged = logger.borrow().get_values().clone();
+        (values, logged)
+    };
+
+    let mut interpreted = Circuit::new();
+    let expected = interpreted.enter(|| {
+        let (logger, doubled) = build();
+        run(logger, doubled)
+    });
+
+    let mut compiled = Circuit::new();
+    compiled.set_backend(SimulationBackend::Compiled);
+    let actual = compiled.enter(|| {
+        let (logger, doubled) = build();
+        run(logger, doubled)
+    });
+
+    assert_eq!(expected, actual);
+}
@@ -0,0 +1,301 @@
+use crate::{current, execute_externals, Circuit, ExecuteSegment, ExternalContext, WireValue};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct EventDrivenStatistics {
+    pub passes: u64,
+    pub evaluated_gates: u64,
+    pub skipped_gates: u64,
+}
+
+/// Fanout and dirty-gate bookkeeping of the event-driven backend.
+///
+/// Gates are evaluated in ascending index order, like the levelized `execute_gates()`. When a
+/// gate output toggles, its fanout gates are queued for this pass if they come later, or for the
+/// next pass if they have already been passed, which is exactly when the levelized order would
+/// see the new value. Wires not driven by a gate (inputs, reg outputs) are compared with their
+/// last seen value at the start of each pass and after each external segment. Gate outputs must
+/// not be written from outside.
+pub struct EventDrivenState {
+    wire_count: usize,
+    external_count: usize,
+    fanout_offsets: Vec<u32>, // fanout gates of wire i: fanout_gates[offsets[i]..offsets[i + 1]]
+    fanout_gates: Vec<u32>,
+    sources: Vec<(u32, u32)>, // (wire, last fanout gate) of wires not driven by a gate
+    source_values: Vec<WireValue>,
+    dirty: Vec<u64>, // bitset of gates queued for this pass
+    next_pass: Vec<u32>,
+    statistics: EventDrivenStatistics,
+}
+
+impl EventDrivenState {
+    fn build(circuit: &Circuit) -> Self {
+        let wire_count = circuit.wires.len();
+        let gate_count = circuit.gates.len();
+
+        let mut fanout_count = vec![0u32; wire_count + 1];
+        let mut gate_driven = vec![false; wire_count];
+        for gate in &circuit.gates {
+            fanout_count[gate.wire_a.0 + 1] += 1;
+            if gate.wire_b.0 != gate.wire_a.0 {
+                fanout_count[gate.wire_b.0 + 1] += 1;
+            }
+            gate_driven[gate.wire_out.0] = true;
+        }
+        let mut fanout_offsets = fanout_count;
+        for i in 0..wire_count {
+            fanout_offsets[i + 1] += fanout_offsets[i];
+        }
+        let mut fanout_gates = vec![0u32; fanout_offsets[wire_count] as usize];
+        let mut cursor = fanout_offsets.clone();
+        for (index, gate) in circuit.gates.iter().enumerate() {
+            fanout_gates[cursor[gate.wire_a.0] as usize] = index as u32;
+            cursor[gate.wire_a.0] += 1;
+            if gate.wire_b.0 != gate.wire_a.0 {
+                fanout_gates[cursor[gate.wire_b.0] as usize] = index as u32;
+                cursor[gate.wire_b.0] += 1;
+            }
+        }
+
+        // sources without fanout never matter, the rest sorted by their last fanout gate
+        let last_fanout = |wire: usize| {
+            let range = fanout_offsets[wire] as usize..fanout_offsets[wire + 1] as usize;
+            fanout_gates[range].iter().max().copied()
+        };
+        let mut sources = (0..wire_count)
+            .filter(|i| !gate_driven[*i])
+            .filter_map(|i| last_fanout(i).map(|last| (i as u32, last)))
+            .collect::<Vec<_>>();
+        sources.sort_by_key(|(_, last)| std::cmp::Reverse(*last));
+        let source_values = sources
+            .iter()
+            .map(|(i, _)| circuit.wires[*i as usize])
+            .collect();
+
+        // the first pass evaluates everything, like the levelized order
+        Self {
+            wire_count,
+            external_count: circuit.externals.len(),
+            fanout_offsets,
+            fanout_gates,
+            sources,
+            source_values,
+            dirty: vec![u64::MAX; (gate_count + 63) / 64],
+            next_pass: Vec::new(),
+            statistics: EventDrivenStatistics::default(),
+        }
+    }
+
+    fn is_outdated(&self, circuit: &Circuit) -> bool {
+        // wires only grow, every new gate or reg creates a new wire
+        self.wire_count != circuit.wires.len() || self.external_count != circuit.externals.len()
+    }
+
+    /// Queue the fanout of `wire`, gates before `cursor` are already passed in this pass.
+    fn wire_changed(&mut self, wire: usize, cursor: u32) {
+        let range = self.fanout_offsets[wire] as usize..self.fanout_offsets[wire + 1] as usize;
+        for &gate in &self.fanout_gates[range] {
+            if gate < cursor {
+                self.next_pass.push(gate);
+            } else {
+                self.dirty[gate as usize / 64] |= 1 << (gate % 64);
+            }
+        }
+    }
+
+    /// Take the first queued gate in `start..end`.
+    fn next_dirty(&mut self, start: usize, end: usize) -> Option<usize> {
+        let mut word = start / 64;
+        let mut bits = self.dirty.get(word)? & (u64::MAX << (start % 64));
+        while bits == 0 {
+            word += 1;
+            if word * 64 >= end {
+                return None;
+            }
+            bits = self.dirty[word];
+        }
+        let index = word * 64 + bits.trailing_zeros() as usize;
+        if index >= end {
+            return None;
+        }
+        self.dirty[word] &= !(1 << (index % 64));
+        Some(index)
+    }
+
+    /// Sources whose fanout is all before `cursor` are left to the check of the next pass.
+    fn check_sources(&mut self, wires: &[WireValue], cursor: u32) {
+        for i in 0..self.sources.len() {
+            let (wire, last_fanout) = self.sources[i];
+            if last_fanout < cursor {
+                break;
+            }
+            let wire = wire as usize;
+            if wires[wire] != self.source_values[i] {
+                self.source_values[i] = wires[wire];
+                self.wire_changed(wire, cursor);
+            }
+        }
+    }
+}
+
+impl Circuit {
+    pub(crate) fn execute_gates_event_driven(&mut self) {
+        let outdated = self
+            .event_driven
+            .as_ref()
+            .map_or(true, |state| state.is_outdated(self));
+        if outdated {
+            self.event_driven = Some(EventDrivenState::build(self));
+        }
+
+        let state = self.event_driven.as_mut().unwrap();
+        let mut ctx = ExternalContext::new(
+            &mut self.wires,
+            &mut self.latencies,
+            &mut self.set_by,
+            self.frozen,
+            &self.faults.stuck,
+        );
+
+        for gate in std::mem::take(&mut state.next_pass) {
+            state.dirty[gate as usize / 64] |= 1 << (gate % 64);
+        }
+        state.check_sources(ctx.wires, 0);
+
+        let mut evaluated = 0;
+        let mut passed = 0; // gates before this index are done for this pass
+        for segment in &self.execute_segments {
+            match segment {
+                ExecuteSegment::Gates(range) => {
+                    passed = range.end as u32;
+                    let wires = &mut *ctx.wires;
+                    let mut next = range.start;
+                    while let Some(index) = state.next_dirty(next, range.end) {
+                        next = index + 1;
+                        evaluated += 1;
+
+                        let gate = &self.gates[index];
+                        let value = !(wires[gate.wire_a.0] & wires[gate.wire_b.0]) & 1;
+                        if wires[gate.wire_out.0] != value {
+                            wires[gate.wire_out.0] = value;
+                            state.wire_changed(gate.wire_out.0, next as u32);
+                        }
+                    }
+                }
+                ExecuteSegment::Externals(range) => {
+                    execute_externals(&self.externals, range, &mut ctx);
+                    state.check_sources(ctx.wires, passed);
+                }
+            }
+        }
+
+        state.statistics.passes += 1;
+        state.statistics.evaluated_gates += evaluated;
+        state.statistics.skipped_gates += self.gates.len() as u64 - evaluated;
+    }
+
+    pub fn get_event_driven_statistics(&self) -> EventDrivenStatistics {
+        self.event_driven
+            .as_ref()
+            .map(|state| state.statistics)
+            .unwrap_or_default()
+    }
+}
+
+pub fn get_event_driven_statistics() -> EventDrivenStatistics {
+    current().get_event_driven_statistics()
+}
+
+#[test]
+fn test_event_driven_matches_interpreted() {
+    use crate::*;
+
+    // counter -> logger -> adder, plus a slow input that rarely changes
+    let build = || {
+        let step = input_w::<4>();
+        let curr = reg_w::<4>();
+        curr.set_in(add_naive(curr.out, step).sum);
+        let logger = external(LoggerU8::new("curr".to_string(), curr.out));
+        let slow = input_w::<8>();
+        let doubled = add_naive(curr.out, curr.out).sum;
+        let sum = add_naive(slow, slow).sum;
+        (step, slow, logger, doubled, sum)
+    };
+    let run = |step: Wires<4>,
+               slow: Wires<8>,
+               logger: ExternalHandle<LoggerU8<4>>,
+               a: Wires<4>,
+               b: Wires<8>| {
+        let mut values = vec![];
+        for i in 0..100 {
+            step.set_u8((i / 10) % 16);
+            slow.set_u8(i / 30);
+            simulate();
+            values.push((a.get_u8(), b.get_u8()));
+        }
+        let logged = logger.borrow().get_values().clone();
+        (values, logged)
+    };
+
+    let mut interpreted = Circuit::new();
+    let expected = interpreted.enter(|| {
+        let (step, slow, logger, doubled, sum) = build();
+        run(step, slow, logger, doubled, sum)
+    });
+
+    let mut event_driven = Circuit::new();
+    event_driven.set_backend(SimulationBackend::EventDriven);
+    let actual = event_driven.enter(|| {
+        let (step, slow, logger, doubled, sum) = build();
+        run(step, slow, logger, doubled, sum)
+    });
+
+    assert_eq!(expected, actual);
+    let statistics = event_driven.get_event_driven_statistics();
+    let gate_count = event_driven.get_statistics().gate_count as u64;
+    assert_eq!(100, statistics.passes);
+    assert_eq!(
+        100 * gate_count,
+        statistics.evaluated_gates + statistics.skipped_gates
+    );
+    assert!(statistics.skipped_gates > statistics.evaluated_gates);
+}
+
+#[test]
+fn test_event_driven_regfile() {
+    use crate::*;
+
+    let build = || {
+        let reset_all = input();
+        let addr = [input_w::<2>(), input_w::<2>()];
+        let write_data = [input_w::<4>()];
+        let write_enable = input_w::<1>();
+        let regs = Regfile4x4_2R1W::create_regs();
+        let read = Regfile4x4_2R1W::apply(regs, addr, write_enable, write_data, reset_all);
+        (addr, write_data, write_enable, read)
+    };
+    let run = |(addr, write_data, write_enable, read): (
+        [Wires<2>; 2],
+        [Wires<4>; 1],
+        Wires<1>,
+        [Wires<4>; 2],
+    )| {
+        shuffled_list(1 << 9, 4.567)
+            .into_iter()
+            .map(|i| {
+                addr[0].set_u8((i % 4) as u8);
+                addr[1].set_u8(((i >> 2) % 4) as u8);
+                write_enable.set_u8(((i >> 4) % 2) as u8);
+                write_data[0].set_u8(((i >> 5) % 16) as u8);
+                simulate();
+                (read[0].get_u8(), read[1].get_u8())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut interpreted = Circuit::new();
+    let expected = interpreted.enter(|| run(build()));
+    let mut event_driven = Circuit::new();
+    event_driven.set_backend(SimulationBackend::EventDriven);
+    let actual = event_driven.enter(|| run(build()));
+    assert_eq!(expected, actual);
+}
@@ -0,0 +1,190 @@
+use crate::{
+    current, execute_externals, Assert, Circuit, ExecuteSegment, ExternalContext, Gate, IsTrue,
+    Reg, Wire, WireValue, Wires, WIRE_0, WIRE_1,
+};
+
+/// Unknown value, every `input()` and reg starts as X in four-state simulation.
+pub const VALUE_X: WireValue = 2;
+/// Undriven value, gates read it as X.
+pub const VALUE_Z: WireValue = 3;
+
+pub fn is_known(value: WireValue) -> bool {
+    value <= 1
+}
+
+/// NAND with pessimistic X: a known 0 on either input decides the output, anything else unknown
+/// gives X.
+pub fn nand_four_state(a: WireValue, b: WireValue) -> WireValue {
+    match (a, b) {
+        (0, _) | (_, 0) => 1,
+        (1, 1) => 0,
+        _ => VALUE_X,
+    }
+}
+
+impl Gate {
+    fn execute_four_state(&self, wires: &mut [WireValue]) {
+        let a = wires[self.wire_a.0];
+        let b = wires[self.wire_b.0];
+        wires[self.wire_out.0] = nand_four_state(a, b);
+    }
+}
+
+impl Circuit {
+    /// Switch between 0/1 and 0/1/X/Z simulation. Turning it on resets every wire and reg to X,
+    /// turning it off resets them to 0. Regs with an init value start there in both modes.
+    /// Four-state always runs interpreted.
+    pub fn set_four_state(&mut self, four_state: bool) {
+        self.four_state = four_state;
+        let value = if four_state { VALUE_X } else { 0 };
+        self.wires[2..].fill(value);
+        self.wires[WIRE_0] = 0;
+        self.wires[WIRE_1] = 1;
+        for reg in &mut self.regs {
+            reg.temp_value = reg.init.unwrap_or(value);
+            self.wires[reg.wire_out.0] = reg.temp_value;
+        }
+        self.event_driven = None;
+    }
+    pub fn is_four_state(&self) -> bool {
+        self.four_state
+    }
+
+    pub(crate) fn execute_gates_four_state(&mut self) {
+        let mut ctx = ExternalContext::new(
+            &mut self.wires,
+            &mut self.latencies,
+            &mut self.set_by,
+            self.frozen,
+            &self.faults.stuck,
+        );
+        for segment in &self.execute_segments {
+            match segment {
+                ExecuteSegment::Gates(range) => {
+                    for gate in &self.gates[range.clone()] {
+                        gate.execute_four_state(ctx.wires);
+                    }
+                }
+                ExecuteSegment::Externals(range) => {
+                    execute_externals(&self.externals, range, &mut ctx)
+                }
+            }
+        }
+    }
+
+    /// Regs whose output is still X or Z, empty once a reset sequence has initialized them all.
+    pub fn unknown_regs(&self) -> Vec<Reg> {
+        (0..self.regs.len())
+            .filter(|index| !is_known(self.wires[self.regs[*index].wire_out.0]))
+            .map(Reg)
+            .collect()
+    }
+}
+
+pub fn set_four_state(four_state: bool) {
+    current().set_four_state(four_state);
+}
+
+pub fn unknown_regs() -> Vec<Reg> {
+    current().unknown_regs()
+}
+
+impl Wire {
+    pub fn is_known(self) -> bool {
+        is_known(self.get())
+    }
+}
+
+impl<const W: usize> Wires<W> {
+    pub fn is_known(&self) -> bool {
+        self.wires.iter().all(|wire| wire.is_known())
+    }
+}
+
+impl<const W: usize> Wires<W>
+where
+    Assert<{ W <= 8 }>: IsTrue,
+{
+    /// `None` if any bit is X or Z.
+    pub fn try_get_u8(&self) -> Option<u8> {
+        self.is_known().then(|| self.get_u8())
+    }
+}
+
+impl<const W: usize> Wires<W>
+where
+    Assert<{ W <= 64 }>: IsTrue,
+{
+    /// `None` if any bit is X or Z.
+    pub fn try_get_u64(&self) -> Option<u64> {
+        self.is_known().then(|| self.get_u64())
+    }
+}
+
+#[test]
+fn test_four_state_nand() {
+    use crate::*;
+    clear_all();
+    set_four_state(true);
+
+    let a = input();
+    let b = input();
+    let out = nand(a, b);
+    let cases = [
+        (0, VALUE_X, 1),
+        (VALUE_X, 0, 1),
+        (1, VALUE_X, VALUE_X),
+        (VALUE_Z, 1, VALUE_X),
+        (VALUE_X, VALUE_X, VALUE_X),
+        (1, 1, 0),
+        (0, 1, 1),
+    ];
+    for (va, vb, expected) in cases {
+        a.set(va);
+        b.set(vb);
+        execute_gates();
+        assert_eq!(expected, out.get());
+    }
+}
+
+#[test]
+fn test_four_state_reset() {
+    use crate::*;
+
+    // the same counter with and without a reset input
+    let build = |with_reset: bool| {
+        let reset = input();
+        let count = reg_w::<4>();
+        let next = add_naive(count.out, Wires::parse_u8(1)).sum;
+        if with_reset {
+            count.set_in(mux2_w(next, Wires::parse_u8(0), reset));
+        } else {
+            count.set_in(next);
+        }
+        (reset, count.out)
+    };
+
+    for with_reset in [false, true] {
+        let mut circuit = Circuit::new();
+        circuit.set_four_state(true);
+        circuit.enter(|| {
+            let (reset, count) = build(with_reset);
+            assert_eq!(None, count.try_get_u8());
+            assert_eq!(4, unknown_regs().len());
+
+            reset.set(1);
+            simulate();
+            reset.set(0);
+            simulate();
+            simulate();
+            if with_reset {
+                assert!(unknown_regs().is_empty());
+                assert_eq!(Some(2), count.try_get_u8());
+            } else {
+                assert_eq!(4, unknown_regs().len());
+                assert_eq!(None, count.try_get_u8());
+                assert!(std::panic::catch_unwind(|| count.get_u8()).is_err());
+            }
+        });
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{current, Assert, Circuit, IsTrue, Wire, WireValue, Wires, WIRE_0, WIRE_1};
+
+/// One bit per stimulus lane, lane `i` is bit `i`.
+pub type LaneValue = u64;
+pub const LANE_COUNT: usize = LaneValue::BITS as usize;
+
+fn broadcast(value: WireValue) -> LaneValue {
+    match value {
+        1 => LaneValue::MAX,
+        _ => 0,
+    }
+}
+
+impl Circuit {
+    fn sync_lanes(&mut self) {
+        assert!(!self.four_state, "Lane simulation is two-state only!");
+        let old_len = self.lanes.len();
+        if old_len < self.wires.len() {
+            // new wires start at their scalar value in every lane, so regs start at their init
+            let wires = &self.wires;
+            self.lanes
+                .extend((old_len..wires.len()).map(|index| broadcast(wires[index])));
+        }
+        self.lanes[WIRE_0] = 0;
+        self.lanes[WIRE_1] = LaneValue::MAX;
+    }
+
+    pub fn get_lanes(&self, wire: Wire) -> LaneValue {
+        match wire.0 {
+            WIRE_0 => 0,
+            WIRE_1 => LaneValue::MAX,
+            index => match self.lanes.get(index) {
+                Some(value) => *value,
+                None => broadcast(self.wires[index]),
+            },
+        }
+    }
+    pub fn set_lanes(&mut self, wire: Wire, value: LaneValue) {
+        self.sync_lanes();
+        self.lanes[wire.0] = value;
+    }
+
+    /// Same as `simulate()`, but every wire evaluates `LANE_COUNT` independent lanes at once.
+    ///
+    /// Clock domains tick on phases of their own, apart from `clock_tick()`. Stuck-at wires are
+    /// not forced, only the gates and regs `inject_stuck_at()` rewired see the fault.
+    pub fn simulate_lanes(&mut self) {
+        self.execute_gates_lanes();
+        self.clock_tick_lanes();
+    }
+
+    pub fn execute_gates_lanes(&mut self) {
+        assert!(
+            self.externals.is_empty(),
+            "Lane simulation supports wire/reg only! Externals are not supported!"
+        );
+        self.sync_lanes();
+        let lanes = &mut self.lanes;
+        for gate in &self.gates {
+            lanes[gate.wire_out.0] = !(lanes[gate.wire_a.0] & lanes[gate.wire_b.0]);
+        }
+    }
+
+    pub fn clock_tick_lanes(&mut self) {
+        self.sync_lanes();
+        let phases = self.clock_domains.phases();
+        self.lane_phases.resize(phases.len(), 0);
+        self.clock_domains.set_phases(&self.lane_phases);
+        let due = self.clock_domains.advance();
+        self.lane_phases = self.clock_domains.phases();
+        self.clock_domains.set_phases(&phases);
+
+        let lanes = &mut self.lanes;
+        let reset = self.reset.map_or(0, |w| lanes[w.0]);
+        let temp_values = self
+            .regs
+            .iter()
+            .map(|reg| {
+                if (due >> reg.domain.0) & 1 == 0 {
+                    return lanes[reg.wire_out.0];
+                }
+                let value = reg.wire_in.map_or(0, |w| lanes[w.0]);
+                let enable = reg.enable.map_or(LaneValue::MAX, |w| lanes[w.0]);
+                let init = broadcast(reg.init.unwrap_or(0));
+                let loaded = (enable & value) | (!enable & lanes[reg.wire_out.0]);
+                (reset & init) | (!reset & loaded)
+            })
+            .collect::<Vec<_>>();
+        self.regs
+            .iter()
+            .zip(temp_values)
+            .for_each(|(reg, value)| lanes[reg.wire_out.0] = value);
+    }
+}
+
+pub fn simulate_lanes() {
+    current().simulate_lanes();
+}
+
+pub fn execute_gates_lanes() {
+    current().execute_gates_lanes();
+}
+
+pub fn clock_tick_lanes() {
+    current().clock_tick_lanes();
+}
+
+impl Wire {
+    pub fn get_lanes(self) -> LaneValue {
+        current().get_lanes(self)
+    }
+    pub fn set_lanes(self, value: LaneValue) {
+        current().set_lanes(self, value);
+    }
+    pub fn get_lane(self, lane: usize) -> WireValue {
+        ((self.get_lanes() >> lane) & 1) as WireValue
+    }
+    pub fn set_lane(self, lane: usize, value: WireValue) {
+        let mask = 1 << lane;
+        let lanes = self.get_lanes() & !mask;
+        self.set_lanes(lanes | (((value & 1) as LaneValue) << lane));
+    }
+}
+
+impl<const W: usize> Wires<W>
+where
+    Assert<{ W <= 8 }>: IsTrue,
+{
+    pub fn set_u8_lane(&self, lane: usize, value: u8) {
+        for i in 0..W {
+            self.wires[i].set_lane(lane, ((value & (1 << i)) > 0).into());
+        }
+    }
+
+    pub fn get_u8_lane(&self, lane: usize) -> u8 {
+        self.wires
+            .iter()
+            .enumerate()
+            .map(|(i, wire)| wire.get_lane(lane) << i)
+            .reduce(|a, b| a | b)
+            .unwrap()
+    }
+}
+
+#[test]
+fn test_lanes_add_exhaustive() {
+    use crate::{add_naive, clear_all, input_w};
+    clear_all();
+
+    let a = input_w::<8>();
+    let b = input_w::<8>();
+    let r = add_naive(a, b);
+
+    let cases = (0..=255u8).flat_map(|a| (0..=255u8).map(move |b| (a, b)));
+    let cases = cases.collect::<Vec<_>>();
+    for chunk in cases.chunks(LANE_COUNT) {
+        for (lane, (va, vb)) in chunk.iter().enumerate() {
+            a.set_u8_lane(lane, *va);
+            b.set_u8_lane(lane, *vb);
+        }
+        simulate_lanes();
+        for (lane, (va, vb)) in chunk.iter().enumerate() {
+            let sum = *va as u16 + *vb as u16;
+            assert_eq!((sum % 256) as u8, r.sum.get_u8_lane(lane));
+            assert_eq!((sum / 256) as u8, r.carry.get_lane(lane));
+        }
+    }
+}
+
+#[test]
+fn test_lanes_reg() {
+    use crate::{add_naive, clear_all, reg_w, simulate};
+    clear_all();
+
+    // each lane counts with its own step, lane 0 matches the scalar simulation
+    let step = crate::input_w::<4>();
+    let curr = reg_w::<4>();
+    curr.set_in(add_naive(curr.out, step).sum);
+    step.set_u8(1);
+    for lane in 0..LANE_COUNT {
+        step.set_u8_lane(lane, (lane % 16) as u8);
+    }
+    for i in 1..40usize {
+        simulate();
+        simulate_lanes();
+        assert_eq!((i % 16) as u8, curr.out.get_u8());
+        for lane in 0..LANE_COUNT {
+            assert_eq!((i * lane % 16) as u8, curr.out.get_u8_lane(lane));
+        }
+    }
+}
+
+#[test]
+fn test_lanes_clock_domains() {
+    use crate::{add_clock_domain, add_naive, clear_all, clock_domain, reg_w, simulate};
+    clear_all();
+
+    // a counter every third tick, mixing both modes shifts neither
+    let slow = add_clock_domain("slow", 1, 3);
+    let count = clock_domain(slow, reg_w::<4>);
+    count.set_in(add_naive(count.out, Wires::parse_u8(1)).sum);
+    simulate();
+    simulate_lanes();
+    simulate_lanes();
+    assert_eq!(0, count.out.get_u8_lane(0));
+    simulate_lanes();
+    assert_eq!(1, count.out.get_u8_lane(0));
+    simulate();
+    assert_eq!(0, count.out.get_u8());
+    simulate();
+    assert_eq!(1, count.out.get_u8());
+}
@@ -0,0 +1,12 @@
+mod compiled;
+mod event_driven;
+mod four_state;
+mod lanes;
+mod snapshot;
+mod toggles;
+pub use compiled::*;
+pub use event_driven::*;
+pub use four_state::*;
+pub use lanes::*;
+pub use snapshot::*;
+pub use toggles::*;
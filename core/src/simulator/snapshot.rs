@@ -0,0 +1,198 @@
+use crate::{current, Circuit, WireValue};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Simulation state of a circuit: wire values, reg temp values, clock domain phases and the state
+/// of externals that opt in through `External::save_state`. The netlist itself is not included, a snapshot can
+/// only be restored into the circuit it was taken from (or one built the same way).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub wires: Vec<WireValue>,
+    pub reg_temps: Vec<WireValue>,
+    pub clock_phases: Vec<u32>,
+    pub externals: Vec<Option<Vec<u8>>>,
+}
+
+const MAGIC: &[u8; 8] = b"DDSNAP02";
+
+impl Snapshot {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        let mut write_block = |block: &[u8]| {
+            bytes.extend_from_slice(&(block.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(block);
+        };
+        write_block(&self.wires);
+        write_block(&self.reg_temps);
+        let phases = self
+            .clock_phases
+            .iter()
+            .flat_map(|phase| phase.to_le_bytes());
+        write_block(&phases.collect::<Vec<_>>());
+        write_block(&(self.externals.len() as u64).to_le_bytes());
+        for state in &self.externals {
+            match state {
+                None => write_block(&[0]),
+                Some(state) => {
+                    write_block(&[1]);
+                    write_block(state);
+                }
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> std::io::Result<Snapshot> {
+        let invalid = || Error::new(ErrorKind::InvalidData, "invalid snapshot");
+        let rest = bytes.strip_prefix(MAGIC).ok_or_else(invalid)?;
+        let mut cursor = 0;
+        let mut read_block = || {
+            let len = rest.get(cursor..cursor + 8).ok_or_else(invalid)?;
+            let len = u64::from_le_bytes(len.try_into().unwrap()) as usize;
+            let block = rest.get(cursor + 8..cursor + 8 + len).ok_or_else(invalid)?;
+            cursor += 8 + len;
+            Ok::<_, Error>(block)
+        };
+
+        let wires = read_block()?.to_vec();
+        let reg_temps = read_block()?.to_vec();
+        let phases = read_block()?;
+        if phases.len() % 4 != 0 {
+            return Err(invalid());
+        }
+        let clock_phases = phases
+            .chunks(4)
+            .map(|phase| u32::from_le_bytes(phase.try_into().unwrap()))
+            .collect();
+        let count = read_block()?.try_into().map_err(|_| invalid())?;
+        let externals = (0..u64::from_le_bytes(count))
+            .map(|_| match read_block()? {
+                [0] => Ok(None),
+                [1] => Ok(Some(read_block()?.to_vec())),
+                _ => Err(invalid()),
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(Snapshot {
+            wires,
+            reg_temps,
+            clock_phases,
+            externals,
+        })
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    pub fn read_from_file(path: impl AsRef<Path>) -> std::io::Result<Snapshot> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+impl Circuit {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            wires: self.wires.clone(),
+            reg_temps: self.regs.iter().map(|reg| reg.temp_value).collect(),
+            clock_phases: self.clock_domains.phases(),
+            externals: self
+                .externals
+                .iter()
+                .map(|e| e.borrow().save_state())
+                .collect(),
+        }
+    }
+
+    /// Rewind to a snapshot taken from this netlist.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        assert!(
+            snapshot.wires.len() == self.wires.len()
+                && snapshot.reg_temps.len() == self.regs.len()
+                && snapshot.clock_phases.len() == self.clock_domain_count()
+                && snapshot.externals.len() == self.externals.len(),
+            "Snapshot does not match the netlist!"
+        );
+        self.wires.copy_from_slice(&snapshot.wires);
+        for (reg, temp_value) in self.regs.iter_mut().zip(&snapshot.reg_temps) {
+            reg.temp_value = *temp_value;
+        }
+        self.clock_domains.set_phases(&snapshot.clock_phases);
+        for (external, state) in self.externals.iter().zip(&snapshot.externals) {
+            if let Some(state) = state {
+                external.borrow_mut().restore_state(state);
+            }
+        }
+        // its last seen source values are gone, the rebuild evaluates every gate once
+        self.event_driven = None;
+    }
+}
+
+pub fn snapshot() -> Snapshot {
+    current().snapshot()
+}
+
+pub fn restore(snapshot: &Snapshot) {
+    current().restore(snapshot);
+}
+
+#[test]
+fn test_snapshot_restore() {
+    use crate::*;
+
+    for backend in [
+        SimulationBackend::Interpreted,
+        SimulationBackend::Compiled,
+        SimulationBackend::EventDriven,
+    ] {
+        let mut circuit = Circuit::new();
+        circuit.set_backend(backend);
+        circuit.enter(|| {
+            let step = input_w::<4>();
+            let curr = reg_w::<4>();
+            curr.set_in(add_naive(curr.out, step).sum);
+            let logger = external(LoggerU8::new("curr".to_string(), curr.out));
+            let doubled = add_naive(curr.out, curr.out).sum;
+
+            let run = |cycles: u8| {
+                (0..cycles)
+                    .map(|i| {
+                        step.set_u8(i % 3 + 1);
+                        simulate();
+                        doubled.get_u8()
+                    })
+                    .collect::<Vec<_>>()
+            };
+            run(10);
+            let saved = snapshot();
+            let logged = logger.borrow().get_values().clone();
+            let expected = run(10);
+            let expected_log = logger.borrow().get_values().clone();
+
+            // rewind, through a file
+            let path = std::env::temp_dir().join(format!("snapshot_{backend:?}.bin"));
+            saved.write_to_file(&path).unwrap();
+            let loaded = Snapshot::read_from_file(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(saved, loaded);
+
+            restore(&loaded);
+            assert_eq!(&logged, logger.borrow().get_values());
+            assert_eq!(expected, run(10));
+            assert_eq!(&expected_log, logger.borrow().get_values());
+        });
+    }
+}
+
+#[test]
+fn test_snapshot_bytes() {
+    let snapshot = Snapshot {
+        wires: vec![0, 1, 1, 0],
+        reg_temps: vec![1],
+        clock_phases: vec![0, 2],
+        externals: vec![None, Some(vec![]), Some(vec![3, 4])],
+    };
+    let bytes = snapshot.to_bytes();
+    assert_eq!(snapshot, Snapshot::from_bytes(&bytes).unwrap());
+    assert!(Snapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Snapshot::from_bytes(b"DDSNAP00").is_err());
+}
@@ -0,0 +1,293 @@
+use crate::{current, Circuit, NamedWire, Wire, WireValue};
+use std::fmt::{Display, Formatter};
+
+/// Per-wire toggle counts, sampled after every `execute_gates()` and `clock_tick()`.
+///
+/// Values are compared between samples, so a wire that glitches inside one `execute_gates()`
+/// counts once at most, like in a zero-delay power estimate.
+pub(crate) struct ToggleCounter {
+    last: Vec<WireValue>,
+    counts: Vec<u64>,
+    per_cycle: Vec<u64>,
+    current_cycle: u64,
+}
+
+impl ToggleCounter {
+    fn new(wires: &[WireValue]) -> Self {
+        Self {
+            last: wires.to_vec(),
+            counts: vec![0; wires.len()],
+            per_cycle: Vec::new(),
+            current_cycle: 0,
+        }
+    }
+
+    fn sample(&mut self, wires: &[WireValue], end_of_cycle: bool) {
+        // wires created since the last sample start without a toggle
+        if self.last.len() < wires.len() {
+            self.last.extend_from_slice(&wires[self.last.len()..]);
+            self.counts.resize(wires.len(), 0);
+        }
+        for ((last, count), value) in self.last.iter_mut().zip(&mut self.counts).zip(wires) {
+            if last != value {
+                *last = *value;
+                *count += 1;
+                self.current_cycle += 1;
+            }
+        }
+        if end_of_cycle {
+            self.per_cycle.push(self.current_cycle);
+            self.current_cycle = 0;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireActivity {
+    pub wire: NamedWire,
+    pub toggles: u64,
+}
+
+/// Wires of one scope (not including its children).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeActivity {
+    pub scope: String,
+    pub wire_count: usize,
+    pub toggles: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToggleReport {
+    pub cycles: usize,
+    pub wire_count: usize,
+    pub total: u64,
+    pub per_cycle: Vec<u64>,          // toggles of each finished clock_tick()
+    pub scopes: Vec<ScopeActivity>,   // sorted by toggles
+    pub top_wires: Vec<WireActivity>, // most active first
+}
+
+impl ToggleReport {
+    pub fn average_per_cycle(&self) -> f64 {
+        match self.cycles {
+            0 => 0.0,
+            cycles => self.total as f64 / cycles as f64,
+        }
+    }
+    /// Share of wires toggling per cycle, the usual activity factor of dynamic power.
+    pub fn activity_factor(&self) -> f64 {
+        match self.wire_count {
+            0 => 0.0,
+            wire_count => self.average_per_cycle() / wire_count as f64,
+        }
+    }
+}
+
+impl Circuit {
+    /// Start counting toggles from the current wire values, or stop and drop the counts.
+    pub fn set_toggle_counting(&mut self, enabled: bool) {
+        self.toggles = enabled.then(|| ToggleCounter::new(&self.wires));
+    }
+    pub fn is_toggle_counting(&self) -> bool {
+        self.toggles.is_some()
+    }
+
+    pub(crate) fn count_toggles(&mut self, end_of_cycle: bool) {
+        if let Some(toggles) = &mut self.toggles {
+            toggles.sample(&self.wires, end_of_cycle);
+        }
+    }
+
+    /// Activity since `set_toggle_counting(true)`, with the `top` most active wires.
+    pub fn toggle_report(&self, top: usize) -> ToggleReport {
+        let toggles = self
+            .toggles
+            .as_ref()
+            .expect("Toggle counting is off, see set_toggle_counting()!");
+        let counts = &toggles.counts;
+
+        let mut scopes = (0..self.scope_count())
+            .map(|scope| ScopeActivity {
+                scope: self.scope_path(scope),
+                wire_count: 0,
+                toggles: 0,
+            })
+            .collect::<Vec<_>>();
+        for (index, count) in counts.iter().enumerate() {
+            let scope = &mut scopes[self.wire_scope(Wire(index))];
+            scope.wire_count += 1;
+            scope.toggles += count;
+        }
+        scopes.retain(|scope| scope.wire_count > 0);
+        scopes.sort_by_key(|scope| std::cmp::Reverse(scope.toggles));
+
+        let mut active = (0..counts.len())
+            .filter(|index| counts[*index] > 0)
+            .collect::<Vec<_>>();
+        active.sort_by_key(|index| std::cmp::Reverse(counts[*index]));
+        let top_wires = active
+            .into_iter()
+            .take(top)
+            .map(|index| WireActivity {
+                wire: self.named(index),
+                toggles: counts[index],
+            })
+            .collect();
+
+        ToggleReport {
+            cycles: toggles.per_cycle.len(),
+            wire_count: counts.len(),
+            total: counts.iter().sum(),
+            per_cycle: toggles.per_cycle.clone(),
+            scopes,
+            top_wires,
+        }
+    }
+}
+
+pub fn set_toggle_counting(enabled: bool) {
+    current().set_toggle_counting(enabled);
+}
+
+pub fn toggle_report(top: usize) -> ToggleReport {
+    current().toggle_report(top)
+}
+
+impl Display for ToggleReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "toggles: {} in {} cycles, {:.1} per cycle, activity factor {:.3}",
+            self.total,
+            self.cycles,
+            self.average_per_cycle(),
+            self.activity_factor()
+        )?;
+        writeln!(f, "scopes (toggles / wires):")?;
+        for scope in &self.scopes {
+            let name = if scope.scope.is_empty() {
+                "(root)"
+            } else {
+                &scope.scope
+            };
+            writeln!(f, "  {name}: {} / {}", scope.toggles, scope.wire_count)?;
+        }
+        writeln!(f, "top wires:")?;
+        for wire in &self.top_wires {
+            writeln!(f, "  {}: {}", wire.wire.name, wire.toggles)?;
+        }
+        Ok(())
+    }
+}
+
+impl Wire {
+    /// Toggles since `set_toggle_counting(true)`.
+    pub fn toggle_count(self) -> u64 {
+        let circuit = current();
+        let toggles = circuit.toggles.as_ref();
+        toggles
+            .and_then(|t| t.counts.get(self.0).copied())
+            .unwrap_or(0)
+    }
+}
+
+#[test]
+fn test_toggle_report() {
+    use crate::*;
+    clear_all();
+
+    let a = input().named("a");
+    let b = input().named("b");
+    let out = scope("logic", || (a & b).named("and"));
+    let count = reg_w::<2>().named("count");
+    count.set_in(add_naive(count.out, Wires::parse_u8(1)).sum);
+    set_toggle_counting(true);
+
+    for i in 0..4u8 {
+        a.set(i & 1);
+        b.set(1);
+        simulate();
+    }
+    // b rises once, a and count[0] toggle every cycle (a starts at 0)
+    let report = toggle_report(3);
+    let text = report.to_string();
+    assert!(text.starts_with("toggles: 69 in 4 cycles, 17.2 per cycle, activity factor 0.507\n"));
+    assert!(text.contains("scopes (toggles / wires):\n  (root): 62 / 32\n  logic: 7 / 2\n"));
+    assert!(text.ends_with(&format!(
+        "top wires:\n  logic.w{}: 4\n  count[0]: 4\n  w{}: 4\n",
+        report.top_wires[0].wire.index, report.top_wires[2].wire.index
+    )));
+    assert_eq!(4, report.cycles);
+    assert_eq!(3, a.toggle_count());
+    assert_eq!(1, b.toggle_count());
+    assert_eq!(3, out.toggle_count());
+    assert_eq!(4, count.out.wires[0].toggle_count());
+    assert_eq!(report.total, report.per_cycle.iter().sum());
+    assert_eq!(3, report.top_wires.len());
+    assert_eq!(4, report.top_wires[0].toggles);
+    // the and is a nand and a not, the nand rises on the first execute_gates()
+    let logic = report.scopes.iter().find(|s| s.scope == "logic").unwrap();
+    assert_eq!((2, 4 + 3), (logic.wire_count, logic.toggles));
+}
+
+#[test]
+fn test_toggle_adders() {
+    use crate::*;
+
+    // ripple carry against a carry select adder, on the same stimulus
+    fn carry_select(a: Wires<8>, b: Wires<8>) -> Wires<8> {
+        let half = |w: Wires<8>, offset: usize| Wires::<4> {
+            wires: std::array::from_fn(|i| w.wires[i + offset]),
+        };
+        let low = add_naive(half(a, 0), half(b, 0));
+        let high0 = add_naive(half(a, 4), half(b, 4)).sum;
+        let high1 = add_naive(high0, Wires::parse_u8(1)).sum;
+        let high = mux2_w(high0, high1, low.carry);
+        Wires {
+            wires: std::array::from_fn(|i| {
+                if i < 4 {
+                    low.sum.wires[i]
+                } else {
+                    high.wires[i - 4]
+                }
+            }),
+        }
+    }
+
+    let mut reports = vec![];
+    for select in [false, true] {
+        let mut circuit = Circuit::new();
+        let report = circuit.enter(|| {
+            let a = input_w::<8>();
+            let b = input_w::<8>();
+            let sum = if select {
+                carry_select(a, b)
+            } else {
+                add_naive(a, b).sum
+            };
+            set_toggle_counting(true);
+            for i in 0..32u8 {
+                a.set_u8(i.wrapping_mul(37));
+                b.set_u8(i.wrapping_mul(101) ^ 0x5a);
+                simulate();
+                assert_eq!(a.get_u8().wrapping_add(b.get_u8()), sum.get_u8());
+            }
+            toggle_report(5)
+        });
+        let (total, wire_count) = (report.total, report.wire_count);
+        let text = report.to_string();
+        assert!(text.contains(&format!(
+            "scopes (toggles / wires):\n  (root): {total} / {wire_count}\ntop wires:\n"
+        )));
+        // the five most active wires, each toggling in every cycle
+        assert_eq!(
+            5,
+            text.lines().skip_while(|l| *l != "top wires:").count() - 1
+        );
+        assert!(text.ends_with(": 32\n"));
+        assert_eq!(32, report.cycles);
+        reports.push(report);
+    }
+    // the select adder computes both upper halves
+    assert!(reports[1].total > reports[0].total);
+    assert!(reports[0].activity_factor() > 0.0);
+}
@@ -1,3 +1,7 @@
+mod truth_table;
+
+pub use truth_table::*;
+
 pub fn shuffled_list(count: usize, seed: f32) -> Vec<u32> {
     let hash = |v: &u32| -> f32 {
         let v = (*v as f32) * seed;
@@ -0,0 +1,301 @@
+use crate::{execute_gates, Wire, Wires};
+use std::fmt::{Display, Formatter};
+
+struct TruthTablePort {
+    name: String,
+    wires: Vec<Wire>, // lsb first, at most 64
+}
+
+impl TruthTablePort {
+    fn new(name: &str, wires: &[Wire]) -> Self {
+        assert!(wires.len() <= 64, "Port {name} is wider than 64 bits!");
+        Self {
+            name: name.to_string(),
+            wires: wires.to_vec(),
+        }
+    }
+    fn mask(&self) -> u64 {
+        match self.wires.len() {
+            64 => u64::MAX,
+            width => (1 << width) - 1,
+        }
+    }
+    fn set(&self, value: u64) {
+        for (i, wire) in self.wires.iter().enumerate() {
+            wire.set(((value >> i) & 1) as u8);
+        }
+    }
+    fn get(&self) -> u64 {
+        let bits = self.wires.iter().enumerate();
+        bits.fold(0, |value, (i, wire)| value | ((wire.get() as u64) << i))
+    }
+}
+
+/// One input combination where the circuit and the reference disagree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruthTableMismatch {
+    pub inputs: Vec<u64>,
+    pub expected: Vec<u64>,
+    pub actual: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruthTableReport {
+    pub name: String,
+    pub input_names: Vec<String>,
+    pub output_names: Vec<String>,
+    pub output_widths: Vec<usize>,
+    pub exhaustive: bool,
+    pub cases: usize,
+    pub mismatch_count: usize,
+    pub mismatches: Vec<TruthTableMismatch>, // the first ones, see `TruthTable::max_mismatches()`
+}
+
+impl TruthTableReport {
+    pub fn passed(&self) -> bool {
+        self.mismatch_count == 0
+    }
+}
+
+impl Display for TruthTableReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mode = if self.exhaustive {
+            "exhaustive"
+        } else {
+            "random"
+        };
+        writeln!(
+            f,
+            "{}: {} of {} {mode} cases differ",
+            self.name, self.mismatch_count, self.cases
+        )?;
+        for mismatch in &self.mismatches {
+            let inputs = self.input_names.iter().zip(&mismatch.inputs);
+            let inputs = inputs.map(|(name, value)| format!("{name}={value}"));
+            writeln!(f, "  {}", inputs.collect::<Vec<_>>().join(" "))?;
+            for (i, name) in self.output_names.iter().enumerate() {
+                let (expected, actual) = (mismatch.expected[i], mismatch.actual[i]);
+                if expected == actual {
+                    continue;
+                }
+                let width = self.output_widths[i];
+                writeln!(
+                    f,
+                    "    {name}: expected {expected} ({expected:0width$b}), got {actual} \
+                     ({actual:0width$b}), diff {:0width$b}",
+                    expected ^ actual
+                )?;
+            }
+        }
+        if self.mismatch_count > self.mismatches.len() {
+            let more = self.mismatch_count - self.mismatches.len();
+            writeln!(f, "  ... {more} more")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks a combinational circuit against a reference function over integers.
+///
+/// Every input combination is tried when the inputs have at most `exhaustive_limit()` bits in
+/// total, otherwise `samples()` random ones from `seed()`. The reference gets the input values
+/// in the order the ports were added and returns the outputs in their order, truncated to the
+/// width of each port. Each case is one `execute_gates()`, regs do not tick.
+pub struct TruthTable {
+    name: String,
+    inputs: Vec<TruthTablePort>,
+    outputs: Vec<TruthTablePort>,
+    exhaustive_limit: usize,
+    samples: usize,
+    seed: u64,
+    max_mismatches: usize,
+}
+
+impl TruthTable {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            inputs: vec![],
+            outputs: vec![],
+            exhaustive_limit: 16,
+            samples: 10_000,
+            seed: 1,
+            max_mismatches: 10,
+        }
+    }
+    pub fn input_wire(&mut self, name: &str, wire: Wire) -> &mut Self {
+        self.inputs.push(TruthTablePort::new(name, &[wire]));
+        self
+    }
+    pub fn input_wires<const W: usize>(&mut self, name: &str, wires: Wires<W>) -> &mut Self {
+        self.inputs.push(TruthTablePort::new(name, &wires.wires));
+        self
+    }
+    pub fn output_wire(&mut self, name: &str, wire: Wire) -> &mut Self {
+        self.outputs.push(TruthTablePort::new(name, &[wire]));
+        self
+    }
+    pub fn output_wires<const W: usize>(&mut self, name: &str, wires: Wires<W>) -> &mut Self {
+        self.outputs.push(TruthTablePort::new(name, &wires.wires));
+        self
+    }
+    /// Most input bits in total that are still tried exhaustively, 16 by default.
+    pub fn exhaustive_limit(&mut self, bits: usize) -> &mut Self {
+        assert!(bits < 64, "Exhaustive limit must be below 64 bits!");
+        self.exhaustive_limit = bits;
+        self
+    }
+    /// Random cases above the exhaustive limit, 10000 by default.
+    pub fn samples(&mut self, samples: usize) -> &mut Self {
+        self.samples = samples;
+        self
+    }
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = seed;
+        self
+    }
+    /// Mismatches kept in the report, 10 by default, all of them are counted.
+    pub fn max_mismatches(&mut self, count: usize) -> &mut Self {
+        self.max_mismatches = count;
+        self
+    }
+
+    pub fn run(&self, reference: impl Fn(&[u64]) -> Vec<u64>) -> TruthTableReport {
+        let width = self
+            .inputs
+            .iter()
+            .map(|port| port.wires.len())
+            .sum::<usize>();
+        let exhaustive = width <= self.exhaustive_limit;
+        let cases = if exhaustive { 1 << width } else { self.samples };
+        // splitmix64
+        let mut state = self.seed;
+        let mut random = || {
+            state = state.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+
+        let mut mismatch_count = 0;
+        let mut mismatches = vec![];
+        for case in 0..cases {
+            let mut offset = 0;
+            let inputs = self
+                .inputs
+                .iter()
+                .map(|port| {
+                    let value = match exhaustive {
+                        true => (case as u64 >> offset) & port.mask(),
+                        false => random() & port.mask(),
+                    };
+                    offset += port.wires.len();
+                    port.set(value);
+                    value
+                })
+                .collect::<Vec<_>>();
+            execute_gates();
+            let actual = self
+                .outputs
+                .iter()
+                .map(|port| port.get())
+                .collect::<Vec<_>>();
+            let expected = reference(&inputs);
+            assert_eq!(
+                self.outputs.len(),
+                expected.len(),
+                "Reference of {} returned {} outputs!",
+                self.name,
+                expected.len()
+            );
+            let expected = (self.outputs.iter().zip(expected))
+                .map(|(port, value)| value & port.mask())
+                .collect::<Vec<_>>();
+            if expected != actual {
+                mismatch_count += 1;
+                if mismatches.len() < self.max_mismatches {
+                    mismatches.push(TruthTableMismatch {
+                        inputs,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+        TruthTableReport {
+            name: self.name.clone(),
+            input_names: self.inputs.iter().map(|port| port.name.clone()).collect(),
+            output_names: self.outputs.iter().map(|port| port.name.clone()).collect(),
+            output_widths: self.outputs.iter().map(|port| port.wires.len()).collect(),
+            exhaustive,
+            cases,
+            mismatch_count,
+            mismatches,
+        }
+    }
+
+    /// `run()`, panicking with the report on a mismatch.
+    pub fn check(&self, reference: impl Fn(&[u64]) -> Vec<u64>) {
+        let report = self.run(reference);
+        assert!(report.passed(), "{report}");
+    }
+}
+
+#[test]
+fn test_truth_table() {
+    use crate::*;
+    clear_all();
+
+    let a = input_w::<8>();
+    let b = input_w::<8>();
+    let select = input();
+    let sum = add_naive(a, b);
+    TruthTable::new("add_naive")
+        .input_wires("a", a)
+        .input_wires("b", b)
+        .output_wires("sum", sum.sum)
+        .output_wire("carry", sum.carry)
+        .check(|v| vec![v[0] + v[1], (v[0] + v[1]) >> 8]);
+
+    let mux = mux2_w(a, b, select);
+    TruthTable::new("mux2_w")
+        .input_wires("a", a)
+        .input_wires("b", b)
+        .input_wire("select", select)
+        .output_wires("out", mux)
+        .samples(500)
+        .check(|v| vec![if v[2] == 1 { v[1] } else { v[0] }]);
+
+    let s = input_w::<4>();
+    let decoded = Wires { wires: decode4(s) };
+    TruthTable::new("decode4")
+        .input_wires("s", s)
+        .output_wires("lines", decoded)
+        .check(|v| vec![1 << v[0]]);
+
+    // an off by one reference, seeded samples come out the same every run
+    let mut table = TruthTable::new("add_naive");
+    table
+        .input_wires("a", a)
+        .input_wires("b", b)
+        .output_wires("sum", sum.sum)
+        .exhaustive_limit(8)
+        .samples(100)
+        .max_mismatches(2)
+        .seed(7);
+    let off_at_3 = |v: &[u64]| vec![v[0] + v[1] + (v[0] == 3) as u64];
+    assert_eq!(table.run(off_at_3), table.run(off_at_3));
+    let report = table.run(|v| vec![v[0] + v[1] + 1]);
+    assert!(!report.exhaustive);
+    assert_eq!(
+        (100, 100, 2),
+        (report.cases, report.mismatch_count, report.mismatches.len())
+    );
+    let first = &report.mismatches[0];
+    assert_eq!(first.expected[0], (first.actual[0] + 1) & 0xff);
+    let text = report.to_string();
+    assert!(text.starts_with("add_naive: 100 of 100 random cases differ\n  a="));
+    assert!(text.contains("    sum: expected "));
+    assert!(text.ends_with("  ... 98 more\n"));
+}
@@ -0,0 +1,332 @@
+use crate::{current, Circuit, LatencyValue, NamedWire, ScopeId, Wire, WIRE_0, WIRE_1};
+use std::fmt::{Display, Formatter};
+
+/// A chain of gates, from a source wire (input or reg output) to an endpoint wire.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimingPath {
+    pub depth: LatencyValue,
+    pub wires: Vec<NamedWire>, // source first, one more than gates
+    pub gates: Vec<usize>,     // gate indices, in signal order
+}
+
+/// Gates of one scope (not including its children).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleDepth {
+    pub scope: String,
+    pub gate_count: usize,
+    /// Longest chain of gates that stays inside this scope.
+    pub max_depth: LatencyValue,
+    /// Gates of `TimingReport::critical_path` inside this scope.
+    pub critical_gates: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegSlack {
+    pub reg: usize,
+    pub wire_in: NamedWire,
+    pub arrival: LatencyValue,
+    pub slack: LatencyValue, // period - arrival
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimingReport {
+    /// Depth the clock has to cover: the deepest reg input or output.
+    pub period: LatencyValue,
+    /// Longest path from a reg output to a reg input.
+    pub reg_to_reg: Option<TimingPath>,
+    /// Longest path from an input to an output, a gate output that nothing reads.
+    pub input_to_output: Option<TimingPath>,
+    /// Longest path to any reg input or output, it defines `period`.
+    pub critical_path: Option<TimingPath>,
+    pub modules: Vec<ModuleDepth>, // sorted by critical_gates, then max_depth
+    pub reg_slack: Vec<RegSlack>,  // in reg order
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Source {
+    Any,
+    Input,
+    Reg,
+}
+
+/// Arrival depth of each wire, counted from the LATENCIES of the source wires.
+struct Arrival {
+    driver: Vec<Option<usize>>, // gate driving each wire
+    depth: Vec<Option<LatencyValue>>,
+}
+
+impl Circuit {
+    fn arrival(&self, source: Source) -> Arrival {
+        let wire_count = self.wires.len();
+        let mut driver = vec![None; wire_count];
+        for (index, gate) in self.gates.iter().enumerate() {
+            driver[gate.wire_out.0] = Some(index);
+        }
+        let mut is_reg = vec![false; wire_count];
+        for reg in &self.regs {
+            is_reg[reg.wire_out.0] = true;
+        }
+
+        let mut depth = (0..wire_count)
+            .map(|wire| {
+                let included = match source {
+                    _ if wire == WIRE_0 || wire == WIRE_1 || driver[wire].is_some() => false,
+                    Source::Any => true,
+                    Source::Input => !is_reg[wire],
+                    Source::Reg => is_reg[wire],
+                };
+                included.then(|| self.latencies[wire])
+            })
+            .collect::<Vec<_>>();
+        // gate inputs are always created before the gate
+        for gate in &self.gates {
+            let inputs = depth[gate.wire_a.0].max(depth[gate.wire_b.0]);
+            depth[gate.wire_out.0] = inputs.map(|d| d + 1);
+        }
+        Arrival { driver, depth }
+    }
+
+    /// Walk back from `end` along the inputs that define its depth.
+    fn trace_path(&self, arrival: &Arrival, end: Wire) -> TimingPath {
+        let depth = arrival.depth[end.0].unwrap();
+        let mut wires = vec![end.0];
+        let mut gates = vec![];
+        let mut wire = end.0;
+        while let Some(index) = arrival.driver[wire] {
+            let gate = &self.gates[index];
+            gates.push(index);
+            let (a, b) = (gate.wire_a.0, gate.wire_b.0);
+            wire = if arrival.depth[a] >= arrival.depth[b] {
+                a
+            } else {
+                b
+            };
+            wires.push(wire);
+        }
+        wires.reverse();
+        gates.reverse();
+        TimingPath {
+            depth,
+            wires: wires.into_iter().map(|w| self.named(w)).collect(),
+            gates,
+        }
+    }
+
+    fn longest_path(&self, arrival: &Arrival, ends: &[Wire]) -> Option<TimingPath> {
+        let end = ends
+            .iter()
+            .filter(|w| arrival.depth[w.0].is_some())
+            .max_by_key(|w| (arrival.depth[w.0], std::cmp::Reverse(w.0)))?;
+        Some(self.trace_path(arrival, *end))
+    }
+
+    /// Gate outputs that no gate or reg reads.
+    fn output_wires(&self) -> Vec<Wire> {
+        let mut read = vec![false; self.wires.len()];
+        for gate in &self.gates {
+            read[gate.wire_a.0] = true;
+            read[gate.wire_b.0] = true;
+        }
+        for reg in &self.regs {
+            if let Some(wire_in) = reg.wire_in {
+                read[wire_in.0] = true;
+            }
+        }
+        self.gates
+            .iter()
+            .map(|gate| gate.wire_out)
+            .filter(|wire| !read[wire.0])
+            .collect()
+    }
+
+    pub fn timing_report(&self) -> TimingReport {
+        let reg_ins = self
+            .regs
+            .iter()
+            .filter_map(|reg| reg.wire_in)
+            .collect::<Vec<_>>();
+        let outputs = self.output_wires();
+        let endpoints = reg_ins.iter().chain(&outputs).copied().collect::<Vec<_>>();
+
+        let any = self.arrival(Source::Any);
+        let from_reg = self.arrival(Source::Reg);
+        let from_input = self.arrival(Source::Input);
+        let critical_path = self.longest_path(&any, &endpoints);
+        let period = critical_path.as_ref().map_or(0, |path| path.depth);
+
+        let reg_slack = self
+            .regs
+            .iter()
+            .enumerate()
+            .filter_map(|(reg, value)| {
+                let wire_in = value.wire_in?;
+                let arrival = any.depth[wire_in.0].unwrap_or(0);
+                Some(RegSlack {
+                    reg,
+                    wire_in: self.named(wire_in.0),
+                    arrival,
+                    slack: period - arrival,
+                })
+            })
+            .collect();
+
+        TimingReport {
+            period,
+            reg_to_reg: self.longest_path(&from_reg, &reg_ins),
+            input_to_output: self.longest_path(&from_input, &outputs),
+            modules: self.module_depths(critical_path.as_ref()),
+            critical_path,
+            reg_slack,
+        }
+    }
+
+    fn module_depths(&self, critical_path: Option<&TimingPath>) -> Vec<ModuleDepth> {
+        let scope_of = |wire: Wire| self.wire_scope(wire);
+        let mut modules = (0..self.scope_count())
+            .map(|scope| ModuleDepth {
+                scope: self.scope_path(scope),
+                gate_count: 0,
+                max_depth: 0,
+                critical_gates: 0,
+            })
+            .collect::<Vec<_>>();
+
+        let mut local_depth = vec![0 as LatencyValue; self.wires.len()];
+        for gate in &self.gates {
+            let scope: ScopeId = scope_of(gate.wire_out);
+            let local = |wire: Wire| {
+                if scope_of(wire) == scope {
+                    local_depth[wire.0]
+                } else {
+                    0
+                }
+            };
+            let depth = local(gate.wire_a).max(local(gate.wire_b)) + 1;
+            local_depth[gate.wire_out.0] = depth;
+            modules[scope].gate_count += 1;
+            modules[scope].max_depth = modules[scope].max_depth.max(depth);
+        }
+        for index in critical_path.iter().flat_map(|path| &path.gates) {
+            modules[scope_of(self.gates[*index].wire_out)].critical_gates += 1;
+        }
+
+        modules.retain(|module| module.gate_count > 0);
+        modules.sort_by_key(|m| std::cmp::Reverse((m.critical_gates, m.max_depth)));
+        modules
+    }
+}
+
+pub fn timing_report() -> TimingReport {
+    current().timing_report()
+}
+
+impl Display for TimingPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let names = self
+            .wires
+            .iter()
+            .map(|w| w.name.as_str())
+            .collect::<Vec<_>>();
+        write!(f, "depth {}: {}", self.depth, names.join(" -> "))
+    }
+}
+impl Display for TimingReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "period: {}", self.period)?;
+        let paths = [
+            ("critical path", &self.critical_path),
+            ("reg to reg", &self.reg_to_reg),
+            ("input to output", &self.input_to_output),
+        ];
+        for (name, path) in paths {
+            match path {
+                Some(path) => writeln!(f, "{name}: {path}")?,
+                None => writeln!(f, "{name}: none")?,
+            }
+        }
+        writeln!(f, "modules (critical gates / max depth / gates):")?;
+        for module in &self.modules {
+            let scope = if module.scope.is_empty() {
+                "(root)"
+            } else {
+                &module.scope
+            };
+            writeln!(
+                f,
+                "  {scope}: {} / {} / {}",
+                module.critical_gates, module.max_depth, module.gate_count
+            )?;
+        }
+        writeln!(f, "reg slack:")?;
+        for slack in &self.reg_slack {
+            writeln!(
+                f,
+                "  r{} ({}): arrival {}, slack {}",
+                slack.reg, slack.wire_in.name, slack.arrival, slack.slack
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_timing_report() {
+    use crate::*;
+    clear_all();
+
+    // counter: reg -> adder -> reg, plus a longer input -> output chain
+    let step = input_w::<4>().named("step");
+    let count = reg_w::<4>().named("count");
+    let next = scope("inc", || add_naive(count.out, step).sum);
+    count.set_in(next);
+    let a = input().named("a");
+    let out = scope("chain", || (0..30).fold(a, |wire, _| !wire));
+
+    let report = timing_report();
+    let text = report.to_string();
+    assert!(text.starts_with("period: 30\ncritical path: depth 30: a -> chain.w"));
+    assert!(text.contains("\nreg to reg: depth 18: count[0] -> inc.w"));
+    assert!(text.contains("\ninput to output: depth 30: a -> chain.w"));
+    assert!(text.contains(
+        "\nmodules (critical gates / max depth / gates):\n  chain: 30 / 30 / 30\n  \
+         inc: 0 / 19 / 52\nreg slack:\n"
+    ));
+    let slack = (0..4)
+        .map(|i| {
+            let (name, arrival) = (next.wires[i].name(), 6 + 4 * i);
+            format!(
+                "  r{i} ({name}): arrival {arrival}, slack {}\n",
+                30 - arrival
+            )
+        })
+        .collect::<String>();
+    assert!(text.ends_with(&format!("reg slack:\n{slack}")));
+
+    let reg_to_reg = report.reg_to_reg.clone().unwrap();
+    assert!(reg_to_reg.wires[0].name.starts_with("count["));
+    assert!(reg_to_reg.wires.last().unwrap().name.starts_with("inc."));
+    assert_eq!(reg_to_reg.gates.len() as LatencyValue, reg_to_reg.depth);
+    assert_eq!(reg_to_reg.wires.len(), reg_to_reg.gates.len() + 1);
+
+    let input_to_output = report.input_to_output.clone().unwrap();
+    assert_eq!(30, input_to_output.depth);
+    assert_eq!("a", input_to_output.wires[0].name);
+    assert_eq!(out.0, input_to_output.wires.last().unwrap().index);
+
+    assert_eq!(30, report.period);
+    assert_eq!(report.critical_path.unwrap().depth, report.period);
+    // the reg input with the deepest arrival (the highest sum bit) has the least slack
+    let slack = report.reg_slack.iter().map(|s| s.slack).collect::<Vec<_>>();
+    assert_eq!(4, slack.len());
+    assert_eq!(
+        report.period - reg_to_reg.depth,
+        *slack.iter().min().unwrap()
+    );
+
+    let chain = report.modules.iter().find(|m| m.scope == "chain").unwrap();
+    assert_eq!((30, 30), (chain.gate_count, chain.max_depth));
+    assert_eq!(30, chain.critical_gates);
+    let inc = report.modules.iter().find(|m| m.scope == "inc").unwrap();
+    assert_eq!(reg_to_reg.depth + 1, inc.max_depth); // the carry out is unused
+    assert_eq!(0, inc.critical_gates);
+}
@@ -0,0 +1,475 @@
+use crate::{current, Circuit, ClockDomain, ExecuteSegment, Wire, WIRE_0, WIRE_1};
+use std::fmt::{Display, Formatter};
+
+/// Who has driven a wire through `set()`, recorded for validation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WireSetter {
+    Nobody,
+    Testbench,
+    External(usize), // index in externals
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedWire {
+    pub index: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetlistIssue {
+    /// `Reg::set_in` was never called, `clock_tick` would always read 0.
+    RegWithoutInput { reg: usize, wire_out: NamedWire },
+    /// An `input()` wire read by gates or regs that has never been set.
+    UndrivenInput { wire: NamedWire },
+    /// A gate reads a wire that is only driven later in the execute segments.
+    ReadBeforeDriven {
+        gate_out: NamedWire,
+        wire: NamedWire,
+    },
+    /// Gates that depend on each other without a reg in between.
+    CombinationalLoop { wires: Vec<NamedWire> },
+    /// A reg reads regs of another clock domain through logic instead of a `synchronizer()`.
+    UnsynchronizedCrossing {
+        reg: usize,
+        wire_out: NamedWire,
+        from_domain: String,
+        to_domain: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetlistError {
+    pub issues: Vec<NetlistIssue>,
+}
+
+impl Display for NetlistIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetlistIssue::RegWithoutInput { reg, wire_out } => {
+                write!(f, "reg {reg} ({}) has no input", wire_out.name)
+            }
+            NetlistIssue::UndrivenInput { wire } => {
+                write!(f, "input {} is never set", wire.name)
+            }
+            NetlistIssue::ReadBeforeDriven { gate_out, wire } => write!(
+                f,
+                "gate {} reads {} before it is driven",
+                gate_out.name, wire.name
+            ),
+            NetlistIssue::CombinationalLoop { wires } => {
+                let names = wires.iter().map(|w| w.name.as_str()).collect::<Vec<_>>();
+                write!(f, "combinational loop {}", names.join(" -> "))
+            }
+            NetlistIssue::UnsynchronizedCrossing {
+                reg,
+                wire_out,
+                from_domain,
+                to_domain,
+            } => write!(
+                f,
+                "reg {reg} ({}) in clock domain {to_domain} reads {from_domain} without a synchronizer",
+                wire_out.name
+            ),
+        }
+    }
+}
+impl Display for NetlistError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} netlist issue(s):", self.issues.len())?;
+        for issue in &self.issues {
+            writeln!(f, "  {issue}")?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for NetlistError {}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Driver {
+    Const,
+    Input,
+    Gate(usize),
+    Reg,
+}
+
+impl Circuit {
+    pub(crate) fn named(&self, index: usize) -> NamedWire {
+        NamedWire {
+            index,
+            name: self.wire_name(Wire(index)),
+        }
+    }
+
+    /// Check the netlist for mistakes the simulation would silently accept.
+    ///
+    /// Externals are only known to drive the wires they have `set()`, so call this after the
+    /// testbench has set its inputs and at least one `simulate()` has run.
+    pub fn validate(&self) -> Result<(), NetlistError> {
+        let wire_count = self.wires.len();
+        let mut drivers = vec![Driver::Input; wire_count];
+        drivers[WIRE_0] = Driver::Const;
+        drivers[WIRE_1] = Driver::Const;
+        for (index, gate) in self.gates.iter().enumerate() {
+            drivers[gate.wire_out.0] = Driver::Gate(index);
+        }
+        for reg in &self.regs {
+            drivers[reg.wire_out.0] = Driver::Reg;
+        }
+        let set_by = |wire: usize| self.set_by.get(wire).copied().unwrap_or(WireSetter::Nobody);
+
+        // position of each gate and external in the execute order
+        let mut gate_segment = vec![0; self.gates.len()];
+        let mut external_segment = vec![0; self.externals.len()];
+        for (segment_index, segment) in self.execute_segments.iter().enumerate() {
+            match segment {
+                ExecuteSegment::Gates(range) => gate_segment[range.clone()].fill(segment_index),
+                ExecuteSegment::Externals(range) => {
+                    external_segment[range.clone()].fill(segment_index)
+                }
+            }
+        }
+
+        let mut issues = vec![];
+
+        for (index, reg) in self.regs.iter().enumerate() {
+            if reg.wire_in.is_none() {
+                issues.push(NetlistIssue::RegWithoutInput {
+                    reg: index,
+                    wire_out: self.named(reg.wire_out.0),
+                });
+            }
+        }
+
+        let mut read = vec![false; wire_count];
+        for gate in &self.gates {
+            read[gate.wire_a.0] = true;
+            read[gate.wire_b.0] = true;
+        }
+        for reg in &self.regs {
+            for wire in reg.wire_in.into_iter().chain(reg.enable) {
+                read[wire.0] = true;
+            }
+        }
+        if let Some(reset) = self.reset {
+            read[reset.0] = true;
+        }
+        for wire in 0..wire_count {
+            if read[wire] && drivers[wire] == Driver::Input && set_by(wire) == WireSetter::Nobody {
+                issues.push(NetlistIssue::UndrivenInput {
+                    wire: self.named(wire),
+                });
+            }
+        }
+
+        // a synchronizer starts with a reg loading the other domain's reg directly, any logic in
+        // between can glitch while the other clock changes it
+        if self.clock_domain_count() > 1 {
+            let mut domains = vec![0u64; wire_count]; // domains of the regs each wire depends on
+            for reg in &self.regs {
+                domains[reg.wire_out.0] |= 1 << reg.domain.0;
+            }
+            for gate in &self.gates {
+                domains[gate.wire_out.0] = domains[gate.wire_a.0] | domains[gate.wire_b.0];
+            }
+            for (index, reg) in self.regs.iter().enumerate() {
+                for wire in reg.wire_in.into_iter().chain(reg.enable) {
+                    let foreign = domains[wire.0] & !(1 << reg.domain.0);
+                    if foreign != 0 && drivers[wire.0] != Driver::Reg {
+                        let from = ClockDomain(foreign.trailing_zeros() as usize);
+                        issues.push(NetlistIssue::UnsynchronizedCrossing {
+                            reg: index,
+                            wire_out: self.named(reg.wire_out.0),
+                            from_domain: self.clock_domain_name(from).to_string(),
+                            to_domain: self.clock_domain_name(reg.domain).to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut back_edges = false;
+        for (index, gate) in self.gates.iter().enumerate() {
+            let mut inputs = vec![gate.wire_a.0];
+            if gate.wire_b.0 != gate.wire_a.0 {
+                inputs.push(gate.wire_b.0);
+            }
+            for wire in inputs {
+                let driven_later = match (drivers[wire], set_by(wire)) {
+                    (Driver::Gate(driver), _) => driver >= index,
+                    (Driver::Input, WireSetter::External(external)) => {
+                        external_segment[external] > gate_segment[index]
+                    }
+                    _ => false,
+                };
+                if driven_later {
+                    back_edges |= matches!(drivers[wire], Driver::Gate(_));
+                    issues.push(NetlistIssue::ReadBeforeDriven {
+                        gate_out: self.named(gate.wire_out.0),
+                        wire: self.named(wire),
+                    });
+                }
+            }
+        }
+
+        // without any gate reading a later gate, the gate order is already topological
+        if back_edges {
+            for cycle in self.gate_cycles(&drivers) {
+                issues.push(NetlistIssue::CombinationalLoop {
+                    wires: cycle
+                        .into_iter()
+                        .map(|gate| self.named(self.gates[gate].wire_out.0))
+                        .collect(),
+                });
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(NetlistError { issues })
+        }
+    }
+
+    /// Validate, then forbid any further change to the netlist.
+    pub fn freeze(&mut self) -> Result<(), NetlistError> {
+        self.validate()?;
+        self.frozen = true;
+        Ok(())
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Strongly connected gate groups with a cycle, by Tarjan's algorithm.
+    fn gate_cycles(&self, drivers: &[Driver]) -> Vec<Vec<usize>> {
+        let gate_count = self.gates.len();
+        let fanin = |gate: usize| {
+            let g = &self.gates[gate];
+            [g.wire_a.0, g.wire_b.0].map(|wire| match drivers[wire] {
+                Driver::Gate(driver) => Some(driver),
+                _ => None,
+            })
+        };
+
+        const UNVISITED: usize = usize::MAX;
+        let mut order = vec![UNVISITED; gate_count];
+        let mut low = vec![0; gate_count];
+        let mut on_stack = vec![false; gate_count];
+        let mut stack = vec![];
+        let mut next_order = 0;
+        let mut cycles = vec![];
+
+        for root in 0..gate_count {
+            if order[root] != UNVISITED {
+                continue;
+            }
+            // (gate, next fanin slot to visit)
+            let mut work = vec![(root, 0)];
+            while let Some(&mut (gate, ref mut slot)) = work.last_mut() {
+                if *slot == 0 {
+                    order[gate] = next_order;
+                    low[gate] = next_order;
+                    next_order += 1;
+                    stack.push(gate);
+                    on_stack[gate] = true;
+                }
+                if *slot < 2 {
+                    let next = fanin(gate)[*slot];
+                    *slot += 1;
+                    match next {
+                        Some(next) if order[next] == UNVISITED => work.push((next, 0)),
+                        Some(next) if on_stack[next] => low[gate] = low[gate].min(order[next]),
+                        _ => {}
+                    }
+                    continue;
+                }
+                work.pop();
+                if let Some(&(parent, _)) = work.last() {
+                    low[parent] = low[parent].min(low[gate]);
+                }
+                if low[gate] == order[gate] {
+                    let mut component = vec![];
+                    loop {
+                        let g = stack.pop().unwrap();
+                        on_stack[g] = false;
+                        component.push(g);
+                        if g == gate {
+                            break;
+                        }
+                    }
+                    let self_loop = fanin(gate).contains(&Some(gate));
+                    if component.len() > 1 || self_loop {
+                        component.sort();
+                        cycles.push(component);
+                    }
+                }
+            }
+        }
+        cycles
+    }
+}
+
+pub fn validate() -> Result<(), NetlistError> {
+    current().validate()
+}
+
+pub fn freeze() -> Result<(), NetlistError> {
+    current().freeze()
+}
+
+#[test]
+fn test_validate() {
+    use crate::*;
+    clear_all();
+
+    let a = input();
+    let b = input();
+    let r0 = reg();
+    let r1 = reg();
+    r0.set_in(a & b);
+    a.set(1);
+    simulate();
+
+    let error = validate().unwrap_err();
+    let r1_out = r1.out().0;
+    assert_eq!(
+        format!(
+            "2 netlist issue(s):\n  reg 1 (w{r1_out}) has no input\n  input w{} is never set\n",
+            b.0
+        ),
+        error.to_string()
+    );
+    assert_eq!(
+        error.issues,
+        vec![
+            NetlistIssue::RegWithoutInput {
+                reg: 1,
+                wire_out: NamedWire {
+                    index: r1_out,
+                    name: format!("w{r1_out}"),
+                },
+            },
+            NetlistIssue::UndrivenInput {
+                wire: NamedWire {
+                    index: b.0,
+                    name: format!("w{}", b.0),
+                },
+            },
+        ]
+    );
+
+    b.set(0);
+    r1.set_in(a);
+    assert_eq!(Ok(()), freeze());
+    assert!(std::panic::catch_unwind(input).is_err());
+
+    // drivers are no longer recorded once frozen
+    let recorded = current().set_by.clone();
+    r1.out().set(1);
+    simulate();
+    assert_eq!(recorded, current().set_by);
+}
+
+#[test]
+fn test_validate_read_before_driven() {
+    use crate::*;
+    clear_all();
+
+    struct Copy(Wire, Wire);
+    impl External for Copy {
+        fn execute(&mut self, ctx: &mut ExternalContext) {
+            ctx.set(self.1, ctx.get(self.0));
+        }
+    }
+
+    let a = input();
+    let late = input();
+    let out = !late; // reads `late` one segment before the external writes it
+    external(Copy(a, late));
+    a.set(1);
+    simulate();
+
+    let error = validate().unwrap_err();
+    assert_eq!(
+        error.issues,
+        vec![NetlistIssue::ReadBeforeDriven {
+            gate_out: NamedWire {
+                index: out.0,
+                name: format!("w{}", out.0),
+            },
+            wire: NamedWire {
+                index: late.0,
+                name: format!("w{}", late.0),
+            },
+        }]
+    );
+}
+
+#[test]
+fn test_validate_combinational_loop() {
+    use crate::*;
+    clear_all();
+
+    let a = input();
+    let b = nand(a, a);
+    let c = nand(b, b);
+    let d = nand(c, c);
+    // rewire b to read d, not possible through nand()
+    current().gates[0].wire_a = d;
+    a.set(0);
+
+    let error = validate().unwrap_err();
+    let loops = error
+        .issues
+        .into_iter()
+        .filter_map(|issue| match issue {
+            NetlistIssue::CombinationalLoop { wires } => {
+                Some(wires.into_iter().map(|w| w.index).collect::<Vec<_>>())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(loops, vec![vec![b.0, c.0, d.0]]);
+}
+
+#[test]
+fn test_validate_clock_crossing() {
+    use crate::*;
+    clear_all();
+
+    let device = add_clock_domain("device", 1, 4);
+    let valid = reg().named("valid");
+    let data = reg().named("data");
+    valid.set_in(!valid.out());
+    data.set_in(!data.out());
+    let synced = synchronizer(data.out(), device);
+    let latched = clock_domain(device, || {
+        let latched = reg().named("latched");
+        latched.set_in(data.out() & valid.out() | synced);
+        latched
+    });
+    simulate();
+
+    let error = validate().unwrap_err();
+    assert_eq!(
+        format!(
+            "1 netlist issue(s):\n  reg {} (latched) in clock domain device reads clk without a \
+             synchronizer\n",
+            latched.0
+        ),
+        error.to_string()
+    );
+    assert_eq!(
+        error.issues,
+        vec![NetlistIssue::UnsynchronizedCrossing {
+            reg: latched.0,
+            wire_out: NamedWire {
+                index: latched.out().0,
+                name: "latched".to_string(),
+            },
+            from_domain: "clk".to_string(),
+            to_domain: "device".to_string(),
+        }]
+    );
+}
@@ -1,4 +1,4 @@
-use crate::{input, input_const, mux2_w, reg, LatencyValue, Reg, Wire, WireValue};
+use crate::{input, input_const, mux2_w, reg, reg_with_init, LatencyValue, Reg, Wire, WireValue};
 
 pub enum Assert<const CHECK: bool> {}
 
@@ -85,15 +85,27 @@ impl<const F: usize> Wires<F> {
 pub trait WiresU8 {
     fn set_u8(&self, value: u8);
     fn get_u8(&self) -> u8;
+    fn try_get_u8(&self) -> Option<u8>;
+}
+
+pub trait WiresU64 {
+    fn set_u64(&self, value: u64);
+    fn get_u64(&self) -> u64;
+    fn try_get_u64(&self) -> Option<u64>;
 }
 
 impl<const W: usize> std::fmt::Debug for Wires<W>
 where
-    Assert<{ W <= 8 }>: IsTrue,
+    Assert<{ W <= 64 }>: IsTrue,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let v = self.get_u8();
-        f.write_str(&format!("{v}({v:b})"))
+        match self.try_get_u64() {
+            Some(v) => f.write_str(&format!("{v}({v:b})")),
+            None => {
+                let bits = self.wires.iter().rev().map(|w| format!("{w:?}"));
+                f.write_str(&format!("?({})", bits.collect::<String>()))
+            }
+        }
     }
 }
 
@@ -108,6 +120,10 @@ where
     fn get_u8(&self) -> u8 {
         self.get_u8()
     }
+
+    fn try_get_u8(&self) -> Option<u8> {
+        self.try_get_u8()
+    }
 }
 
 impl<const W: usize> Wires<W>
@@ -133,15 +149,70 @@ where
         self.wires
             .iter()
             .enumerate()
-            .map(|(i, wire)| ((1 << i) * wire.get()) as WireValue)
+            .map(|(i, wire)| {
+                let value = wire.get();
+                assert!(value <= 1, "Unknown (X/Z) bit, use try_get_u8()!");
+                ((1 << i) * value) as WireValue
+            })
             .reduce(|a, b| a + b)
             .unwrap()
     }
 }
 
+impl<const W: usize> WiresU64 for Wires<W>
+where
+    Assert<{ W <= 64 }>: IsTrue,
+{
+    fn set_u64(&self, value: u64) {
+        self.set_u64(value);
+    }
+
+    fn get_u64(&self) -> u64 {
+        self.get_u64()
+    }
+
+    fn try_get_u64(&self) -> Option<u64> {
+        self.try_get_u64()
+    }
+}
+
+impl<const W: usize> Wires<W>
+where
+    Assert<{ W <= 64 }>: IsTrue,
+{
+    pub fn parse_u64(value: u64) -> Wires<W> {
+        Wires::<W> {
+            wires: std::array::from_fn(|i| input_const(((value >> i) & 1) as WireValue)),
+        }
+    }
+
+    /// Bits above `W` are dropped, so a negative `i64` cast to `u64` sets its two's complement.
+    pub fn set_u64(&self, value: u64) {
+        for i in 0..W {
+            self.wires[i].set(((value >> i) & 1) as WireValue);
+        }
+    }
+
+    pub fn get_u64(&self) -> u64 {
+        self.wires.iter().enumerate().fold(0, |result, (i, wire)| {
+            let value = wire.get();
+            assert!(value <= 1, "Unknown (X/Z) bit, use try_get_u64()!");
+            result | ((value as u64) << i)
+        })
+    }
+
+    /// The value as two's complement, bit `W - 1` is the sign.
+    pub fn get_i64(&self) -> i64 {
+        match W {
+            0 => 0,
+            _ => ((self.get_u64() << (64 - W)) as i64) >> (64 - W),
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct Regs<const W: usize> {
-    regs: [Reg; W],
+    pub(crate) regs: [Reg; W],
     pub out: Wires<W>,
 }
 impl<const W: usize> Regs<W> {
@@ -154,6 +225,13 @@ impl<const W: usize> Regs<W> {
             self.regs[i].set_in(wires.wires[i]);
         }
     }
+
+    /// Every reg keeps its value while `enable` is 0.
+    pub fn set_enable(&self, enable: Wire) {
+        for reg in self.regs {
+            reg.set_enable(enable);
+        }
+    }
 }
 pub fn reg_w<const W: usize>() -> Regs<W> {
     let mut regs: [Reg; W] = [Reg(0); W];
@@ -168,6 +246,20 @@ pub fn reg_w<const W: usize>() -> Regs<W> {
     }
 }
 
+/// Regs starting at `value`, and loading it again on reset.
+pub fn reg_w_with_init<const W: usize>(value: u8) -> Regs<W>
+where
+    Assert<{ W <= 8 }>: IsTrue,
+{
+    let regs: [Reg; W] = std::array::from_fn(|i| reg_with_init((value >> i) & 1));
+    Regs::<W> {
+        regs,
+        out: Wires {
+            wires: regs.map(|reg| reg.out()),
+        },
+    }
+}
+
 pub fn flipflop_w<const W: usize>(data: Wires<W>, write_enabled: Wire) -> Wires<W> {
     let r = reg_w();
     r.set_in(mux2_w(r.out, data, write_enabled));
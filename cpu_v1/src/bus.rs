@@ -1,6 +1,6 @@
 use crate::devices::{DeviceReadResult, Devices};
 use crate::{CpuComponent, CpuComponentEmu};
-use digital_design_code::{input_w, select, Wire, Wires};
+use digital_design_code::{input_w, select, ExternalContext, ExternalPorts, Wire, Wires};
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -47,25 +47,33 @@ impl CpuComponentEmu<CpuBus> for CpuBusEmu {
             bus_addr1_next,
         }
     }
-    fn execute(input: &CpuBusInput, output: &CpuBusOutput) {
-        let bus_addr0_write = input.bus_addr0_write.get() > 0;
-        let bus_addr1_write = input.bus_addr1_write.get() > 0;
+    fn save_state(input: &CpuBusInput) -> Option<Vec<u8>> {
+        Some(input.devices.borrow().save_state())
+    }
+    fn restore_state(input: &CpuBusInput, state: &[u8]) {
+        input.devices.borrow_mut().restore_state(state);
+    }
+    fn execute(ctx: &mut ExternalContext, input: &CpuBusInput, output: &CpuBusOutput) {
+        let bus_addr0_write = ctx.get(input.bus_addr0_write) > 0;
+        let bus_addr1_write = ctx.get(input.bus_addr1_write) > 0;
         let bus_addr0_src = select(bus_addr0_write, input.reg0_data, input.bus_addr0);
         let bus_addr1_src = select(bus_addr1_write, input.reg0_data, input.bus_addr1);
-        output.bus_addr0_next.set_u8(bus_addr0_src.get_u8());
-        output.bus_addr1_next.set_u8(bus_addr1_src.get_u8());
+        let bus_addr0_next = ctx.get_u8(&bus_addr0_src.wires);
+        let bus_addr1_next = ctx.get_u8(&bus_addr1_src.wires);
+        ctx.set_u8(&output.bus_addr0_next.wires, bus_addr0_next);
+        ctx.set_u8(&output.bus_addr1_next.wires, bus_addr1_next);
 
-        let bus_enable = input.bus_enable.get() > 0;
-        let reg0 = input.reg0_data.get_u8();
-        let reg1 = input.reg1_data.get_u8();
-        let imm = input.imm.get_u8(); // high 1 bit -> bus0 or bus1, low 3 bit -> opcode
+        let bus_enable = ctx.get(input.bus_enable) > 0;
+        let reg0 = ctx.get_u8(&input.reg0_data.wires);
+        let reg1 = ctx.get_u8(&input.reg1_data.wires);
+        let imm = ctx.get_u8(&input.imm.wires); // high 1 bit -> bus0 or bus1, low 3 bit -> opcode
 
         let bus0_enable = (imm & (0b1000)) == 0;
         let bus1_enable = (imm & (0b1000)) > 0;
         let bus_opcode = imm & 0b0111;
 
-        let bus_addr0 = input.bus_addr0.get_u8() * (bus0_enable as u8);
-        let bus_addr1 = input.bus_addr1.get_u8() * (bus1_enable as u8);
+        let bus_addr0 = ctx.get_u8(&input.bus_addr0.wires) * (bus0_enable as u8);
+        let bus_addr1 = ctx.get_u8(&input.bus_addr1.wires) * (bus1_enable as u8);
         let bus_addr = bus_addr0 | bus_addr1;
 
         let bus_out: u8;
@@ -86,20 +94,38 @@ impl CpuComponentEmu<CpuBus> for CpuBusEmu {
             bus_out_latency = 0;
         }
 
-        let latency1 = input.bus_enable.get_latency();
+        let latency1 = ctx.get_latency(input.bus_enable);
         let latency2 = input
             .reg0_data
             .wires
             .iter()
-            .map(|w| w.get_latency())
+            .map(|w| ctx.get_latency(*w))
             .max()
             .unwrap();
         let latency = latency1.max(latency2) + bus_out_latency;
-        output.bus_out.set_u8(bus_out);
+        ctx.set_u8(&output.bus_out.wires, bus_out);
         output
             .bus_out
             .wires
             .iter()
-            .for_each(|w| w.set_latency(latency));
+            .for_each(|w| ctx.set_latency(*w, latency));
+    }
+    fn export_ports(input: &CpuBusInput, output: &CpuBusOutput) -> Option<ExternalPorts> {
+        // devices keep state of their own
+        let mut ports = ExternalPorts::new("cpu_bus");
+        ports
+            .clocked()
+            .input_wire("bus_addr0_write", input.bus_addr0_write)
+            .input_wire("bus_addr1_write", input.bus_addr1_write)
+            .input_wire("bus_enable", input.bus_enable)
+            .input_wires("bus_addr0", &input.bus_addr0.wires)
+            .input_wires("bus_addr1", &input.bus_addr1.wires)
+            .input_wires("reg0_data", &input.reg0_data.wires)
+            .input_wires("reg1_data", &input.reg1_data.wires)
+            .input_wires("imm", &input.imm.wires)
+            .output_wires("bus_out", &output.bus_out.wires)
+            .output_wires("bus_addr0_next", &output.bus_addr0_next.wires)
+            .output_wires("bus_addr1_next", &output.bus_addr1_next.wires);
+        Some(ports)
     }
 }
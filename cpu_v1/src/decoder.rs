@@ -1,5 +1,7 @@
 use crate::{CpuComponent, CpuComponentEmu};
-use digital_design_code::{input, input_w, mux2_w, unflatten2, unflatten3, Wire, Wires};
+use digital_design_code::{
+    input, input_w, mux2_w, unflatten2, unflatten3, ExternalContext, ExternalPorts, Wire, Wires,
+};
 
 #[derive(Debug, Clone)]
 pub struct CpuDecoderInput {
@@ -262,9 +264,9 @@ impl CpuComponentEmu<CpuDecoder> for CpuDecoderEmu {
 
         output
     }
-    fn execute(input: &CpuDecoderInput, output: &CpuDecoderOutput) {
+    fn execute(ctx: &mut ExternalContext, input: &CpuDecoderInput, output: &CpuDecoderOutput) {
         use crate::isa::*;
-        let inst = input.inst.get_u8();
+        let inst = ctx.get_u8(&input.inst.wires);
 
         let reg0_bits: u8 = inst & 0b00000011;
         let reg1_bits: u8 = (inst & 0b00001100) >> 2;
@@ -482,22 +484,44 @@ impl CpuComponentEmu<CpuDecoder> for CpuDecoderEmu {
             unreachable!("unknown instruction")
         }
 
-        output.imm.set_u8(imm);
-        output.reg0_addr.set_u8(reg0_addr);
-        output.reg1_addr.set_u8(reg1_addr);
-        output.reg0_write_enable.set(reg0_write_enable);
-        output.reg0_write_select.set_u8(reg0_write_select);
-        output.alu_op.set_u8(alu_op);
-        output.alu0_select.set_u8(alu0_select);
-        output.alu1_select.set_u8(alu1_select);
-        output.mem_addr_select.set_u8(mem_addr_select);
-        output.mem_write_enable.set(mem_write_enable);
-        output.mem_page_write_enable.set(mem_page_write_enable);
-        output.jmp_op.set_u8(jmp_op);
-        output.jmp_src_select.set_u8(jmp_src_select);
-        output.bus_enable.set(bus_enable);
-        output.bus_addr0_write.set(bus_addr0_write);
-        output.bus_addr1_write.set(bus_addr1_write);
+        ctx.set_u8(&output.imm.wires, imm);
+        ctx.set_u8(&output.reg0_addr.wires, reg0_addr);
+        ctx.set_u8(&output.reg1_addr.wires, reg1_addr);
+        ctx.set(output.reg0_write_enable, reg0_write_enable);
+        ctx.set_u8(&output.reg0_write_select.wires, reg0_write_select);
+        ctx.set_u8(&output.alu_op.wires, alu_op);
+        ctx.set_u8(&output.alu0_select.wires, alu0_select);
+        ctx.set_u8(&output.alu1_select.wires, alu1_select);
+        ctx.set_u8(&output.mem_addr_select.wires, mem_addr_select);
+        ctx.set(output.mem_write_enable, mem_write_enable);
+        ctx.set(output.mem_page_write_enable, mem_page_write_enable);
+        ctx.set_u8(&output.jmp_op.wires, jmp_op);
+        ctx.set_u8(&output.jmp_src_select.wires, jmp_src_select);
+        ctx.set(output.bus_enable, bus_enable);
+        ctx.set(output.bus_addr0_write, bus_addr0_write);
+        ctx.set(output.bus_addr1_write, bus_addr1_write);
+    }
+    fn export_ports(input: &CpuDecoderInput, output: &CpuDecoderOutput) -> Option<ExternalPorts> {
+        let mut ports = ExternalPorts::new("cpu_decoder");
+        ports
+            .input_wires("inst", &input.inst.wires)
+            .output_wires("imm", &output.imm.wires)
+            .output_wires("reg0_addr", &output.reg0_addr.wires)
+            .output_wires("reg1_addr", &output.reg1_addr.wires)
+            .output_wire("reg0_write_enable", output.reg0_write_enable)
+            .output_wires("reg0_write_select", &output.reg0_write_select.wires)
+            .output_wires("alu_op", &output.alu_op.wires)
+            .output_wires("alu0_select", &output.alu0_select.wires)
+            .output_wires("alu1_select", &output.alu1_select.wires)
+            .output_wires("mem_addr_select", &output.mem_addr_select.wires)
+            .output_wire("mem_write_enable", output.mem_write_enable)
+            .output_wire("mem_page_write_enable", output.mem_page_write_enable)
+            .output_wires("jmp_op", &output.jmp_op.wires)
+            .output_wires("jmp_src_select", &output.jmp_src_select.wires)
+            .output_wire("bus_enable", output.bus_enable)
+            .output_wire("bus_addr0_write", output.bus_addr0_write)
+            .output_wire("bus_addr1_write", output.bus_addr1_write);
+        Some(ports)
     }
 }
 
@@ -68,6 +68,12 @@ impl Device for DeviceMath {
             self_latency: 3,
         }
     }
+    fn save_state(&self) -> Option<Vec<u8>> {
+        Some(self.value.clone())
+    }
+    fn restore_state(&mut self, state: &[u8]) {
+        self.value = state.to_vec();
+    }
 }
 
 #[test]
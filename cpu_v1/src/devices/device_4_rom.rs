@@ -69,6 +69,12 @@ impl Device for DeviceRom {
             self_latency: 4,
         }
     }
+    fn save_state(&self) -> Option<Vec<u8>> {
+        Some((self.cursor as u64).to_le_bytes().to_vec())
+    }
+    fn restore_state(&mut self, state: &[u8]) {
+        self.cursor = u64::from_le_bytes(state.try_into().unwrap()) as usize;
+    }
 }
 
 #[test]
@@ -76,6 +82,7 @@ fn test_device_rom() {
     use crate::devices::device_0_terminal::DeviceTerminalOp;
     use crate::devices::test_device;
     use crate::isa::Instruction::*;
+    let _lock = digital_design_code::global_lock();
 
     set_rom_content(&[0x1, 0x2, 0x3, 0x4]);
 
@@ -104,6 +111,7 @@ fn test_copy_rom_to_mem() {
     use crate::devices::*;
     use crate::isa::Instruction::*;
     use crate::isa::RegisterIndex::*;
+    let _lock = digital_design_code::global_lock();
 
     let mut rom = [0; 256];
     for i in 0..256 {
@@ -25,6 +25,11 @@ pub enum DeviceType {
 pub trait Device: 'static {
     fn device_type(&self) -> DeviceType;
     fn exec(&mut self, opcode3: u8, reg0: u8, reg1: u8) -> DeviceReadResult;
+    /// Opt-in for snapshots, see `External::save_state`.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+    fn restore_state(&mut self, _state: &[u8]) {}
 }
 #[derive(Default)]
 pub struct DeviceReadResult {
@@ -104,6 +109,34 @@ impl Devices {
             d.exec(bus_opcode3, reg0, reg1)
         })
     }
+
+    /// (slot, length, state) of each device with state.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for (slot, device) in self.devices.iter().enumerate() {
+            if let Some(state) = device.as_ref().and_then(|d| d.save_state()) {
+                bytes.push(slot as u8);
+                bytes.extend_from_slice(&(state.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(&state);
+            }
+        }
+        bytes
+    }
+    pub fn restore_state(&mut self, mut bytes: &[u8]) {
+        while let [slot, l0, l1, l2, l3, rest @ ..] = bytes {
+            let slot = *slot as usize;
+            let len = u32::from_le_bytes([*l0, *l1, *l2, *l3]) as usize;
+            if self.devices[slot].is_none() {
+                if let Some(generator) = self.generators[slot].take() {
+                    generator(self);
+                }
+            }
+            if let Some(device) = self.devices[slot].as_mut() {
+                device.restore_state(&rest[..len]);
+            }
+            bytes = &rest[len..];
+        }
+    }
 }
 
 #[cfg(test)]
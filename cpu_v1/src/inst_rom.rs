@@ -1,5 +1,5 @@
 use crate::{CpuComponent, CpuComponentEmu};
-use digital_design_code::{input_w, mux256_w, Wires};
+use digital_design_code::{input_w, mux256_w, ExternalContext, ExternalPorts, Wires};
 
 #[derive(Clone)]
 pub struct CpuInstInput {
@@ -29,9 +29,18 @@ impl CpuComponentEmu<CpuInstRom> for CpuInstRomEmu {
         output.inst.set_latency(i.pc.get_max_latency() + 10); //TODO accurate latency
         output
     }
-    fn execute(input: &CpuInstInput, output: &CpuInstOutput) {
-        let pc = input.pc.get_u8();
-        let inst = input.inst[pc as usize].get_u8();
-        output.inst.set_u8(inst);
+    fn execute(ctx: &mut ExternalContext, input: &CpuInstInput, output: &CpuInstOutput) {
+        let pc = ctx.get_u8(&input.pc.wires);
+        let inst = ctx.get_u8(&input.inst[pc as usize].wires);
+        ctx.set_u8(&output.inst.wires, inst);
+    }
+    fn export_ports(input: &CpuInstInput, output: &CpuInstOutput) -> Option<ExternalPorts> {
+        let inst = input.inst.iter().flat_map(|w| w.wires).collect::<Vec<_>>();
+        let mut ports = ExternalPorts::new("cpu_inst_rom");
+        ports
+            .input_wires("inst", &inst)
+            .input_wires("pc", &input.pc.wires)
+            .output_wires("inst_out", &output.inst.wires);
+        Some(ports)
     }
 }
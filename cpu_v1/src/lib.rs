@@ -33,7 +33,10 @@ mod programs;
 
 extern crate digital_design_code;
 use digital_design_code::get_statistics;
-pub(crate) use digital_design_code::{clear_all, external, reg, reg_w, External, Reg, Regs, Wires};
+pub(crate) use digital_design_code::{
+    clear_all, external, is_known, reg, reg_w, reset_signal, scope, ExportPort, External,
+    ExternalContext, ExternalPorts, Reg, Regs, Wire, Wires, VALUE_X,
+};
 use std::any::Any;
 use std::cell::RefCell;
 use std::marker::PhantomData;
@@ -52,25 +55,27 @@ struct CpuV1State {
     flag_n: Reg,  // write in CpuV1
     bus_addr0: Regs<4>,
     bus_addr1: Regs<4>,
+    reset: Wire, // all regs back to 0 on the next clock, shared by every state of the circuit
     devices: Rc<RefCell<Devices>>,
 }
 impl CpuV1State {
     fn create(inst_src: [Instruction; 256]) -> Self {
         let inst = inst_src.map(|v| Wires::<8>::parse_u8(v.to_binary()));
-        let regs = [0u8; 4].map(|_| reg_w());
-        let mem = [0u8; 256].map(|_| reg_w());
+        let regs = std::array::from_fn(|i| reg_w().named(&format!("reg{i}")));
+        let mem = std::array::from_fn(|i| reg_w().named(&format!("mem{i}")));
         Self {
             inst_src,
             inst,
-            pc: reg_w(),
+            pc: reg_w().named("pc"),
             mem,
-            mem_page: reg_w(),
+            mem_page: reg_w().named("mem_page"),
             reg: regs,
-            flag_p: reg(),
-            flag_nz: reg(),
-            flag_n: reg(),
-            bus_addr0: reg_w(),
-            bus_addr1: reg_w(),
+            flag_p: reg().named("flag_p"),
+            flag_nz: reg().named("flag_nz"),
+            flag_n: reg().named("flag_n"),
+            bus_addr0: reg_w().named("bus_addr0"),
+            bus_addr1: reg_w().named("bus_addr1"),
+            reset: reset_signal().named("reset"),
             devices: Rc::new(RefCell::new(Devices::new())),
         }
     }
@@ -102,12 +107,12 @@ trait CpuV1 {
             inst: state.inst,
             pc: state.pc.out,
         };
-        let inst_rom_out: CpuInstOutput = Self::InstRom::build(&inst_rom_in);
+        let inst_rom_out: CpuInstOutput = scope("inst_rom", || Self::InstRom::build(&inst_rom_in));
         let CpuInstOutput { inst } = inst_rom_out;
 
         // Decoder
         let decoder_in = CpuDecoderInput { inst };
-        let decoder_out: CpuDecoderOutput = Self::Decoder::build(&decoder_in);
+        let decoder_out: CpuDecoderOutput = scope("decoder", || Self::Decoder::build(&decoder_in));
         let CpuDecoderOutput {
             reg0_addr,
             reg1_addr,
@@ -133,7 +138,8 @@ trait CpuV1 {
             reg0_addr,
             reg1_addr,
         };
-        let reg_read_out: CpuRegReadOutput = Self::RegRead::build(&reg_read_in);
+        let reg_read_out: CpuRegReadOutput =
+            scope("reg_read", || Self::RegRead::build(&reg_read_in));
         let CpuRegReadOutput {
             reg0_data,
             reg1_data,
@@ -151,7 +157,7 @@ trait CpuV1 {
             imm,
             devices: state.devices.clone(),
         };
-        let bus_out: CpuBusOutput = Self::Bus::build(&bus_in);
+        let bus_out: CpuBusOutput = scope("bus", || Self::Bus::build(&bus_in));
         let CpuBusOutput {
             bus_out,
             bus_addr0_next,
@@ -169,7 +175,7 @@ trait CpuV1 {
             alu0_select,
             alu1_select,
         };
-        let alu_out = Self::Alu::build(&alu_in);
+        let alu_out = scope("alu", || Self::Alu::build(&alu_in));
         let CpuAluOutput { alu_out } = alu_out;
 
         // Mem
@@ -183,7 +189,7 @@ trait CpuV1 {
             reg1: reg1_data,
             mem_addr_select,
         };
-        let mem_out = Self::Mem::build(&mem_in);
+        let mem_out = scope("mem", || Self::Mem::build(&mem_in));
         let CpuMemOutput {
             mem_out,
             mem_next,
@@ -204,7 +210,7 @@ trait CpuV1 {
             mem_out,
             bus_out,
         };
-        let reg_write_out = Self::RegWrite::build(&reg_write_in);
+        let reg_write_out = scope("reg_write", || Self::RegWrite::build(&reg_write_in));
         let CpuRegWriteOutput { reg0_write_data } = reg_write_out;
 
         // Branch
@@ -219,7 +225,7 @@ trait CpuV1 {
             flag_nz: state.flag_nz.out(),
             flag_n: state.flag_n.out(),
         };
-        let branch_out: CpuBranchOutput = Self::Branch::build(&branch_in);
+        let branch_out: CpuBranchOutput = scope("branch", || Self::Branch::build(&branch_in));
         let CpuBranchOutput {
             pc_offset_enable,
             pc_offset,
@@ -238,7 +244,7 @@ trait CpuV1 {
             jmp_long_enable,
             jmp_long,
         };
-        let next_pc_out: CpuPcOutput = Self::Pc::build(&next_pc_in);
+        let next_pc_out: CpuPcOutput = scope("pc", || Self::Pc::build(&next_pc_in));
 
         // set regs
         state.pc.set_in(next_pc_out.next_pc);
@@ -335,13 +341,29 @@ pub trait CpuComponent: Any {
 
 pub trait CpuComponentEmu<T: CpuComponent>: Sized + Any {
     fn init_output(input: &T::Input) -> T::Output;
-    fn execute(input: &T::Input, output: &T::Output);
+    fn execute(ctx: &mut ExternalContext, input: &T::Input, output: &T::Output);
+    /// State kept outside of wires, for snapshots. Most components have none.
+    fn save_state(_input: &T::Input) -> Option<Vec<u8>> {
+        None
+    }
+    fn restore_state(_input: &T::Input, _state: &[u8]) {}
+    /// Black box standing for the emulation in exports, see `External::export_ports`.
+    fn export_ports(_input: &T::Input, _output: &T::Output) -> Option<ExternalPorts> {
+        None
+    }
     fn build(input: &T::Input) -> T::Output {
         let output = Self::init_output(input);
+        let ports = Self::export_ports(input, &output);
+        let port_wires = |ports: &[ExportPort]| {
+            let wires = ports.iter().flat_map(|port| port.wires().to_vec());
+            wires.collect::<Vec<_>>()
+        };
         let ctx: CpuComponentEmuContext<T, Self> = CpuComponentEmuContext {
             _phantom: Default::default(),
             input: input.clone(),
             output: output.clone(),
+            input_wires: ports.as_ref().map_or(vec![], |p| port_wires(p.inputs())),
+            output_wires: ports.as_ref().map_or(vec![], |p| port_wires(p.outputs())),
         };
         external(ctx);
         output
@@ -351,13 +373,32 @@ struct CpuComponentEmuContext<T: CpuComponent, E: CpuComponentEmu<T>> {
     _phantom: PhantomData<E>,
     input: T::Input,
     output: T::Output,
+    // of `export_ports()`, an unknown input makes every output unknown in four-state simulation
+    input_wires: Vec<Wire>,
+    output_wires: Vec<Wire>,
 }
 impl<T: CpuComponent, E: CpuComponentEmu<T>> External for CpuComponentEmuContext<T, E> {
-    fn execute(&mut self) {
-        E::execute(&self.input, &self.output);
+    fn execute(&mut self, ctx: &mut ExternalContext) {
+        if self
+            .input_wires
+            .iter()
+            .any(|wire| !is_known(ctx.get(*wire)))
+        {
+            for wire in &self.output_wires {
+                ctx.set(*wire, VALUE_X);
+            }
+            return;
+        }
+        E::execute(ctx, &self.input, &self.output);
+    }
+    fn save_state(&self) -> Option<Vec<u8>> {
+        E::save_state(&self.input)
+    }
+    fn restore_state(&mut self, state: &[u8]) {
+        E::restore_state(&self.input, state);
     }
-    fn as_any(&self) -> &dyn Any {
-        self
+    fn export_ports(&self) -> Option<ExternalPorts> {
+        E::export_ports(&self.input, &self.output)
     }
 }
 impl<T: CpuComponent, E: CpuComponentEmu<T>> CpuComponent for CpuComponentEmuContext<T, E> {
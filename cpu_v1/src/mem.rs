@@ -1,6 +1,8 @@
 use crate::decoder::MemAddrSelect;
 use crate::{CpuComponent, CpuComponentEmu};
-use digital_design_code::{decode8, flatten2, input_w, mux2_w, reduce256, Wire, Wires};
+use digital_design_code::{
+    decode8, flatten2, input_w, mux2_w, reduce256, ExternalContext, ExternalPorts, Wire, Wires,
+};
 
 #[derive(Clone)]
 pub struct CpuMemInput {
@@ -80,32 +82,57 @@ impl CpuComponentEmu<CpuMem> for CpuMemEmu {
             .set_latency(i.reg0.get_max_latency() + 1);
         output
     }
-    fn execute(input: &CpuMemInput, output: &CpuMemOutput) {
-        let mem_page = input.mem_page.get_u8();
-        let imm = input.imm.get_u8();
-        let reg0 = input.reg0.get_u8();
-        let reg1 = input.reg1.get_u8();
+    fn execute(ctx: &mut ExternalContext, input: &CpuMemInput, output: &CpuMemOutput) {
+        let mem_page = ctx.get_u8(&input.mem_page.wires);
+        let imm = ctx.get_u8(&input.imm.wires);
+        let reg0 = ctx.get_u8(&input.reg0.wires);
+        let reg1 = ctx.get_u8(&input.reg1.wires);
 
-        let addr_imm = input.mem_addr_select.wires[MemAddrSelect::Imm as usize].get();
-        let addr_reg1 = input.mem_addr_select.wires[MemAddrSelect::Reg1 as usize].get();
+        let addr_imm = ctx.get(input.mem_addr_select.wires[MemAddrSelect::Imm as usize]);
+        let addr_reg1 = ctx.get(input.mem_addr_select.wires[MemAddrSelect::Reg1 as usize]);
 
-        if input.mem_page_write_enable.get() > 0 {
-            output.mem_page_next.set_u8(reg0);
+        if ctx.get(input.mem_page_write_enable) > 0 {
+            ctx.set_u8(&output.mem_page_next.wires, reg0);
         } else {
-            output.mem_page_next.set_u8(mem_page);
+            ctx.set_u8(&output.mem_page_next.wires, mem_page);
         }
 
         let addr_low = imm * addr_imm + reg1 * addr_reg1;
         let addr = addr_low + (mem_page << 4);
 
-        output.mem_out.set_u8(input.mem[addr as usize].get_u8());
+        let mem_out = ctx.get_u8(&input.mem[addr as usize].wires);
+        ctx.set_u8(&output.mem_out.wires, mem_out);
         (0..256).for_each(|i| {
-            output.mem_next[i].set_u8(input.mem[i].get_u8());
+            let mem = ctx.get_u8(&input.mem[i].wires);
+            ctx.set_u8(&output.mem_next[i].wires, mem);
         });
-        if input.mem_write_enable.get() > 0 {
-            output.mem_next[addr as usize].set_u8(reg0);
+        if ctx.get(input.mem_write_enable) > 0 {
+            ctx.set_u8(&output.mem_next[addr as usize].wires, reg0);
         }
     }
+    fn export_ports(input: &CpuMemInput, output: &CpuMemOutput) -> Option<ExternalPorts> {
+        // the memory itself stays in regs, only the addressing is emulated
+        let mem = input.mem.iter().flat_map(|w| w.wires).collect::<Vec<_>>();
+        let mem_next = output
+            .mem_next
+            .iter()
+            .flat_map(|w| w.wires)
+            .collect::<Vec<_>>();
+        let mut ports = ExternalPorts::new("cpu_mem");
+        ports
+            .input_wires("mem", &mem)
+            .input_wires("mem_page", &input.mem_page.wires)
+            .input_wire("mem_page_write_enable", input.mem_page_write_enable)
+            .input_wires("reg0", &input.reg0.wires)
+            .input_wire("mem_write_enable", input.mem_write_enable)
+            .input_wires("imm", &input.imm.wires)
+            .input_wires("reg1", &input.reg1.wires)
+            .input_wires("mem_addr_select", &input.mem_addr_select.wires)
+            .output_wires("mem_out", &output.mem_out.wires)
+            .output_wires("mem_next", &mem_next)
+            .output_wires("mem_page_next", &output.mem_page_next.wires);
+        Some(ports)
+    }
 }
 
 #[test]
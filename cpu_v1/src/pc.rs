@@ -1,6 +1,8 @@
 use super::CpuComponent;
 use crate::CpuComponentEmu;
-use digital_design_code::{add_naive, flatten2, input_w, Wire, Wires};
+use digital_design_code::{
+    add_naive, flatten2, input_w, ExternalContext, ExternalPorts, Wire, Wires,
+};
 
 #[derive(Debug, Clone)]
 pub struct CpuPcInput {
@@ -22,27 +24,38 @@ impl CpuComponentEmu<CpuPc> for CpuPcEmu {
         output.next_pc.set_latency(i.curr_pc.get_max_latency() + 30);
         output
     }
-    fn execute(input: &CpuPcInput, output: &CpuPcOutput) {
+    fn execute(ctx: &mut ExternalContext, input: &CpuPcInput, output: &CpuPcOutput) {
         assert_eq!(
             1,
-            input.jmp_long_enable.get() + input.pc_offset_enable.get()
+            ctx.get(input.jmp_long_enable) + ctx.get(input.pc_offset_enable)
         );
 
-        let curr_pc = input.curr_pc.get_u8();
-        let offset = input.pc_offset.get_u8();
-        let long = input.jmp_long.get_u8();
-        let next_pc = if input.pc_offset_enable.is_one() {
+        let curr_pc = ctx.get_u8(&input.curr_pc.wires);
+        let offset = ctx.get_u8(&input.pc_offset.wires);
+        let long = ctx.get_u8(&input.jmp_long.wires);
+        let next_pc = if ctx.is_one(input.pc_offset_enable) {
             if offset < 8 {
                 curr_pc + offset
             } else {
                 curr_pc + offset - 16
             }
-        } else if input.jmp_long_enable.is_one() {
+        } else if ctx.is_one(input.jmp_long_enable) {
             long * 16
         } else {
             curr_pc + 1
         };
-        output.next_pc.set_u8(next_pc);
+        ctx.set_u8(&output.next_pc.wires, next_pc);
+    }
+    fn export_ports(input: &CpuPcInput, output: &CpuPcOutput) -> Option<ExternalPorts> {
+        let mut ports = ExternalPorts::new("cpu_pc");
+        ports
+            .input_wires("curr_pc", &input.curr_pc.wires)
+            .input_wire("pc_offset_enable", input.pc_offset_enable)
+            .input_wires("pc_offset", &input.pc_offset.wires)
+            .input_wire("jmp_long_enable", input.jmp_long_enable)
+            .input_wires("jmp_long", &input.jmp_long.wires)
+            .output_wires("next_pc", &output.next_pc.wires);
+        Some(ports)
     }
 }
 
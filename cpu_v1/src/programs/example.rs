@@ -1,10 +1,9 @@
 use crate::programs::*;
 use crate::*;
-use digital_design_code::global_lock;
+use digital_design_code::{set_simulation_backend, SimulationBackend};
 
 #[test]
 fn test_fibonacci() {
-    let _lock = global_lock();
     use isa::Instruction::*;
     use isa::RegisterIndex::*;
     test_cpu_with_emu(
@@ -27,7 +26,6 @@ fn test_fibonacci() {
 
 #[test]
 fn test_fibonacci2() {
-    let _lock = global_lock();
     use isa::RegisterIndex::*;
     let mut asm = Assembler::new();
     asm.reg0().load_imm(5);
@@ -43,3 +41,9 @@ fn test_fibonacci2() {
 
     test_cpu_with_emu(asm.finish().as_slice(), 35, print_regs);
 }
+
+#[test]
+fn test_fibonacci_event_driven() {
+    set_simulation_backend(SimulationBackend::EventDriven);
+    test_fibonacci();
+}
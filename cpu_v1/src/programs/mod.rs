@@ -6,9 +6,12 @@ use digital_design_code::{clock_tick, execute_gates};
 mod example;
 mod game_sokoban;
 mod test_alu;
+mod test_fault;
 mod test_jmp;
 mod test_mem;
 mod test_perf;
+mod test_reset;
+mod test_snapshot;
 
 fn print_regs(cycle: u32, state: &CpuV1State) {
     print!(
@@ -0,0 +1,43 @@
+use crate::cpu_v1_build_mix;
+use crate::isa::Instruction;
+use crate::isa::Instruction::*;
+use crate::isa::RegisterIndex::*;
+use digital_design_code::{run_fault_campaign, stuck_at_faults, wire_name, Fault};
+
+#[test]
+fn test_fault_coverage_alu() {
+    let inst = &[
+        load_imm(3),
+        mov((Reg0, Reg1)),
+        load_imm(6),
+        mov((Reg0, Reg2)),
+        add((Reg1, Reg2)),
+        inc(Reg2),
+        xor((Reg2, Reg1)),
+        and((Reg1, Reg2)),
+        or((Reg0, Reg3)),
+        neg(Reg1),
+    ];
+    let mut inst_rom = [Instruction::default(); 256];
+    inst_rom[..inst.len()].copy_from_slice(inst);
+    let (state, _) = cpu_v1_build_mix(inst_rom);
+
+    let faults = stuck_at_faults()
+        .into_iter()
+        .filter(|fault| match fault {
+            Fault::StuckAt { wire, .. } => wire_name(*wire).starts_with("alu."),
+            Fault::BitFlip { .. } => false,
+        })
+        .collect::<Vec<_>>();
+    let observe = state
+        .reg
+        .iter()
+        .flat_map(|reg| reg.out.wires)
+        .chain(state.pc.out.wires)
+        .collect::<Vec<_>>();
+    let report = run_fault_campaign(&faults, inst.len(), &observe, |_| {});
+    println!("{report}");
+    assert!(!faults.is_empty());
+    assert!(report.detected_count() > 0);
+    assert!(report.coverage() < 1.0);
+}
@@ -3,13 +3,155 @@
 use crate::isa::Instruction;
 use crate::isa::Instruction::*;
 use crate::isa::RegisterIndex::*;
-use crate::{CpuV1, CpuV1MixInstance, CpuV1State};
-use digital_design_code::{clear_all, get_statistics, simulate};
+use crate::{CpuV1, CpuV1EmuInstance, CpuV1Instance, CpuV1MixInstance, CpuV1State};
+use digital_design_code::{
+    clear_all, design_report, export_gate_reg, external, get_event_driven_statistics,
+    get_statistics, set_simulation_backend, set_toggle_counting, simulate, timing_report,
+    toggle_report, BlifExporter, ExportModuleInterface, Exporter, Logger, SimulationBackend,
+    VerilogModuleExporter, YosysJsonExporter,
+};
 
 #[test]
 #[ignore]
 fn raw_circuit() {
+    run_circuit(SimulationBackend::Interpreted);
+}
+
+#[test]
+#[ignore]
+fn compiled_circuit() {
+    run_circuit(SimulationBackend::Compiled);
+}
+
+#[test]
+#[ignore]
+fn event_driven_circuit() {
+    run_circuit(SimulationBackend::EventDriven);
+}
+
+#[test]
+fn circuit_timing() {
+    clear_all();
+    let mut state = CpuV1State::create([Instruction::default(); 256]);
+    let _ = CpuV1Instance::build(&mut state);
+
+    let report = timing_report();
+    println!("{report}");
+    assert_eq!(get_statistics().max_latency, report.period);
+    let critical = &report.modules[0];
+    assert!(critical.critical_gates > 0);
+    assert!(!critical.scope.is_empty());
+}
+
+#[test]
+fn circuit_design_report() {
     clear_all();
+    let mut state = CpuV1State::create([Instruction::default(); 256]);
+    let _ = CpuV1Instance::build(&mut state);
+
+    let report = design_report();
+    println!("{report}");
+    let gates: usize = report.components.iter().map(|c| c.gate_count).sum();
+    assert_eq!(get_statistics().gate_count, gates);
+    for scope in ["inst_rom", "decoder", "alu", "reg_write", "pc"] {
+        assert!(report.component(scope).unwrap().gate_count > 0, "{scope}");
+    }
+    assert!(report.to_json().contains("{\"scope\":\"alu\","));
+}
+
+#[test]
+fn circuit_export_mix() {
+    clear_all();
+    let mut state = CpuV1State::create([Instruction::default(); 256]);
+    let _ = CpuV1MixInstance::build(&mut state);
+    external(Logger::from_wire(state.pc.out.wires[0]));
+
+    // emulated components become black boxes, the logger is left out
+    let content = export_gate_reg();
+    let modules = content
+        .externals
+        .iter()
+        .map(|e| e.ports.module_name())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["cpu_inst_rom", "cpu_bus", "cpu_mem"], modules);
+    assert_eq!(get_statistics().gate_count, content.gates.len());
+
+    let mut interface = ExportModuleInterface::default();
+    interface
+        .module_name("cpu_v1")
+        .clk("clk")
+        .input_wire("reset", state.reset)
+        .output_wires("pc", state.pc.out)
+        .output_wires("reg0", state.reg[0].out);
+    let verilog = VerilogModuleExporter::default().export(&interface, &content);
+    assert!(verilog.contains("cpu_mem ext2 (\n"));
+    let blif = BlifExporter::default().export(&interface, &content);
+    assert!(blif.contains(".subckt cpu_bus clk=clk "));
+    assert!(blif.contains("\n.model cpu_inst_rom\n"));
+    let json = YosysJsonExporter::default().export(&interface, &content);
+    assert!(json.contains("\"type\": \"cpu_mem\""));
+}
+
+#[test]
+fn circuit_export_emu() {
+    clear_all();
+    let mut state = CpuV1State::create([Instruction::default(); 256]);
+    let _ = CpuV1EmuInstance::build(&mut state);
+
+    // every emulated component has a black box
+    let content = export_gate_reg();
+    let modules = content
+        .externals
+        .iter()
+        .map(|e| e.ports.module_name())
+        .collect::<Vec<_>>();
+    let expected = [
+        "cpu_inst_rom",
+        "cpu_decoder",
+        "cpu_bus",
+        "cpu_mem",
+        "cpu_pc",
+    ];
+    assert_eq!(expected.to_vec(), modules);
+    let decoder = &content.externals[1].ports;
+    assert_eq!(8, decoder.inputs()[0].wires().len());
+    assert_eq!(16, decoder.outputs().len());
+}
+
+#[test]
+fn circuit_toggles() {
+    // the same count to 12, by inc and by adding a loaded 1
+    let variants: [&[Instruction]; 2] = [
+        &[inc(Reg1), jmp_offset(16 - 1)],
+        &[
+            load_imm(1),
+            add((Reg1, Reg0)),
+            mov((Reg0, Reg1)),
+            jmp_offset(16 - 3),
+        ],
+    ];
+    let reports = variants.map(|inst| {
+        clear_all();
+        let mut inst_rom = [Instruction::default(); 256];
+        inst_rom[..inst.len()].copy_from_slice(inst);
+        let mut state = CpuV1State::create(inst_rom);
+        let _ = CpuV1Instance::build(&mut state);
+        set_toggle_counting(true);
+        while state.reg[1].out.get_u8() != 12 {
+            simulate();
+        }
+        toggle_report(5)
+    });
+    for report in &reports {
+        println!("{report}");
+    }
+    assert!(reports[0].cycles < reports[1].cycles);
+    assert!(reports[0].total < reports[1].total);
+}
+
+fn run_circuit(backend: SimulationBackend) {
+    clear_all();
+    set_simulation_backend(backend);
 
     let mut inst_rom = [Instruction::default(); 256];
     let inst = &[
@@ -39,11 +181,17 @@ fn raw_circuit() {
         simulate();
     }
     let duration = start.elapsed();
-    println!("simulate {CYCLES} cycles: {}ms", duration.as_millis());
+    println!(
+        "simulate {CYCLES} cycles ({backend:?}): {}ms",
+        duration.as_millis()
+    );
     let time_per_cycle = duration.as_secs_f64() / CYCLES as f64;
     println!("{:.0} cycles for 30fps", 1. / 30. / time_per_cycle);
     println!("{:.0} cycles for 60fps", 1. / 60. / time_per_cycle);
 
     let result = get_statistics();
     println!("{:?}", result);
+    if backend == SimulationBackend::EventDriven {
+        println!("{:?}", get_event_driven_statistics());
+    }
 }
@@ -0,0 +1,74 @@
+use crate::isa::Instruction;
+use crate::isa::Instruction::*;
+use crate::isa::RegisterIndex::*;
+use crate::{cpu_v1_build, cpu_v1_build_mix, CpuV1State};
+use digital_design_code::{set_four_state, simulate, unknown_regs};
+
+#[test]
+fn test_reset_rerun() {
+    let inst = &[
+        load_imm(3),       // 0
+        mov((Reg0, Reg1)), // 1
+        inc(Reg2),         // 2
+        inc(Reg2),         // 3
+        load_imm(7),       // 4
+    ];
+    let mut inst_rom = [Instruction::default(); 256];
+    inst_rom[..inst.len()].copy_from_slice(inst);
+    let (state, _) = cpu_v1_build_mix(inst_rom);
+
+    let regs = |state: &CpuV1State| state.reg.map(|r| r.out.get_u8());
+    let run = || {
+        (0..8)
+            .map(|_| {
+                simulate();
+                (state.pc.out.get_u8(), regs(&state))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let first = run();
+    assert_eq!([7, 3, 2, 0], first.last().unwrap().1);
+
+    // one clock with reset held, no rebuild
+    state.reset.set(1);
+    simulate();
+    state.reset.set(0);
+    assert_eq!((0, [0; 4]), (state.pc.out.get_u8(), regs(&state)));
+    assert_eq!(0, state.flag_p.out().get());
+
+    assert_eq!(first, run());
+}
+
+#[test]
+fn test_reset_four_state() {
+    let inst = &[
+        load_imm(3),       // 0
+        mov((Reg0, Reg1)), // 1
+        inc(Reg2),         // 2
+        inc(Reg2),         // 3
+        load_imm(7),       // 4
+    ];
+    let mut inst_rom = [Instruction::default(); 256];
+    inst_rom[..inst.len()].copy_from_slice(inst);
+
+    for build in [cpu_v1_build, cpu_v1_build_mix] {
+        let (state, _) = build(inst_rom);
+        set_four_state(true);
+        assert!(!unknown_regs().is_empty());
+
+        // the reset sequence alone has to initialize every reg
+        state.reset.set(1);
+        simulate();
+        state.reset.set(0);
+        assert!(unknown_regs().is_empty());
+        let regs = |state: &CpuV1State| state.reg.map(|r| r.out.get_u8());
+        assert_eq!((0, [0; 4]), (state.pc.out.get_u8(), regs(&state)));
+
+        for _ in 0..8 {
+            simulate();
+        }
+        assert_eq!([7, 3, 2, 0], regs(&state));
+        set_four_state(false);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::devices::{DeviceMathOpcode, DeviceType};
+use crate::isa::Instruction;
+use crate::isa::Instruction::*;
+use crate::isa::RegisterIndex::*;
+use crate::{cpu_v1_build_mix, CpuV1State};
+use digital_design_code::{restore, simulate, snapshot, Snapshot};
+
+#[test]
+fn test_snapshot_rewind() {
+    let inst = &[
+        load_imm(DeviceType::Math as u8),
+        set_bus_addr0(()),
+        load_imm(0b1010),
+        bus0(DeviceMathOpcode::PushBits01 as u8),
+        bus0(DeviceMathOpcode::Pop as u8), // cycle 5, half of the stack left in the device
+        mov((Reg0, Reg3)),
+        bus0(DeviceMathOpcode::Pop as u8),
+        mov((Reg0, Reg2)),
+        bus0(DeviceMathOpcode::Pop as u8),
+        mov((Reg0, Reg1)),
+        bus0(DeviceMathOpcode::Pop as u8),
+    ];
+    let mut inst_rom = [Instruction::default(); 256];
+    inst_rom[..inst.len()].copy_from_slice(inst);
+    let (state, _) = cpu_v1_build_mix(inst_rom);
+
+    let regs = |state: &CpuV1State| state.reg.map(|r| r.out.get_u8());
+    let run = |cycles: usize| {
+        (0..cycles)
+            .map(|_| {
+                simulate();
+                (state.pc.out.get_u8(), regs(&state))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    run(5);
+    let saved = Snapshot::from_bytes(&snapshot().to_bytes()).unwrap();
+    let expected = run(6);
+    assert_eq!([1, 0, 1, 0], expected.last().unwrap().1);
+
+    restore(&saved);
+    assert_eq!(expected, run(6));
+}